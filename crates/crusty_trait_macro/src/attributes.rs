@@ -0,0 +1,144 @@
+use proc_macro2::TokenStream;
+use syn::{Attribute, Ident, ItemTrait, LitInt, TraitItem, parse::Parser};
+
+/// The name of the helper attribute placed on trait methods, e.g. `#[crusty(since = 2)]`.
+const CRUSTY_ATTRIBUTE: &str = "crusty";
+
+/// Options parsed from the arguments of `#[crusty_trait(...)]`.
+#[derive(Default)]
+pub struct TraitAttrs {
+    /// Lays the vtable out by explicit method slots instead of declaration order.
+    pub stable_layout: bool,
+    /// The slots of the super traits in a `stable_layout` vtable, e.g.
+    /// `stable_layout(Named = 2, Clone = 3)`.
+    pub super_slots: Vec<(Ident, usize)>,
+}
+
+impl TraitAttrs {
+    pub fn parse(attrs: TokenStream) -> syn::Result<Self> {
+        let mut trait_attrs = TraitAttrs::default();
+        syn::meta::parser(|meta| {
+            if meta.path.is_ident("stable_layout") {
+                trait_attrs.stable_layout = true;
+                if meta.input.is_empty() || meta.input.peek(syn::Token![,]) {
+                    return Ok(());
+                }
+                meta.parse_nested_meta(|super_trait| {
+                    let ident = super_trait.path.require_ident()?.clone();
+                    let lit: LitInt = super_trait.value()?.parse()?;
+                    trait_attrs.super_slots.push((ident, lit.base10_parse()?));
+                    Ok(())
+                })
+            } else {
+                Err(meta.error("unsupported crusty_trait attribute, expected `stable_layout`"))
+            }
+        })
+        .parse2(attrs)?;
+        Ok(trait_attrs)
+    }
+}
+
+/// Options parsed from the `#[crusty(...)]` attributes of a trait method.
+#[derive(Default)]
+pub struct MethodAttrs {
+    /// The trait version the method was added in, appended after the base vtable slots.
+    pub since: Option<u32>,
+    /// The explicit position of the method in a `stable_layout` vtable.
+    pub slot: Option<usize>,
+}
+
+impl MethodAttrs {
+    pub fn from_attrs(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut method_attrs = MethodAttrs::default();
+        for attr in attrs.iter().filter(|attr| is_crusty_attr(attr)) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("since") {
+                    let lit: LitInt = meta.value()?.parse()?;
+                    method_attrs.since = Some(lit.base10_parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("slot") {
+                    let lit: LitInt = meta.value()?.parse()?;
+                    method_attrs.slot = Some(lit.base10_parse()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported crusty attribute, expected `since` or `slot`"))
+                }
+            })?;
+        }
+        Ok(method_attrs)
+    }
+}
+
+pub fn is_crusty_attr(attr: &Attribute) -> bool {
+    attr.path().is_ident(CRUSTY_ATTRIBUTE)
+}
+
+/// Returns the attributes without any `#[crusty(...)]` helper attributes.
+pub fn without_crusty_attrs(attrs: &[Attribute]) -> Vec<Attribute> {
+    attrs
+        .iter()
+        .filter(|attr| !is_crusty_attr(attr))
+        .cloned()
+        .collect()
+}
+
+/// Removes the `#[crusty(...)]` helper attributes from the trait so it can be emitted as is.
+pub fn strip_crusty_attrs(input: &mut ItemTrait) {
+    input.items.iter_mut().for_each(|item| {
+        if let TraitItem::Fn(fn_item) = item {
+            fn_item.attrs = without_crusty_attrs(&fn_item.attrs);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn test_method_attrs() {
+        let attrs: Vec<Attribute> = vec![
+            parse_quote!(#[doc = "A method"]),
+            parse_quote!(#[crusty(since = 3, slot = 1)]),
+        ];
+        let method_attrs = MethodAttrs::from_attrs(&attrs).unwrap();
+        assert_eq!(method_attrs.since, Some(3));
+        assert_eq!(method_attrs.slot, Some(1));
+        assert_eq!(without_crusty_attrs(&attrs), vec![attrs[0].clone()]);
+    }
+
+    #[test]
+    fn test_trait_attrs() {
+        assert!(!TraitAttrs::parse(TokenStream::new()).unwrap().stable_layout);
+        assert!(
+            TraitAttrs::parse(quote::quote!(stable_layout))
+                .unwrap()
+                .stable_layout
+        );
+        assert!(TraitAttrs::parse(quote::quote!(unstable_layout)).is_err());
+    }
+
+    #[test]
+    fn test_trait_attrs_super_slots() {
+        let trait_attrs =
+            TraitAttrs::parse(quote::quote!(stable_layout(Named = 2, Clone = 3))).unwrap();
+        assert!(trait_attrs.stable_layout);
+        let super_slots = trait_attrs
+            .super_slots
+            .iter()
+            .map(|(ident, slot)| (ident.to_string(), *slot))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            super_slots,
+            [("Named".to_string(), 2), ("Clone".to_string(), 3)]
+        );
+        assert!(TraitAttrs::parse(quote::quote!(stable_layout(Named))).is_err());
+    }
+
+    #[test]
+    fn test_method_attrs_unknown_key() {
+        let attrs: Vec<Attribute> = vec![parse_quote!(#[crusty(until = 3)])];
+        assert!(MethodAttrs::from_attrs(&attrs).is_err());
+    }
+}
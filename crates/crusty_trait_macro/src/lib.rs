@@ -1,10 +1,16 @@
 //! macro to impl the crusty traits.
 
 use proc_macro2::TokenStream;
+use quote::quote_spanned;
+use std_traits::ALLOC_SLOT_TRAITS;
 use super_trait::get_super_traits;
+use syn::{Ident, parse_quote};
 use vtable::create_vtable;
 
+mod attributes;
 mod cdrop;
+mod lowering;
+mod std_traits;
 mod super_trait;
 mod trait_for_cref;
 mod utils;
@@ -20,19 +26,51 @@ fn error_file(msg: TokenStream) -> syn::File {
     }
 }
 
+/// Returns the errors for the methods and super traits that need the `alloc` feature, which are
+/// only emitted when `crusty_traits_core` is built without it.
+fn alloc_errors(input: &syn::ItemTrait, slot_traits: &[Ident]) -> Option<syn::Item> {
+    let methods = input.items.iter().filter_map(|item| match item {
+        syn::TraitItem::Fn(method) if lowering::needs_alloc(&method.sig) => {
+            Some(quote_spanned! {method.sig.ident.span()=>
+                compile_error!("methods returning futures or iterators need the `alloc` feature");
+            })
+        }
+        _ => None,
+    });
+    let slot_traits = slot_traits
+        .iter()
+        .filter(|slot_trait| ALLOC_SLOT_TRAITS.contains(&slot_trait.to_string().as_str()))
+        .map(|slot_trait| {
+            let msg = format!("`{slot_trait}` super traits need the `alloc` feature");
+            quote_spanned! {slot_trait.span()=> compile_error!(#msg); }
+        });
+    let errors = methods.chain(slot_traits).collect::<Vec<_>>();
+    (!errors.is_empty()).then(|| parse_quote!(crusty_alloc! { {} else { #(#errors)* } }))
+}
+
 /// Generate the crusty trait and its vtable.
-pub fn impl_crusty_trait(input: syn::ItemTrait) -> syn::File {
+pub fn impl_crusty_trait(attrs: TokenStream, mut input: syn::ItemTrait) -> syn::File {
     let mut output = syn::File {
         shebang: None,
         attrs: vec![],
         items: vec![],
     };
 
+    let trait_attrs = match attributes::TraitAttrs::parse(attrs) {
+        Ok(t) => t,
+        Err(e) => return error_file(e.to_compile_error()),
+    };
+
     let super_traits = match get_super_traits(&input) {
         Ok(s) => s,
         Err(e) => return error_file(e.to_compile_error()),
     };
-    let vtable = match create_vtable(&input, &super_traits.super_traits) {
+    let vtable = match create_vtable(
+        &input,
+        &trait_attrs,
+        &super_traits.super_traits,
+        &super_traits.slot_traits,
+    ) {
         Ok(v) => v,
         Err(e) => return error_file(e.to_compile_error()),
     };
@@ -41,24 +79,76 @@ pub fn impl_crusty_trait(input: syn::ItemTrait) -> syn::File {
             .collect::<Vec<_>>();
 
     let cdrop_impl = cdrop::impl_cdrop_for_vtable(&vtable);
+    let marker_trait_impls = std_traits::impl_marker_traits(&super_traits.ignore_bounds, &vtable);
+    let slot_trait_impls = std_traits::impl_slot_traits(&super_traits.slot_traits, &vtable);
+    let static_vtable =
+        vtable::methods::impl_static_vtable(&input, &vtable, &super_traits.slot_traits);
     let vtable_methods = vtable::methods::impl_vtable_methods(&input, &vtable);
-    let trait_for_cref = trait_for_cref::impl_trait_for_c_ref(&input, &vtable);
-    let trait_for_cref_where_as_vtable = trait_for_cref::impl_trait_for_c_ref_where_as_vtable(
+    let trait_for_cref = match trait_for_cref::impl_trait_for_c_ref(&input, &vtable) {
+        Ok(i) => i,
+        Err(e) => return error_file(e.to_compile_error()),
+    };
+    let trait_for_cref_where_as_vtable = match trait_for_cref::impl_trait_for_c_ref_where_as_vtable(
         &input,
         &vtable,
         &super_traits.super_traits,
         &super_traits.ignore_bounds,
-    );
+        &super_traits.slot_traits,
+    ) {
+        Ok(i) => i,
+        Err(e) => return error_file(e.to_compile_error()),
+    };
 
+    let repr_impls = [&trait_for_cref, &trait_for_cref_where_as_vtable];
+    let inline_impls = repr_impls.map(trait_for_cref::impl_for_c_repr_inline);
+    let read_only_ident = trait_for_cref::read_only_trait_ident(&input);
+    let c_ref_mut_impls = repr_impls
+        .map(|item| trait_for_cref::impl_for_wrapper(item, "CRefMut", true, &input, None));
+    let c_ref_impls = repr_impls.map(|item| {
+        trait_for_cref::impl_for_wrapper(item, "CRef", true, &input, read_only_ident.as_ref())
+    });
+    let scoped_impls = repr_impls
+        .map(|item| trait_for_cref::impl_for_wrapper(item, "CReprScoped", true, &input, None));
+    // `CArc` lives in a reference counted allocation
+    let arc_impls = repr_impls.map(|item| {
+        let arc_impl =
+            trait_for_cref::impl_for_wrapper(item, "CArc", false, &input, read_only_ident.as_ref());
+        syn::Item::Macro(parse_quote!(crusty_alloc! { #arc_impl }))
+    });
+    let alloc_errors = alloc_errors(&input, &super_traits.slot_traits);
+    let read_only_trait =
+        read_only_ident.map(|ident| trait_for_cref::create_read_only_trait(&input, ident));
+
+    attributes::strip_crusty_attrs(&mut input);
     output.items.push(input.into());
     output.items.push(vtable.into());
     output.items.extend(as_vtable_impls);
     output.items.push(cdrop_impl);
+    output.items.extend(marker_trait_impls);
+    output.items.extend(slot_trait_impls);
+    output.items.push(static_vtable);
     output.items.push(vtable_methods);
     output.items.push(syn::Item::Impl(trait_for_cref));
     output
         .items
         .push(syn::Item::Impl(trait_for_cref_where_as_vtable));
+    output
+        .items
+        .extend(inline_impls.into_iter().map(syn::Item::Impl));
+    output
+        .items
+        .extend(read_only_trait.into_iter().map(syn::Item::Trait));
+    output
+        .items
+        .extend(c_ref_mut_impls.into_iter().map(syn::Item::Impl));
+    output
+        .items
+        .extend(c_ref_impls.into_iter().map(syn::Item::Impl));
+    output
+        .items
+        .extend(scoped_impls.into_iter().map(syn::Item::Impl));
+    output.items.extend(arc_impls);
+    output.items.extend(alloc_errors);
 
     output
 }
@@ -78,16 +168,18 @@ mod tests {
                 fn another_method(&mut self, y: String);
             }
         };
-        let output = impl_crusty_trait(input.clone());
+        let output = impl_crusty_trait(TokenStream::new(), input.clone());
         assert_eq!(output.items[0], syn::Item::Trait(input));
 
         let expected_vtable: syn::ItemStruct = parse_quote! {
             #[repr(C)]
             #[doc = "A repr C vtable for the trait MyTrait"]
             pub struct MyTraitVTable {
+                #[doc = "The size of the vtable in bytes, used to detect methods added in later versions"]
+                pub vtable_size: usize,
                 pub my_method: unsafe extern "C" fn(CRef<MyTraitVTable>, i32) -> i32,
                 pub another_method: unsafe extern "C" fn(CRefMut<MyTraitVTable>, String),
-                #[doc = "A function pointer to the drop function for the trait"]
+                #[doc = "Drops the object in place, its memory is freed afterwards by the `dealloc` of its owner"]
                 pub drop: unsafe extern "C" fn(CRefMut<MyTraitVTable>),
             }
         };
@@ -110,59 +202,108 @@ mod tests {
         };
         assert_eq!(output.items[2], syn::Item::Impl(expected_impl.clone()));
 
-        let expected_methods: syn::ItemImpl = parse_quote! {
-        impl MyTraitVTable {
-            /// Creates a new vtable for the type GEN that implements the trait
-            pub fn new_boxed<GEN: MyTrait + 'static>(input: GEN) -> CRepr<MyTraitVTable> {
-                let vtable  = MyTraitVTable::create_vtable::<GEN>();
-                CRepr::new_boxed(vtable, input)
+        let expected_static_vtable: syn::ItemImpl = parse_quote! {
+            impl<GEN: MyTrait> StaticVTable<GEN> for MyTraitVTable {
+                const VTABLE: Self = MyTraitVTable {
+                    vtable_size: ::core::mem::size_of::<Self>(),
+                    my_method: {
+                        unsafe extern "C" fn my_method<GEN: MyTrait>(arg0: CRef<MyTraitVTable>, arg1: i32) -> i32 {
+                            #[allow(unsafe_code)]
+                            unsafe {
+                                GEN::my_method(&*arg0.as_ptr().cast::<GEN>(), arg1)
+                            }
+                        }
+                        my_method::<GEN>
+                    },
+                    another_method: {
+                        unsafe extern "C" fn another_method<GEN: MyTrait>(arg0: CRefMut<MyTraitVTable>, arg1: String) {
+                            #[allow(unsafe_code)]
+                            unsafe {
+                                GEN::another_method(&mut *arg0.as_mut_ptr().cast::<GEN>(), arg1)
+                            }
+                        }
+                        another_method::<GEN>
+                    },
+                    drop: {
+                        unsafe extern "C" fn drop<GEN: MyTrait>(arg_0: CRefMut<MyTraitVTable>) {
+                            #[allow(unsafe_code)]
+                            unsafe {
+                                arg_0.drop_in_place::<GEN>();
+                            }
+                        }
+                        drop::<GEN>
+                    },
+                };
             }
+        };
+        assert_eq!(
+            item_to_pretty_string(output.items[3].clone()),
+            item_to_pretty_string(syn::Item::Impl(expected_static_vtable))
+        );
+
+        let expected_methods: syn::ItemImpl = parse_quote! {
+            impl MyTraitVTable {
+                crusty_alloc! {
+                    /// Creates a new vtable for the type GEN that implements the trait
+                    pub fn new_boxed<GEN: MyTrait + 'static>(input: GEN) -> CRepr<MyTraitVTable> {
+                        let vtable  = MyTraitVTable::create_vtable::<GEN>();
+                        CRepr::new_boxed(vtable, input)
+                    }
+
+                    /// Creates a new boxed object for the type GEN, which may borrow data for `'scope`
+                    pub fn new_scoped<'scope, GEN: MyTrait + 'scope>(input: GEN) -> CReprScoped<'scope, MyTraitVTable>
+                    where
+                        Self: StaticVTable<GEN>,
+                    {
+                        let vtable  = MyTraitVTable::create_vtable::<GEN>();
+                        CReprScoped::new_boxed(vtable, input)
+                    }
+                }
 
-            /// Creates a new vtable for the type GEN then store in a static variable in the heap
-            pub fn create_vtable<GEN: MyTrait + 'static>() -> &'static MyTraitVTable {
-                   static FN_MAP: std::sync::LazyLock<std::sync::Mutex<std::collections::HashMap<std::any::TypeId, &'static (dyn std::any::Any + Send + Sync)>>> =
-                        std::sync::LazyLock::new(|| std::sync::Mutex::new(std::collections::HashMap::new()));
-
-                    let type_id = std::any::TypeId::of::<GEN>();
-
-                    let mut map = FN_MAP.lock().unwrap();
-                    let entry = map.entry(type_id).or_insert_with(|| {
-                        let vtable = Box::new(MyTraitVTable {
-                            my_method: {
-                                unsafe extern "C" fn my_method<GEN: MyTrait>(arg0: CRef<MyTraitVTable>, arg1: i32) -> i32 {
-                                    #[allow(unsafe_code)]
-                                    unsafe {
-                                        GEN::my_method(&*(arg0.as_ptr() as *const GEN), arg1)
-                                    }
-                                }
-                                my_method::<GEN>
-                            },
-                            another_method: {
-                                unsafe extern "C" fn another_method<GEN: MyTrait>(arg0: CRefMut<MyTraitVTable>, arg1: String) {
-                                    #[allow(unsafe_code)]
-                                    unsafe {
-                                        GEN::another_method(&mut *(arg0.as_ptr() as *mut GEN), arg1)
-                                    }
-                                }
-                                another_method::<GEN>
-                            },
-                            drop: {
-                                unsafe extern "C" fn drop<GEN: MyTrait>(arg_0: CRefMut<MyTraitVTable>) {
-                                    #[allow(unsafe_code)]
-                                    unsafe {
-                                        ::core::mem::drop(Box::from_raw(arg_0.as_ptr() as *mut GEN));
-                                    }
-                                }
-                                drop::<GEN>
-                            },
-                        });
-                        Box::leak(vtable)
-                    });
-                    entry.downcast_ref().unwrap()
+                /// Creates a new object for the type GEN with its context allocated by `allocator`
+                pub fn new_in<GEN: MyTrait + 'static, A: Allocator + 'static>(allocator: A, input: GEN) -> Result<CRepr<MyTraitVTable>, GEN> {
+                    let vtable = MyTraitVTable::create_vtable::<GEN>();
+                    CRepr::new_in(vtable, allocator, input)
+                }
+
+                /// Creates a new object for the type GEN with its context written into `buffer`
+                /// # Safety
+                /// `buffer` must be valid for reads and writes of `layout` until the object is dropped
+                pub unsafe fn new_in_place<GEN: MyTrait + 'static>(buffer: ::core::ptr::NonNull<u8>, layout: ::core::alloc::Layout, input: GEN) -> Result<CRepr<MyTraitVTable>, GEN> {
+                    let vtable = MyTraitVTable::create_vtable::<GEN>();
+                    unsafe { CRepr::new_in_place(vtable, buffer, layout, input) }
+                }
+
+                /// Borrows `value` as a `CRef` to the trait object for as long as it is borrowed
+                pub fn borrow<GEN: MyTrait>(value: &GEN) -> CRef<'_, MyTraitVTable>
+                where
+                    Self: StaticVTable<GEN>,
+                {
+                    let vtable = MyTraitVTable::create_vtable::<GEN>();
+                    #[allow(unsafe_code)]
+                    unsafe { CRef::from_raw_parts(::core::ptr::NonNull::from(vtable), ::core::ptr::NonNull::from(value).cast()) }
+                }
+
+                /// Borrows `value` as a `CRefMut` to the trait object for as long as it is borrowed
+                pub fn borrow_mut<GEN: MyTrait>(value: &mut GEN) -> CRefMut<'_, MyTraitVTable>
+                where
+                    Self: StaticVTable<GEN>,
+                {
+                    let vtable = MyTraitVTable::create_vtable::<GEN>();
+                    #[allow(unsafe_code)]
+                    unsafe { CRefMut::from_raw_parts(::core::ptr::NonNull::from(vtable), ::core::ptr::NonNull::from(value).cast()) }
+                }
+
+                /// Returns the vtable for the type GEN, built at compile time and promoted to a static
+                pub const fn create_vtable<GEN: MyTrait>() -> &'static MyTraitVTable
+                where
+                    Self: StaticVTable<GEN>,
+                {
+                    &<Self as StaticVTable<GEN>>::VTABLE
                 }
             }
         };
-        assert_eq!(output.items[3], syn::Item::Impl(expected_methods.clone()));
+        assert_eq!(output.items[4], syn::Item::Impl(expected_methods.clone()));
 
         let expected_trait_for_cref: syn::ItemImpl = parse_quote! {
             impl MyTrait for CRepr<MyTraitVTable> {
@@ -181,7 +322,7 @@ mod tests {
             }
         };
         assert_eq!(
-            output.items[4],
+            output.items[5],
             syn::Item::Impl(expected_trait_for_cref.clone())
         );
 
@@ -196,7 +337,7 @@ mod tests {
                         unsafe {
                             (methods
                                 .my_method)(
-                                self.as_cref_with_methods(std::ptr::NonNull::from(methods)),
+                                self.as_cref_with_methods(::core::ptr::NonNull::from(methods)),
                                 x,
                             )
                         }
@@ -207,7 +348,7 @@ mod tests {
                         unsafe {
                             (methods
                                 .another_method)(
-                                self.as_cref_mut_with_methods(std::ptr::NonNull::from(methods)),
+                                self.as_cref_mut_with_methods(::core::ptr::NonNull::from(methods)),
                                 y,
                             )
                         }
@@ -215,12 +356,33 @@ mod tests {
                 }
         };
         assert_eq!(
-            item_to_pretty_string(output.items[5].clone()),
+            item_to_pretty_string(output.items[6].clone()),
             item_to_pretty_string(syn::Item::Impl(
                 expected_trait_for_cref_where_as_vtable.clone()
             )),
             "Generated impl for trait for CRepr with where clause does not match",
         );
+
+        let expected_trait_for_inline: syn::ItemImpl = parse_quote! {
+            impl<const INLINE_WORDS: usize> MyTrait for CReprInline<MyTraitVTable, INLINE_WORDS> {
+                fn my_method(&self, x: i32) -> i32 {
+                    #[allow(unsafe_code)]
+                    unsafe {
+                        (self.get_vtable().my_method)(self.as_cref(), x)
+                    }
+                }
+                fn another_method(&mut self, y: String) {
+                    #[allow(unsafe_code)]
+                    unsafe {
+                        (self.get_vtable().another_method)(self.as_cref_mut(), y)
+                    }
+                }
+            }
+        };
+        assert_eq!(
+            item_to_pretty_string(output.items[7].clone()),
+            item_to_pretty_string(syn::Item::Impl(expected_trait_for_inline)),
+        );
     }
 
     #[test]
@@ -231,7 +393,7 @@ mod tests {
             }
         };
 
-        let output = impl_crusty_trait(input.clone());
+        let output = impl_crusty_trait(TokenStream::new(), input.clone());
 
         assert_eq!(output.items[0], syn::Item::Trait(input));
 
@@ -0,0 +1,386 @@
+//! Lowering of return types that can't cross the vtable as they are, futures and iterators, to
+//! `CFuture` and `CIterator` objects.
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    FnArg, GenericArgument, Lifetime, PathArguments, ReturnType, Signature, Type, TypeParamBound,
+    parse_quote, punctuated::Punctuated, token::Plus,
+};
+
+/// Returns the associated type `assoc` of the first bound named `trait_name`.
+fn bound_assoc_type(
+    bounds: &Punctuated<TypeParamBound, Plus>,
+    trait_name: &str,
+    assoc: &str,
+) -> Option<Type> {
+    bounds.iter().find_map(|bound| {
+        let TypeParamBound::Trait(bound) = bound else {
+            return None;
+        };
+        let segment = bound.path.segments.last()?;
+        let PathArguments::AngleBracketed(args) = &segment.arguments else {
+            return None;
+        };
+        if segment.ident != trait_name {
+            return None;
+        }
+        args.args.iter().find_map(|arg| match arg {
+            GenericArgument::AssocType(assoc_ty) if assoc_ty.ident == assoc => {
+                Some(assoc_ty.ty.clone())
+            }
+            _ => None,
+        })
+    })
+}
+
+/// Returns what the future returned by an `async fn`, or a method returning
+/// `impl Future<Output = T>`, resolves to.
+pub fn future_output(sig: &Signature) -> Option<Type> {
+    if sig.asyncness.is_some() {
+        return Some(match &sig.output {
+            ReturnType::Default => parse_quote!(()),
+            ReturnType::Type(_, ty) => ty.as_ref().clone(),
+        });
+    }
+    let ReturnType::Type(_, ty) = &sig.output else {
+        return None;
+    };
+    let Type::ImplTrait(impl_trait) = ty.as_ref() else {
+        return None;
+    };
+    bound_assoc_type(&impl_trait.bounds, "Future", "Output")
+}
+
+/// An iterator returned by a method.
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub enum ReturnedIterator {
+    /// `impl Iterator<Item = T>`, which may borrow the object the method was called on.
+    Impl(Type),
+    /// `Box<dyn Iterator<Item = T> + 'a>`, valid for the lifetime of the trait object, which is
+    /// `'static` or elided.
+    Boxed(Type, Lifetime),
+}
+
+/// Returns the iterator returned by a method, if any.
+pub fn returned_iterator(sig: &Signature) -> Option<ReturnedIterator> {
+    if sig.asyncness.is_some() {
+        return None;
+    }
+    let ReturnType::Type(_, ty) = &sig.output else {
+        return None;
+    };
+    match ty.as_ref() {
+        Type::ImplTrait(impl_trait) => {
+            bound_assoc_type(&impl_trait.bounds, "Iterator", "Item").map(ReturnedIterator::Impl)
+        }
+        Type::Path(path) => {
+            let segment = path.path.segments.last()?;
+            let PathArguments::AngleBracketed(args) = &segment.arguments else {
+                return None;
+            };
+            let Some(GenericArgument::Type(Type::TraitObject(object))) = args.args.first() else {
+                return None;
+            };
+            if segment.ident != "Box" || args.args.len() != 1 {
+                return None;
+            }
+            let item = bound_assoc_type(&object.bounds, "Iterator", "Item")?;
+            let lifetime = object
+                .bounds
+                .iter()
+                .find_map(|bound| match bound {
+                    // the vtable's fn pointer doesn't declare the method's lifetimes, so a named
+                    // lifetime is elided to the one of the object the method was called on, which
+                    // `check_method` makes the only lifetime of the inputs
+                    TypeParamBound::Lifetime(lifetime) if lifetime.ident != "static" => {
+                        Some(parse_quote!('_))
+                    }
+                    TypeParamBound::Lifetime(lifetime) => Some(lifetime.clone()),
+                    _ => None,
+                })
+                .unwrap_or_else(|| parse_quote!('static));
+            Some(ReturnedIterator::Boxed(item, lifetime))
+        }
+        _ => None,
+    }
+}
+
+/// Returns whether the shim of a method boxes its output in a `'static` vtable, which needs
+/// `'static` generics.
+pub fn needs_static_generics(sig: &Signature) -> bool {
+    future_output(sig).is_some() || returned_iterator(sig).is_some()
+}
+
+/// Returns the output of a method in the vtable, where futures are lowered to a `CFuture` object
+/// borrowing the object the method was called on, and iterators to a `CIterator` object.
+pub fn lowered_output(sig: &Signature) -> ReturnType {
+    if let Some(output) = future_output(sig) {
+        return parse_quote!(-> CReprScoped<'_, CFutureVTable<#output>>);
+    }
+    match returned_iterator(sig) {
+        Some(ReturnedIterator::Impl(item)) => {
+            parse_quote!(-> CReprScoped<'_, CIteratorVTable<#item>>)
+        }
+        Some(ReturnedIterator::Boxed(item, lifetime)) => {
+            parse_quote!(-> CReprScoped<#lifetime, CIteratorVTable<#item>>)
+        }
+        None => sig.output.clone(),
+    }
+}
+
+/// Returns whether the method returns a future or an iterator, which are boxed and so need the
+/// `alloc` feature.
+pub fn needs_alloc(sig: &Signature) -> bool {
+    future_output(sig).is_some() || returned_iterator(sig).is_some()
+}
+
+/// Returns whether the generic arguments of a path have a lifetime.
+fn arguments_have_lifetime(arguments: &PathArguments) -> bool {
+    match arguments {
+        PathArguments::None => false,
+        PathArguments::AngleBracketed(args) => args.args.iter().any(|arg| match arg {
+            GenericArgument::Lifetime(_) => true,
+            GenericArgument::Type(ty) => has_lifetime(ty),
+            GenericArgument::AssocType(assoc) => has_lifetime(&assoc.ty),
+            _ => false,
+        }),
+        PathArguments::Parenthesized(args) => {
+            args.inputs.iter().any(has_lifetime)
+                || matches!(&args.output, ReturnType::Type(_, ty) if has_lifetime(ty))
+        }
+    }
+}
+
+/// Returns whether a type is a reference or has a lifetime, `'static` included. Lifetimes hidden
+/// in a path, as in `CRef<MyVTable>`, can't be seen here and are left to the compiler.
+fn has_lifetime(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(_) => true,
+        Type::Path(path) => {
+            path.qself
+                .as_ref()
+                .is_some_and(|qself| has_lifetime(&qself.ty))
+                || path
+                    .path
+                    .segments
+                    .iter()
+                    .any(|segment| arguments_have_lifetime(&segment.arguments))
+        }
+        Type::TraitObject(object) => object.bounds.iter().any(|bound| match bound {
+            TypeParamBound::Lifetime(_) => true,
+            TypeParamBound::Trait(bound) => bound
+                .path
+                .segments
+                .iter()
+                .any(|segment| arguments_have_lifetime(&segment.arguments)),
+            _ => false,
+        }),
+        Type::Tuple(tuple) => tuple.elems.iter().any(has_lifetime),
+        Type::Array(array) => has_lifetime(&array.elem),
+        Type::Slice(slice) => has_lifetime(&slice.elem),
+        Type::Paren(paren) => has_lifetime(&paren.elem),
+        Type::Group(group) => has_lifetime(&group.elem),
+        _ => false,
+    }
+}
+
+/// Checks that a method returning a future or an iterator can be lowered to a `CFuture` or
+/// `CIterator` object.
+pub fn check_method(sig: &Signature, since: Option<u32>) -> syn::Result<()> {
+    let iterator = returned_iterator(sig);
+    if future_output(sig).is_none() && iterator.is_none() {
+        return Ok(());
+    }
+    // the returned object borrows the object the method was called on, which is elided in the
+    // vtable's fn pointer, so no other argument may have a lifetime unless the object is `'static`
+    let is_static = match &iterator {
+        Some(ReturnedIterator::Boxed(_, lifetime)) => lifetime.ident == "static",
+        _ => false,
+    };
+    if !is_static {
+        let borrowed = sig.inputs.iter().find_map(|input| match input {
+            FnArg::Typed(arg) if has_lifetime(&arg.ty) => Some(arg),
+            _ => None,
+        });
+        if let Some(arg) = borrowed {
+            return Err(syn::Error::new_spanned(
+                arg,
+                "the returned future or iterator can only borrow `self`, take this argument by value",
+            ));
+        }
+    }
+    if sig.asyncness.is_none() && since.is_some() {
+        if future_output(sig).is_some() {
+            return Err(syn::Error::new_spanned(
+                &sig.ident,
+                "methods with a `since` version must be `async fn` to return a future",
+            ));
+        }
+        if let Some(ReturnedIterator::Impl(_)) = iterator {
+            return Err(syn::Error::new_spanned(
+                &sig.ident,
+                "methods with a `since` version must return a `Box<dyn Iterator>`",
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Wraps the call of a generated shim, boxing the future or iterator returned by the method.
+pub fn lower_call(sig: &Signature, call: TokenStream) -> TokenStream {
+    if future_output(sig).is_some() {
+        return quote! {
+            CFutureVTable::new_scoped_future_unchecked(#call)
+        };
+    }
+    match returned_iterator(sig) {
+        Some(ReturnedIterator::Boxed(_, lifetime)) if lifetime.ident == "static" => quote! {
+            CIteratorVTable::new_scoped_iter(#call)
+        },
+        Some(_) => quote! {
+            CIteratorVTable::new_scoped_iter_unchecked(#call)
+        },
+        None => call,
+    }
+}
+
+/// Returns the call through the vtable in a wrapper's method, awaiting the `CFuture` object in an
+/// `async fn` and boxing the `CIterator` object of a method returning a `Box<dyn Iterator>`.
+pub fn vtable_call(sig: &Signature, call: TokenStream) -> TokenStream {
+    if sig.asyncness.is_some() {
+        return quote! {
+            #[allow(unsafe_code)]
+            let future = unsafe { #call };
+            future.await
+        };
+    }
+    if let Some(ReturnedIterator::Boxed(..)) = returned_iterator(sig) {
+        return quote! {
+            #[allow(unsafe_code)]
+            let iter = unsafe { #call };
+            Box::new(iter)
+        };
+    }
+    quote! {
+        #[allow(unsafe_code)]
+        unsafe {
+            #call
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_future_output() {
+        let sig: Signature = parse_quote!(async fn fetch(&self, id: u32) -> String);
+        assert_eq!(future_output(&sig), Some(parse_quote!(String)));
+        let sig: Signature = parse_quote!(async fn run(&mut self));
+        assert_eq!(future_output(&sig), Some(parse_quote!(())));
+        let sig: Signature =
+            parse_quote!(fn fetch(&self) -> impl core::future::Future<Output = u32> + 'static);
+        assert_eq!(future_output(&sig), Some(parse_quote!(u32)));
+        let sig: Signature = parse_quote!(fn fetch(&self) -> impl Iterator<Item = u32>);
+        assert_eq!(future_output(&sig), None);
+        let sig: Signature = parse_quote!(fn fetch(&self) -> u32);
+        assert_eq!(future_output(&sig), None);
+    }
+
+    #[test]
+    fn test_returned_iterator() {
+        let sig: Signature = parse_quote!(fn items(&self) -> impl Iterator<Item = u32> + '_);
+        assert_eq!(
+            returned_iterator(&sig),
+            Some(ReturnedIterator::Impl(parse_quote!(u32)))
+        );
+        let sig: Signature = parse_quote!(fn items(&self) -> Box<dyn Iterator<Item = String>>);
+        assert_eq!(
+            returned_iterator(&sig),
+            Some(ReturnedIterator::Boxed(
+                parse_quote!(String),
+                parse_quote!('static)
+            ))
+        );
+        let sig: Signature =
+            parse_quote!(fn items<'a>(&'a self) -> Box<dyn Iterator<Item = u8> + 'a>);
+        assert_eq!(
+            returned_iterator(&sig),
+            Some(ReturnedIterator::Boxed(parse_quote!(u8), parse_quote!('_)))
+        );
+        let sig: Signature = parse_quote!(fn items(&self) -> Box<dyn Fn() -> u32>);
+        assert_eq!(returned_iterator(&sig), None);
+        let sig: Signature = parse_quote!(fn items(&self) -> Vec<u32>);
+        assert_eq!(returned_iterator(&sig), None);
+    }
+
+    #[test]
+    fn test_lowered_output() {
+        let sig: Signature = parse_quote!(async fn fetch(&self) -> String);
+        let expected: ReturnType = parse_quote!(-> CReprScoped<'_, CFutureVTable<String>>);
+        assert_eq!(lowered_output(&sig), expected);
+        let sig: Signature = parse_quote!(fn items(&self) -> impl Iterator<Item = u32>);
+        let expected: ReturnType = parse_quote!(-> CReprScoped<'_, CIteratorVTable<u32>>);
+        assert_eq!(lowered_output(&sig), expected);
+        let sig: Signature = parse_quote!(fn items(&self) -> Box<dyn Iterator<Item = u32>>);
+        let expected: ReturnType = parse_quote!(-> CReprScoped<'static, CIteratorVTable<u32>>);
+        assert_eq!(lowered_output(&sig), expected);
+        let sig: Signature =
+            parse_quote!(fn items<'a>(&'a self) -> Box<dyn Iterator<Item = u32> + 'a>);
+        let expected: ReturnType = parse_quote!(-> CReprScoped<'_, CIteratorVTable<u32>>);
+        assert_eq!(lowered_output(&sig), expected);
+    }
+
+    #[test]
+    fn test_check_method() {
+        let sig: Signature = parse_quote!(fn fetch(&self) -> impl Future<Output = u32>);
+        assert!(check_method(&sig, Some(1)).is_err());
+        let sig: Signature = parse_quote!(async fn fetch(&self) -> u32);
+        assert!(check_method(&sig, Some(1)).is_ok());
+        let sig: Signature = parse_quote!(fn items(&self) -> impl Iterator<Item = u32>);
+        assert!(check_method(&sig, Some(1)).is_err());
+        let sig: Signature = parse_quote!(fn items(&self) -> Box<dyn Iterator<Item = u32>>);
+        assert!(check_method(&sig, Some(1)).is_ok());
+    }
+
+    #[test]
+    fn test_check_method_borrowed_arguments() {
+        let sig: Signature = parse_quote!(async fn fetch(&self, key: &str) -> usize);
+        assert!(check_method(&sig, None).is_err());
+        let sig: Signature = parse_quote!(async fn fetch(&self, keys: Vec<&str>) -> usize);
+        assert!(check_method(&sig, None).is_err());
+        let sig: Signature =
+            parse_quote!(fn fetch<'a>(&'a self, key: (u8, &'a str)) -> impl Future<Output = u8>);
+        assert!(check_method(&sig, None).is_err());
+        let sig: Signature = parse_quote!(async fn merge(&self, other: CRef<'_, MyVTable>));
+        assert!(check_method(&sig, None).is_err());
+        let sig: Signature = parse_quote!(async fn fetch(&self, key: &'static str) -> usize);
+        assert!(check_method(&sig, None).is_err());
+
+        let sig: Signature = parse_quote!(async fn fetch(&mut self, key: String) -> usize);
+        assert!(check_method(&sig, None).is_ok());
+        let sig: Signature = parse_quote!(fn len(&self, key: &str) -> usize);
+        assert!(check_method(&sig, None).is_ok());
+    }
+
+    #[test]
+    fn test_check_method_borrowed_iterator_arguments() {
+        let sig: Signature = parse_quote!(fn items(&self, key: &str) -> impl Iterator<Item = u8>);
+        assert!(check_method(&sig, None).is_err());
+        let sig: Signature = parse_quote!(
+            fn items<'a>(&'a self, key: &'a str) -> Box<dyn Iterator<Item = u8> + 'a>
+        );
+        assert!(check_method(&sig, None).is_err());
+        let sig: Signature =
+            parse_quote!(fn items(&self, keys: &[u8]) -> Box<dyn Iterator<Item = u8> + '_>);
+        assert!(check_method(&sig, None).is_err());
+
+        // a `'static` iterator doesn't borrow, so its arguments may
+        let sig: Signature =
+            parse_quote!(fn items(&self, key: &str) -> Box<dyn Iterator<Item = u8>>);
+        assert!(check_method(&sig, None).is_ok());
+        let sig: Signature = parse_quote!(fn items(&self, key: u8) -> impl Iterator<Item = u8>);
+        assert!(check_method(&sig, None).is_ok());
+    }
+}
@@ -0,0 +1,449 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Field, Generics, Ident, ItemStruct, parse_quote};
+
+/// Std super traits that are lowered to slots of the vtable instead of a nested vtable.
+///
+/// The slots are laid out in this order, whatever the order of the super traits.
+pub(crate) const SLOT_TRAITS: [&str; 8] = [
+    "Clone",
+    "Debug",
+    "Display",
+    "PartialEq",
+    "Eq",
+    "PartialOrd",
+    "Ord",
+    "Hash",
+];
+
+/// The slot traits whose slot boxes a copy of the object.
+pub(crate) const ALLOC_SLOT_TRAITS: [&str; 1] = ["Clone"];
+
+/// Adds the slot traits implied by the given ones, such as `PartialEq` for `Eq`, and sorts them
+/// in the order of [`SLOT_TRAITS`].
+pub fn with_implied_traits(slot_traits: Vec<Ident>) -> Vec<Ident> {
+    let mut names = slot_traits.iter().map(Ident::to_string).collect::<Vec<_>>();
+    for (slot_trait, implied) in [
+        ("Ord", ["Eq", "PartialOrd"].as_slice()),
+        ("PartialOrd", &["PartialEq"]),
+        ("Eq", &["PartialEq"]),
+    ] {
+        if names.iter().any(|name| name == slot_trait) {
+            names.extend(implied.iter().map(ToString::to_string));
+        }
+    }
+
+    SLOT_TRAITS
+        .iter()
+        .filter(|name| names.iter().any(|n| n == *name))
+        .map(|name| {
+            slot_traits
+                .iter()
+                .find(|ident| ident == name)
+                .cloned()
+                .unwrap_or_else(|| Ident::new(name, proc_macro2::Span::call_site()))
+        })
+        .collect()
+}
+
+/// Returns the vtable fields for the slot traits, named after the method they forward.
+pub fn slot_fields(slot_traits: &[Ident], vtable_ty: &TokenStream) -> Vec<Field> {
+    slot_traits
+        .iter()
+        .flat_map(|slot_trait| match slot_trait.to_string().as_str() {
+            "Clone" => vec![parse_quote!(
+                #[doc = "Clones the object into a new boxed context"]
+                pub clone: unsafe extern "C" fn(CRef<#vtable_ty>) -> CReprScoped<'_, #vtable_ty>
+            )],
+            "Debug" => vec![parse_quote!(
+                #[doc = "Writes the `Debug` text of the object, returning `false` on error"]
+                pub debug: unsafe extern "C" fn(CRef<#vtable_ty>, CFormatter<'_>) -> bool
+            )],
+            "Display" => vec![parse_quote!(
+                #[doc = "Writes the `Display` text of the object, returning `false` on error"]
+                pub display: unsafe extern "C" fn(CRef<#vtable_ty>, CFormatter<'_>) -> bool
+            )],
+            "PartialEq" => vec![
+                parse_quote!(
+                    #[doc = "Returns an identifier unique to the implementation, which objects must share to be compared"]
+                    pub implementation: unsafe extern "C" fn() -> CImplementationId
+                ),
+                parse_quote!(
+                    #[doc = "Compares two objects of the same implementation for equality"]
+                    pub eq: unsafe extern "C" fn(CRef<#vtable_ty>, CRef<#vtable_ty>) -> bool
+                ),
+            ],
+            // `Eq` has no methods, it only promises that `eq` is an equivalence relation
+            "Eq" => vec![],
+            "PartialOrd" => vec![parse_quote!(
+                #[doc = "Compares two objects of the same implementation, returning -1, 0 or 1, or any other value if they are unordered"]
+                pub partial_cmp: unsafe extern "C" fn(CRef<#vtable_ty>, CRef<#vtable_ty>) -> i8
+            )],
+            "Ord" => vec![parse_quote!(
+                #[doc = "Compares two objects of the same implementation, returning -1, 0 or 1"]
+                pub cmp: unsafe extern "C" fn(CRef<#vtable_ty>, CRef<#vtable_ty>) -> i8
+            )],
+            "Hash" => vec![parse_quote!(
+                #[doc = "Writes the bytes identifying the object into a `CHasher`"]
+                pub hash: unsafe extern "C" fn(CRef<#vtable_ty>, CHasher<'_>)
+            )],
+            _ => unreachable!("not a slot trait"),
+        })
+        .collect()
+}
+
+/// Returns the initializers of the slot fields in the static vtable of `GEN`.
+pub fn static_slots(
+    slot_traits: &[Ident],
+    vtable_ty: &TokenStream,
+    method_generics: &Generics,
+    method_generics_names: &[TokenStream],
+) -> Vec<TokenStream> {
+    let compare = |slot, output, call| {
+        static_compare_slot(
+            slot,
+            output,
+            call,
+            vtable_ty,
+            method_generics,
+            method_generics_names,
+        )
+    };
+
+    slot_traits
+        .iter()
+        .filter_map(|slot_trait| match slot_trait.to_string().as_str() {
+            "Clone" => Some(quote! {
+                clone: {
+                    unsafe extern "C" fn clone #method_generics(arg0: CRef<#vtable_ty>) -> CReprScoped<'_, #vtable_ty> {
+                        #[allow(unsafe_code)]
+                        unsafe {
+                            let context = <GEN as ::core::clone::Clone>::clone(&*arg0.as_ptr().cast::<GEN>());
+                            CRepr::new_boxed_unchecked(::core::ptr::NonNull::from(arg0.get_vtable()), context).into()
+                        }
+                    }
+                    clone::<#(#method_generics_names),*>
+                }
+            }),
+            "Debug" => Some(static_fmt_slot(
+                quote! { debug },
+                quote! { ::core::fmt::Debug },
+                (quote! { "{:?}" }, quote! { "{:#?}" }),
+                vtable_ty,
+                method_generics,
+                method_generics_names,
+            )),
+            "Display" => Some(static_fmt_slot(
+                quote! { display },
+                quote! { ::core::fmt::Display },
+                (quote! { "{}" }, quote! { "{:#}" }),
+                vtable_ty,
+                method_generics,
+                method_generics_names,
+            )),
+            "PartialEq" => {
+                let eq = compare(
+                    quote! { eq },
+                    quote! { bool },
+                    quote! { <GEN as ::core::cmp::PartialEq>::eq(a, b) },
+                );
+                Some(quote! {
+                    implementation: {
+                        unsafe extern "C" fn implementation #method_generics() -> CImplementationId
+                        where
+                            GEN: 'static,
+                        {
+                            implementation_id::<GEN>()
+                        }
+                        implementation::<#(#method_generics_names),*>
+                    },
+                    #eq
+                })
+            }
+            "Eq" => None,
+            "PartialOrd" => Some(compare(
+                quote! { partial_cmp },
+                quote! { i8 },
+                quote! {
+                    match <GEN as ::core::cmp::PartialOrd>::partial_cmp(a, b) {
+                        Some(ordering) => ordering as i8,
+                        None => i8::MAX,
+                    }
+                },
+            )),
+            "Ord" => Some(compare(
+                quote! { cmp },
+                quote! { i8 },
+                quote! { <GEN as ::core::cmp::Ord>::cmp(a, b) as i8 },
+            )),
+            "Hash" => Some(quote! {
+                hash: {
+                    unsafe extern "C" fn hash #method_generics(arg0: CRef<#vtable_ty>, mut arg1: CHasher<'_>) {
+                        #[allow(unsafe_code)]
+                        unsafe {
+                            <GEN as ::core::hash::Hash>::hash(&*arg0.as_ptr().cast::<GEN>(), &mut arg1);
+                        }
+                    }
+                    hash::<#(#method_generics_names),*>
+                }
+            }),
+            _ => unreachable!("not a slot trait"),
+        })
+        .collect()
+}
+
+/// Returns the initializer of a slot comparing two objects, as `a` and `b`, of the same `GEN`.
+fn static_compare_slot(
+    slot: TokenStream,
+    output: TokenStream,
+    call: TokenStream,
+    vtable_ty: &TokenStream,
+    method_generics: &Generics,
+    method_generics_names: &[TokenStream],
+) -> TokenStream {
+    quote! {
+        #slot: {
+            unsafe extern "C" fn #slot #method_generics(arg0: CRef<#vtable_ty>, arg1: CRef<#vtable_ty>) -> #output {
+                #[allow(unsafe_code)]
+                let (a, b) = unsafe { (&*arg0.as_ptr().cast::<GEN>(), &*arg1.as_ptr().cast::<GEN>()) };
+                #call
+            }
+            #slot::<#(#method_generics_names),*>
+        }
+    }
+}
+
+/// Returns the initializer of a formatting slot, which formats `GEN` into the `CFormatter`.
+fn static_fmt_slot(
+    slot: TokenStream,
+    fmt_trait: TokenStream,
+    (format, alternate_format): (TokenStream, TokenStream),
+    vtable_ty: &TokenStream,
+    method_generics: &Generics,
+    method_generics_names: &[TokenStream],
+) -> TokenStream {
+    quote! {
+        #slot: {
+            unsafe extern "C" fn #slot #method_generics(arg0: CRef<#vtable_ty>, mut arg1: CFormatter<'_>) -> bool {
+                #[allow(unsafe_code)]
+                let value: &dyn #fmt_trait = unsafe { &*arg0.as_ptr().cast::<GEN>() };
+                if arg1.alternate {
+                    ::core::fmt::Write::write_fmt(&mut arg1, format_args!(#alternate_format, value)).is_ok()
+                } else {
+                    ::core::fmt::Write::write_fmt(&mut arg1, format_args!(#format, value)).is_ok()
+                }
+            }
+            #slot::<#(#method_generics_names),*>
+        }
+    }
+}
+
+/// Returns the core traits implemented for the vtable, which forward to the slots.
+pub fn impl_slot_traits(slot_traits: &[Ident], vtable: &ItemStruct) -> Vec<syn::Item> {
+    let name = &vtable.ident;
+    let mut generics = vtable.generics.clone();
+    generics.params.iter_mut().for_each(|param| {
+        if let syn::GenericParam::Type(type_param) = param {
+            type_param.bounds.clear();
+        }
+    });
+
+    slot_traits
+        .iter()
+        .map(|slot_trait| match slot_trait.to_string().as_str() {
+            "Clone" => parse_quote! {
+                unsafe impl #generics CClone for #name #generics {
+                    fn clone<'a>(repr: CRef<'a, Self>) -> CReprScoped<'a, Self> {
+                        unsafe { (repr.get_vtable().clone)(repr) }
+                    }
+                }
+            },
+            "Debug" => impl_fmt_trait(quote! { CDebug }, quote! { debug }, name, &generics),
+            "Display" => impl_fmt_trait(quote! { CDisplay }, quote! { display }, name, &generics),
+            "PartialEq" => parse_quote! {
+                impl #generics CPartialEq for #name #generics {
+                    fn implementation(repr: CRef<Self>) -> CImplementationId {
+                        unsafe { (repr.get_vtable().implementation)() }
+                    }
+
+                    unsafe fn eq(a: CRef<Self>, b: CRef<Self>) -> bool {
+                        unsafe { (a.get_vtable().eq)(a, b) }
+                    }
+                }
+            },
+            "Eq" => parse_quote! {
+                impl #generics CEq for #name #generics {}
+            },
+            "PartialOrd" => parse_quote! {
+                impl #generics CPartialOrd for #name #generics {
+                    unsafe fn partial_cmp(a: CRef<Self>, b: CRef<Self>) -> Option<::core::cmp::Ordering> {
+                        match unsafe { (a.get_vtable().partial_cmp)(a, b) } {
+                            -1 => Some(::core::cmp::Ordering::Less),
+                            0 => Some(::core::cmp::Ordering::Equal),
+                            1 => Some(::core::cmp::Ordering::Greater),
+                            _ => None,
+                        }
+                    }
+                }
+            },
+            "Ord" => parse_quote! {
+                impl #generics COrd for #name #generics {
+                    unsafe fn cmp(a: CRef<Self>, b: CRef<Self>) -> ::core::cmp::Ordering {
+                        unsafe { (a.get_vtable().cmp)(a, b) }.cmp(&0)
+                    }
+                }
+            },
+            "Hash" => parse_quote! {
+                impl #generics CHash for #name #generics {
+                    fn hash<H: ::core::hash::Hasher>(repr: CRef<Self>, state: &mut H) {
+                        unsafe { (repr.get_vtable().hash)(repr, CHasher::new(state)) }
+                    }
+                }
+            },
+            _ => unreachable!("not a slot trait"),
+        })
+        .collect()
+}
+
+/// Implements a core formatting trait by calling the slot with a `CFormatter` of `f`.
+fn impl_fmt_trait(
+    c_trait: TokenStream,
+    slot: TokenStream,
+    name: &Ident,
+    generics: &Generics,
+) -> syn::Item {
+    parse_quote! {
+        impl #generics #c_trait for #name #generics {
+            fn fmt(repr: CRef<Self>, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                let written = unsafe { (repr.get_vtable().#slot)(repr, CFormatter::new(f)) };
+                written.then_some(()).ok_or(::core::fmt::Error)
+            }
+        }
+    }
+}
+
+/// Returns the core marker a vtable implements for a `Send` or `Sync` super trait.
+pub fn marker_trait(bound: &str) -> Option<Ident> {
+    match bound {
+        "Send" => Some(Ident::new("CSend", proc_macro2::Span::call_site())),
+        "Sync" => Some(Ident::new("CSync", proc_macro2::Span::call_site())),
+        _ => None,
+    }
+}
+
+/// Implements the core markers of the `Send` and `Sync` super traits for the vtable, which every
+/// implementer of the trait satisfies.
+pub fn impl_marker_traits(ignore_bounds: &[String], vtable: &ItemStruct) -> Vec<syn::Item> {
+    let name = &vtable.ident;
+    let mut generics = vtable.generics.clone();
+    generics.params.iter_mut().for_each(|param| {
+        if let syn::GenericParam::Type(type_param) = param {
+            type_param.bounds.clear();
+        }
+    });
+
+    ignore_bounds
+        .iter()
+        .filter_map(|bound| marker_trait(bound))
+        .map(|marker| {
+            parse_quote! {
+                unsafe impl #generics #marker for #name #generics {}
+            }
+        })
+        .collect()
+}
+
+/// Returns the bounds a vtable `GEN` needs for `CRepr<GEN>` to implement the slot traits.
+pub fn slot_bounds(slot_traits: &[Ident]) -> Vec<TokenStream> {
+    slot_traits
+        .iter()
+        .map(|slot_trait| match slot_trait.to_string().as_str() {
+            "Clone" => quote! { + CClone },
+            "Debug" => quote! { + CDebug },
+            "Display" => quote! { + CDisplay },
+            "PartialEq" => quote! { + CPartialEq },
+            "Eq" => quote! { + CEq },
+            "PartialOrd" => quote! { + CPartialOrd },
+            "Ord" => quote! { + COrd },
+            "Hash" => quote! { + CHash },
+            _ => unreachable!("not a slot trait"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_impl_slot_traits() {
+        let vtable: ItemStruct = parse_quote! {
+            pub struct MyTraitVTable<T: 'static> {
+                pub clone: unsafe extern "C" fn(CRef<MyTraitVTable<T>>) -> CReprScoped<'_, MyTraitVTable<T>>,
+            }
+        };
+        let slot_traits = [Ident::new("Clone", proc_macro2::Span::call_site())];
+
+        let expected: syn::Item = parse_quote! {
+            unsafe impl<T> CClone for MyTraitVTable<T> {
+                fn clone<'a>(repr: CRef<'a, Self>) -> CReprScoped<'a, Self> {
+                    unsafe { (repr.get_vtable().clone)(repr) }
+                }
+            }
+        };
+        assert_eq!(impl_slot_traits(&slot_traits, &vtable), vec![expected]);
+
+        let vtable_ty = quote! { MyTraitVTable<T> };
+        let expected: Field = parse_quote!(
+            #[doc = "Clones the object into a new boxed context"]
+            pub clone: unsafe extern "C" fn(CRef<MyTraitVTable<T>>) -> CReprScoped<'_, MyTraitVTable<T>>
+        );
+        assert_eq!(slot_fields(&slot_traits, &vtable_ty), vec![expected]);
+    }
+
+    #[test]
+    fn test_impl_fmt_slot_traits() {
+        let vtable: ItemStruct = parse_quote! {
+            pub struct MyTraitVTable {}
+        };
+        let slot_traits = [Ident::new("Debug", proc_macro2::Span::call_site())];
+
+        let expected: syn::Item = parse_quote! {
+            impl CDebug for MyTraitVTable {
+                fn fmt(repr: CRef<Self>, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                    let written = unsafe { (repr.get_vtable().debug)(repr, CFormatter::new(f)) };
+                    written.then_some(()).ok_or(::core::fmt::Error)
+                }
+            }
+        };
+        assert_eq!(impl_slot_traits(&slot_traits, &vtable), vec![expected]);
+        assert_eq!(
+            slot_bounds(&slot_traits)[0].to_string(),
+            quote! { + CDebug }.to_string()
+        );
+    }
+
+    #[test]
+    fn test_with_implied_traits() {
+        let slot_traits = ["Hash", "Ord", "Clone"]
+            .map(|name| Ident::new(name, proc_macro2::Span::call_site()))
+            .to_vec();
+        assert_eq!(
+            with_implied_traits(slot_traits),
+            ["Clone", "PartialEq", "Eq", "PartialOrd", "Ord", "Hash"]
+        );
+    }
+
+    #[test]
+    fn test_impl_marker_traits() {
+        let vtable: ItemStruct = parse_quote! {
+            pub struct MyTraitVTable<T> {}
+        };
+        let ignore_bounds = ["Sized", "Send"].map(String::from);
+
+        let expected: syn::Item = parse_quote! {
+            unsafe impl<T> CSend for MyTraitVTable<T> {}
+        };
+        assert_eq!(impl_marker_traits(&ignore_bounds, &vtable), vec![expected]);
+    }
+}
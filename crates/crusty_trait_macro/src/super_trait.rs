@@ -3,6 +3,7 @@ use syn::{Ident, ItemTrait, Type, parse_quote};
 
 use crate::{
     IGNORE_SUPER_TRAITS,
+    std_traits::{SLOT_TRAITS, with_implied_traits},
     utils::{map_field_ident, map_vtable_ident},
 };
 
@@ -20,10 +21,13 @@ pub type SuperTraits = Vec<SuperTrait>;
 pub struct SuperTraitReturn {
     pub super_traits: SuperTraits,
     pub ignore_bounds: Vec<String>,
+    /// Std traits lowered to slots of the vtable, see [`SLOT_TRAITS`].
+    pub slot_traits: Vec<Ident>,
 }
 
 pub fn get_super_traits(input: &ItemTrait) -> syn::Result<SuperTraitReturn> {
     let mut ignore_bounds = vec![];
+    let mut slot_traits = vec![];
     let mut errors = vec![];
     let super_traits = input
         .supertraits
@@ -41,6 +45,14 @@ pub fn get_super_traits(input: &ItemTrait) -> syn::Result<SuperTraitReturn> {
                     ignore_bounds.push(ident.to_string());
                     return None;
                 }
+                // std traits may be written with their path, like `fmt::Debug`
+                let std_ident = trait_bound.path.segments.last().map(|s| s.ident.clone());
+                if let Some(ident) = std_ident
+                    && SLOT_TRAITS.contains(&ident.to_string().as_str())
+                {
+                    slot_traits.push(ident);
+                    return None;
+                }
                 Some((trait_bound, ident))
             }
             _ => None,
@@ -54,14 +66,12 @@ pub fn get_super_traits(input: &ItemTrait) -> syn::Result<SuperTraitReturn> {
                 && let syn::PathArguments::AngleBracketed(angle_bracketed) = &segment.arguments
             {
                 for arg in &angle_bracketed.args {
-                    if let syn::GenericArgument::Type(ty) = arg {
-                        if let Type::Path(type_path) = ty {
-                            if let Some(ident) =
-                                type_path.path.segments.first().map(|s| s.ident.clone())
-                            {
-                                generics.push(ident);
-                            }
-                        }
+                    if let syn::GenericArgument::Type(ty) = arg
+                        && let Type::Path(type_path) = ty
+                        && let Some(ident) =
+                            type_path.path.segments.first().map(|s| s.ident.clone())
+                    {
+                        generics.push(ident);
                     }
                 }
             }
@@ -106,9 +116,9 @@ pub fn get_super_traits(input: &ItemTrait) -> syn::Result<SuperTraitReturn> {
         .fold(None, |acc: Option<syn::Error>, err: syn::Error| {
             if let Some(mut acc) = acc {
                 acc.combine(err.clone());
-                return Some(acc);
+                Some(acc)
             } else {
-                return Some(err.clone());
+                Some(err.clone())
             }
         });
 
@@ -119,6 +129,7 @@ pub fn get_super_traits(input: &ItemTrait) -> syn::Result<SuperTraitReturn> {
     Ok(SuperTraitReturn {
         super_traits,
         ignore_bounds,
+        slot_traits: with_implied_traits(slot_traits),
     })
 }
 
@@ -209,6 +220,21 @@ mod tests {
         assert_eq!(result.ignore_bounds.len(), 0);
     }
 
+    #[test]
+    fn test_get_super_traits_with_slot_trait() {
+        let item: ItemTrait = parse_quote! {
+            trait MyTrait: SuperTrait1 + Clone + core::fmt::Debug + Send {
+                fn my_method(&self);
+            }
+        };
+
+        let result = get_super_traits(&item).unwrap();
+        assert_eq!(result.super_traits.len(), 1);
+        assert_eq!(result.super_traits[0].ident, "SuperTrait1");
+        assert_eq!(result.slot_traits, vec!["Clone", "Debug"]);
+        assert_eq!(result.ignore_bounds, vec!["Send"]);
+    }
+
     #[test]
     fn test_with_super_trait_generic() {
         let item: ItemTrait = parse_quote! {
@@ -1,7 +1,38 @@
+use proc_macro2::TokenStream;
 use quote::{ToTokens, quote};
 use syn::{Ident, Type, parse_quote, spanned::Spanned};
 
-pub fn impl_trait_for_c_ref(input: &syn::ItemTrait, vtable: &syn::ItemStruct) -> syn::ItemImpl {
+use crate::attributes::{MethodAttrs, without_crusty_attrs};
+
+/// Wraps a call through the vtable so methods added in a later version fall back to their
+/// default body when the vtable was built by an older implementation.
+fn versioned_call(
+    call: TokenStream,
+    vtable_size: TokenStream,
+    vtable_ty: TokenStream,
+    f: &syn::TraitItemFn,
+) -> syn::Result<TokenStream> {
+    let method_name = &f.sig.ident;
+    if MethodAttrs::from_attrs(&f.attrs)?.since.is_none() {
+        return Ok(call);
+    }
+    let Some(fallback) = &f.default else {
+        return Err(syn::Error::new_spanned(
+            method_name,
+            "methods with a `since` version need a default body for vtables without their slot",
+        ));
+    };
+    Ok(quote! {
+        if #vtable_size > ::core::mem::offset_of!(#vtable_ty, #method_name) {
+            #call
+        } else #fallback
+    })
+}
+
+pub fn impl_trait_for_c_ref(
+    input: &syn::ItemTrait,
+    vtable: &syn::ItemStruct,
+) -> syn::Result<syn::ItemImpl> {
     let trait_ident = &input.ident;
     let vtable_ident = &vtable.ident;
     let generics = &input.generics;
@@ -47,31 +78,39 @@ pub fn impl_trait_for_c_ref(input: &syn::ItemTrait, vtable: &syn::ItemStruct) ->
                 }
             });
 
-            f.default = Some(parse_quote!({
-                #[allow(unsafe_code)]
-                unsafe {
-                    (self.get_vtable().#method_name)(#(#inputs),*)
-                }
-            }));
-
-            quote! {
+            let call = crate::lowering::vtable_call(
+                &f.sig,
+                quote! { (self.get_vtable().#method_name)(#(#inputs),*) },
+            );
+            let body = versioned_call(
+                call,
+                quote! { self.get_vtable().vtable_size },
+                quote! { #vtable_ident #generics },
+                &f,
+            )?;
+            f.default = Some(parse_quote!({ #body }));
+            f.attrs = without_crusty_attrs(&f.attrs);
+
+            Ok(quote! {
                 #f
-            }
-        });
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
 
-    parse_quote! {
+    Ok(parse_quote! {
         impl #generics #trait_ident #generics for CRepr<#vtable_ident  #generics> {
             #(#methods)*
         }
-    }
+    })
 }
 
 pub fn impl_trait_for_c_ref_where_as_vtable(
     input: &syn::ItemTrait,
     vtable: &syn::ItemStruct,
     super_traits: &crate::super_trait::SuperTraits,
-    ignore_bounds: &Vec<String>,
-) -> syn::ItemImpl {
+    ignore_bounds: &[String],
+    slot_traits: &[Ident],
+) -> syn::Result<syn::ItemImpl> {
     let trait_ident = &input.ident;
     let vtable_ident = &vtable.ident;
     let generics = &input.generics;
@@ -98,12 +137,12 @@ pub fn impl_trait_for_c_ref_where_as_vtable(
                     match ty {
                         Type::Reference(type_ref) if type_ref.mutability.is_none() => {
                             quote! {
-                                self.as_cref_with_methods(std::ptr::NonNull::from(methods))
+                                self.as_cref_with_methods(::core::ptr::NonNull::from(methods))
                             }
                         }
                         Type::Reference(_) => {
                             quote! {
-                                self.as_cref_mut_with_methods(std::ptr::NonNull::from(methods))
+                                self.as_cref_mut_with_methods(::core::ptr::NonNull::from(methods))
                             }
                         }
                         _ => syn::Error::new(ty.span(), "Receiver type must be a reference")
@@ -118,16 +157,25 @@ pub fn impl_trait_for_c_ref_where_as_vtable(
                 }
             });
 
+            let call = crate::lowering::vtable_call(
+                &f.sig,
+                quote! { (methods.#method_name)(#(#inputs),*) },
+            );
+            let body = versioned_call(
+                call,
+                quote! { methods.vtable_size },
+                quote! { #vtable_ident #generics },
+                &f,
+            )?;
             f.default = Some(parse_quote!({
                 let methods: &'static #vtable_ident #generics = self.as_vtable();
-                #[allow(unsafe_code)]
-                unsafe {
-                    (methods.#method_name)(#(#inputs),*)
-                }
+                #body
             }));
+            f.attrs = without_crusty_attrs(&f.attrs);
 
-            f.to_token_stream()
-        });
+            Ok(f.to_token_stream())
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
 
     let super_trait_as_vtable = super_traits.iter().map(|super_trait| {
         let Type::Reference(vtable_ref) = &super_trait.vtable_ty else {
@@ -143,10 +191,14 @@ pub fn impl_trait_for_c_ref_where_as_vtable(
     });
 
     let ignore_bounds = ignore_bounds.iter().map(|bound| {
-        let bound_ident = Ident::new(bound, proc_macro2::Span::call_site());
+        // `Send` and `Sync` of the object are tracked by markers on the vtable
+        let bound_ident = crate::std_traits::marker_trait(bound)
+            .unwrap_or_else(|| Ident::new(bound, proc_macro2::Span::call_site()));
         quote! { + #bound_ident }
     });
 
+    let slot_bounds = crate::std_traits::slot_bounds(slot_traits);
+
     let mut start_gen = generics.clone();
 
     start_gen.params.insert(0, parse_quote!(GEN));
@@ -160,15 +212,133 @@ pub fn impl_trait_for_c_ref_where_as_vtable(
         }
     });
 
-    parse_quote! {
+    Ok(parse_quote! {
         impl #start_gen #trait_ident #generics for CRepr<GEN>
         where
-            GEN: AsVTable<&'static #vtable_ident #generics> + CDrop  #(#super_trait_as_vtable)* #(#ignore_bounds)*,
+            GEN: AsVTable<&'static #vtable_ident #generics> + CDrop  #(#super_trait_as_vtable)* #(#ignore_bounds)* #(#slot_bounds)*,
             #(#static_generics),*
         {
             #(#methods)*
         }
+    })
+}
+
+/// Reuses an impl for `CRepr` for `CReprInline`, which provides the same methods.
+pub fn impl_for_c_repr_inline(item: &syn::ItemImpl) -> syn::ItemImpl {
+    let mut item = item.clone();
+    item.generics
+        .params
+        .push(parse_quote!(const INLINE_WORDS: usize));
+
+    if let Type::Path(self_ty) = item.self_ty.as_mut()
+        && let Some(segment) = self_ty.path.segments.last_mut()
+    {
+        segment.ident = Ident::new("CReprInline", segment.ident.span());
+        if let syn::PathArguments::AngleBracketed(args) = &mut segment.arguments {
+            args.args.push(parse_quote!(INLINE_WORDS));
+        }
     }
+
+    item
+}
+
+/// Returns whether the method takes `&self`, so it can be called through a `CRef`.
+fn has_shared_receiver(f: &syn::TraitItemFn) -> bool {
+    f.sig
+        .receiver()
+        .is_some_and(|recv| recv.reference.is_some() && recv.mutability.is_none())
+}
+
+/// Returns the name of the read-only trait implemented for `CRef`, if the trait needs one
+/// because some of its methods take `&mut self`.
+pub fn read_only_trait_ident(input: &syn::ItemTrait) -> Option<Ident> {
+    let all_shared = input.items.iter().all(|item| match item {
+        syn::TraitItem::Fn(f) => has_shared_receiver(f),
+        _ => true,
+    });
+    (!all_shared).then(|| quote::format_ident!("{}Ref", input.ident))
+}
+
+/// Creates the trait holding the `&self` methods of the input, implemented for `CRef`.
+pub fn create_read_only_trait(input: &syn::ItemTrait, ident: Ident) -> syn::ItemTrait {
+    let doc = format!(
+        "The `&self` methods of [`{}`], implemented for `CRef`",
+        input.ident
+    );
+    let items = input.items.iter().filter_map(|item| match item {
+        syn::TraitItem::Fn(f) if has_shared_receiver(f) => {
+            let mut f = f.clone();
+            f.default = None;
+            f.semi_token = Some(Default::default());
+            f.attrs = without_crusty_attrs(&f.attrs);
+            Some(f)
+        }
+        _ => None,
+    });
+    let vis = &input.vis;
+    let generics = &input.generics;
+
+    parse_quote! {
+        #[doc = #doc]
+        #vis trait #ident #generics {
+            #(#items)*
+        }
+    }
+}
+
+/// Reuses an impl for `CRepr` for a wrapper that provides the same methods, such as `CRef`,
+/// `CRefMut`, `CReprScoped` or `CArc`, adding the `'crusty` lifetime if the wrapper takes one.
+///
+/// With `read_only` the impl is for that trait and only keeps the `&self` methods, otherwise it
+/// only applies where the wrapper also implements the super traits.
+pub fn impl_for_wrapper(
+    item: &syn::ItemImpl,
+    wrapper: &str,
+    with_lifetime: bool,
+    input: &syn::ItemTrait,
+    read_only: Option<&Ident>,
+) -> syn::ItemImpl {
+    let mut item = item.clone();
+    if with_lifetime {
+        item.generics.params.insert(0, parse_quote!('crusty));
+    }
+
+    if let Type::Path(self_ty) = item.self_ty.as_mut()
+        && let Some(segment) = self_ty.path.segments.last_mut()
+    {
+        segment.ident = Ident::new(wrapper, segment.ident.span());
+        if with_lifetime && let syn::PathArguments::AngleBracketed(args) = &mut segment.arguments {
+            args.args.insert(0, parse_quote!('crusty));
+        }
+    }
+
+    if let Some(read_only) = read_only {
+        if let Some((_, trait_path, _)) = &mut item.trait_
+            && let Some(segment) = trait_path.segments.last_mut()
+        {
+            segment.ident = read_only.clone();
+        }
+        item.items.retain(|item| match item {
+            syn::ImplItem::Fn(f) => f
+                .sig
+                .receiver()
+                .is_some_and(|recv| recv.reference.is_some() && recv.mutability.is_none()),
+            _ => true,
+        });
+    } else if !input.supertraits.is_empty() {
+        let self_ty = &item.self_ty;
+        let supertraits = &input.supertraits;
+        // without a lifetime the bound would be checked eagerly, failing the whole impl when a
+        // super trait is not implemented for the wrapper instead of just not applying
+        let predicate: syn::WherePredicate = if with_lifetime {
+            parse_quote!(#self_ty: #supertraits)
+        } else {
+            parse_quote!(for<'crusty> #self_ty: #supertraits)
+        };
+        item.generics.make_where_clause().predicates.push(predicate);
+    }
+
+    item
 }
 
 #[cfg(test)]
@@ -196,9 +366,16 @@ mod tests {
         let SuperTraitReturn {
             super_traits,
             ignore_bounds,
+            ..
         } = crate::super_trait::get_super_traits(&input).unwrap();
-        let output =
-            impl_trait_for_c_ref_where_as_vtable(&input, &vtable, &super_traits, &ignore_bounds);
+        let output = impl_trait_for_c_ref_where_as_vtable(
+            &input,
+            &vtable,
+            &super_traits,
+            &ignore_bounds,
+            &[],
+        )
+        .unwrap();
 
         let expected_output: syn::ItemImpl = parse_quote!(
             impl<GEN> MyTrait for CRepr<GEN>
@@ -210,7 +387,7 @@ mod tests {
                     #[allow(unsafe_code)]
                     unsafe {
                         (methods.my_method)(
-                            self.as_cref_with_methods(std::ptr::NonNull::from(methods)),
+                            self.as_cref_with_methods(::core::ptr::NonNull::from(methods)),
                             x,
                         )
                     }
@@ -220,7 +397,7 @@ mod tests {
                     #[allow(unsafe_code)]
                     unsafe {
                         (methods.another_method)(
-                            self.as_cref_mut_with_methods(std::ptr::NonNull::from(methods)),
+                            self.as_cref_mut_with_methods(::core::ptr::NonNull::from(methods)),
                             y,
                         )
                     }
@@ -247,7 +424,7 @@ mod tests {
                 pub another_method: unsafe fn(this: &mut CRepr<MyTraitVTable>, y: String),
             }
         };
-        let output = impl_trait_for_c_ref(&input, &vtable);
+        let output = impl_trait_for_c_ref(&input, &vtable).unwrap();
 
         let expected_output: syn::ItemImpl = parse_quote!(
             impl MyTrait for CRepr<MyTraitVTable> {
@@ -269,6 +446,33 @@ mod tests {
         assert_eq!(output, expected_output);
     }
 
+    #[test]
+    fn impl_trait_for_cref_test_async() {
+        let input: syn::ItemTrait = parse_quote! {
+            pub trait MyTrait {
+                async fn fetch(&self, id: u32) -> String;
+            }
+        };
+        let vtable: syn::ItemStruct = parse_quote! {
+            pub struct MyTraitVTable {
+                pub fetch: unsafe extern "C" fn(CRef<MyTraitVTable>, u32) -> CReprScoped<'_, CFutureVTable<String>>,
+            }
+        };
+        let output = impl_trait_for_c_ref(&input, &vtable).unwrap();
+
+        let expected_output: syn::ItemImpl = parse_quote!(
+            impl MyTrait for CRepr<MyTraitVTable> {
+                async fn fetch(&self, id: u32) -> String {
+                    #[allow(unsafe_code)]
+                    let future = unsafe { (self.get_vtable().fetch)(self.as_cref(), id) };
+                    future.await
+                }
+            }
+        );
+
+        assert_eq!(output, expected_output);
+    }
+
     #[test]
     fn impl_trait_for_cref_test_with_generics() {
         let input: syn::ItemTrait = parse_quote! {
@@ -283,7 +487,7 @@ mod tests {
                 pub another_method: unsafe fn(this: &mut CRepr<MyTraitVTable<T>>, y: String),
             }
         };
-        let output = impl_trait_for_c_ref(&input, &vtable);
+        let output = impl_trait_for_c_ref(&input, &vtable).unwrap();
 
         let expected_output: syn::ItemImpl = parse_quote!(
             impl<T> MyTrait<T> for CRepr<MyTraitVTable<T>> {
@@ -321,7 +525,7 @@ mod tests {
             }
         };
 
-        let output = impl_trait_for_c_ref(&input, &vtable);
+        let output = impl_trait_for_c_ref(&input, &vtable).unwrap();
         let expected_output: syn::ItemImpl = parse_quote!(
             impl<T> MyTrait<T> for CRepr<MyTraitVTable<T>> {
                 fn my_method(&self, x: T) -> T {
@@ -341,4 +545,146 @@ mod tests {
 
         assert_eq!(output, expected_output);
     }
+
+    #[test]
+    fn impl_trait_for_cref_test_with_since() {
+        let input: syn::ItemTrait = parse_quote! {
+            pub trait MyTrait {
+                fn my_method(&self) -> i32;
+                #[crusty(since = 2)]
+                fn with_default(&self) -> i32 {
+                    0
+                }
+            }
+        };
+        let vtable: syn::ItemStruct = parse_quote! {
+            pub struct MyTraitVTable {}
+        };
+        let output = impl_trait_for_c_ref(&input, &vtable).unwrap();
+
+        let expected_output: syn::ItemImpl = parse_quote!(
+            impl MyTrait for CRepr<MyTraitVTable> {
+                fn my_method(&self) -> i32 {
+                    #[allow(unsafe_code)]
+                    unsafe {
+                        (self.get_vtable().my_method)(self.as_cref())
+                    }
+                }
+                fn with_default(&self) -> i32 {
+                    if self.get_vtable().vtable_size
+                        > ::core::mem::offset_of!(MyTraitVTable, with_default)
+                    {
+                        #[allow(unsafe_code)]
+                        unsafe {
+                            (self.get_vtable().with_default)(self.as_cref())
+                        }
+                    } else {
+                        0
+                    }
+                }
+            }
+        );
+
+        assert_eq!(
+            crate::utils::test_utils::item_to_pretty_string(syn::Item::Impl(output)),
+            crate::utils::test_utils::item_to_pretty_string(syn::Item::Impl(expected_output))
+        );
+    }
+
+    #[test]
+    fn impl_trait_for_cref_test_since_without_default() {
+        let input: syn::ItemTrait = parse_quote! {
+            pub trait MyTrait {
+                #[crusty(since = 3)]
+                fn without_default(&mut self);
+            }
+        };
+        let vtable: syn::ItemStruct = parse_quote! {
+            pub struct MyTraitVTable {}
+        };
+        assert!(impl_trait_for_c_ref(&input, &vtable).is_err());
+    }
+
+    #[test]
+    fn impl_for_wrapper_test() {
+        let input: syn::ItemTrait = parse_quote! {
+            pub trait MyTrait: SuperTrait {
+                fn my_method(&self, x: i32) -> i32;
+                fn another_method(&mut self, y: String);
+            }
+        };
+        let vtable: syn::ItemStruct = parse_quote! {
+            pub struct MyTraitVTable {
+                pub my_method: unsafe fn(this: &CRepr<MyTraitVTable>, x: i32) -> i32,
+                pub another_method: unsafe fn(this: &mut CRepr<MyTraitVTable>, y: String),
+            }
+        };
+        let repr_impl = impl_trait_for_c_ref(&input, &vtable).unwrap();
+        let read_only = read_only_trait_ident(&input).unwrap();
+
+        let expected_mut: syn::ItemImpl = parse_quote!(
+            impl<'crusty> MyTrait for CRefMut<'crusty, MyTraitVTable>
+            where
+                CRefMut<'crusty, MyTraitVTable>: SuperTrait,
+            {
+                fn my_method(&self, x: i32) -> i32 {
+                    #[allow(unsafe_code)]
+                    unsafe {
+                        (self.get_vtable().my_method)(self.as_cref(), x)
+                    }
+                }
+                fn another_method(&mut self, y: String) {
+                    #[allow(unsafe_code)]
+                    unsafe {
+                        (self.get_vtable().another_method)(self.as_cref_mut(), y)
+                    }
+                }
+            }
+        );
+        assert_eq!(
+            crate::utils::test_utils::item_to_pretty_string(syn::Item::Impl(impl_for_wrapper(
+                &repr_impl, "CRefMut", true, &input, None
+            ))),
+            crate::utils::test_utils::item_to_pretty_string(syn::Item::Impl(expected_mut))
+        );
+
+        let expected_ref: syn::ItemImpl = parse_quote!(
+            impl<'crusty> MyTraitRef for CRef<'crusty, MyTraitVTable> {
+                fn my_method(&self, x: i32) -> i32 {
+                    #[allow(unsafe_code)]
+                    unsafe {
+                        (self.get_vtable().my_method)(self.as_cref(), x)
+                    }
+                }
+            }
+        );
+        assert_eq!(
+            crate::utils::test_utils::item_to_pretty_string(syn::Item::Impl(impl_for_wrapper(
+                &repr_impl,
+                "CRef",
+                true,
+                &input,
+                Some(&read_only)
+            ))),
+            crate::utils::test_utils::item_to_pretty_string(syn::Item::Impl(expected_ref))
+        );
+
+        let expected_trait: syn::ItemTrait = parse_quote! {
+            #[doc = "The `&self` methods of [`MyTrait`], implemented for `CRef`"]
+            pub trait MyTraitRef {
+                fn my_method(&self, x: i32) -> i32;
+            }
+        };
+        assert_eq!(create_read_only_trait(&input, read_only), expected_trait);
+    }
+
+    #[test]
+    fn read_only_trait_ident_test() {
+        let input: syn::ItemTrait = parse_quote! {
+            pub trait MyTrait {
+                fn my_method(&self, x: i32) -> i32;
+            }
+        };
+        assert!(read_only_trait_ident(&input).is_none());
+    }
 }
@@ -119,6 +119,7 @@ pub fn map_ty_generics(ty: &mut Type, mapper: &dyn Fn(&mut Type)) {
                         angle_bracketed.args.iter_mut().for_each(|arg| {
                             if let syn::GenericArgument::Type(ty) = arg {
                                 mapper(ty);
+                                map_ty_generics(ty, &mapper);
                             }
                         });
                     }
@@ -4,18 +4,22 @@ use proc_macro2::TokenStream;
 use quote::quote;
 
 use syn::{
-    BareFnArg, Field, ItemStruct, LitStr, Token, TraitItem, Type, TypeBareFn, Visibility,
+    BareFnArg, Field, Ident, ItemStruct, LitStr, Token, TraitItem, Type, TypeBareFn, Visibility,
     parse_quote,
 };
 
 use crate::{
+    attributes::{MethodAttrs, TraitAttrs, without_crusty_attrs},
+    lowering, std_traits,
     super_trait::SuperTraits,
     utils::{self, doc_attribute, repr_c_attribute},
 };
 
 pub fn create_vtable(
     input: &syn::ItemTrait,
+    trait_attrs: &TraitAttrs,
     super_traits: &SuperTraits,
+    slot_traits: &[Ident],
 ) -> Result<ItemStruct, syn::Error> {
     let trait_ident = &input.ident;
     let repr_c = repr_c_attribute();
@@ -39,47 +43,60 @@ pub fn create_vtable(
     let vtable_ident = &vtable.ident;
     let generics = &vtable.generics;
 
-    let mut fields = input
-        .items
-        .iter()
-        .filter_map(|i| {
-            if let TraitItem::Fn(fn_item) = i {
-                Some(fn_item)
-            } else {
-                None
-            }
-        })
-        .map(|method| {
-            let ty = TypeBareFn {
-                lifetimes: None,
-                unsafety: Some(Default::default()),
-                abi: Some(syn::Abi {
-                    extern_token: Default::default(),
-                    name: Some(LitStr::new("C", proc_macro2::Span::call_site())),
-                }),
-                fn_token: Default::default(),
-                paren_token: Default::default(),
-                inputs: map_inputs(&method.sig.inputs, Some(quote! { #vtable_ident #generics}))
-                    .collect(),
-                variadic: None,
-                output: method.sig.output.clone(),
-            };
-            Field {
-                attrs: method.attrs.clone(),
-                vis: Visibility::Public(Default::default()),
-                mutability: syn::FieldMutability::None,
-                ident: Some(method.sig.ident.clone()),
-                colon_token: Some(Default::default()),
-                ty: Type::BareFn(ty),
-            }
-        })
-        .collect::<Vec<_>>();
+    let mut methods = vec![];
+    for method in input.items.iter().filter_map(|i| {
+        if let TraitItem::Fn(fn_item) = i {
+            Some(fn_item)
+        } else {
+            None
+        }
+    }) {
+        let method_attrs = MethodAttrs::from_attrs(&method.attrs)?;
+        lowering::check_method(&method.sig, method_attrs.since)?;
+        if method_attrs.since.is_some() && method.default.is_none() {
+            return Err(syn::Error::new_spanned(
+                &method.sig.ident,
+                "methods with a `since` version need a default body for vtables without their slot",
+            ));
+        }
+        let ty = TypeBareFn {
+            lifetimes: None,
+            unsafety: Some(Default::default()),
+            abi: Some(syn::Abi {
+                extern_token: Default::default(),
+                name: Some(LitStr::new("C", proc_macro2::Span::call_site())),
+            }),
+            fn_token: Default::default(),
+            paren_token: Default::default(),
+            inputs: map_inputs(&method.sig.inputs, Some(quote! { #vtable_ident #generics}))
+                .collect(),
+            variadic: None,
+            output: lowering::lowered_output(&method.sig),
+        };
+        let field = Field {
+            attrs: without_crusty_attrs(&method.attrs),
+            vis: Visibility::Public(Default::default()),
+            mutability: syn::FieldMutability::None,
+            ident: Some(method.sig.ident.clone()),
+            colon_token: Some(Default::default()),
+            ty: Type::BareFn(ty),
+        };
+        methods.push((method_attrs, field));
+    }
+
+    let size_field: Field = parse_quote!(
+        #[doc = "The size of the vtable in bytes, used to detect methods added in later versions"]
+        pub vtable_size: usize
+    );
 
     let drop_field: Field = parse_quote!(
-        #[doc = "A function pointer to the drop function for the trait"]
+        #[doc = "Drops the object in place, its memory is freed afterwards by the `dealloc` of its owner"]
         pub drop: unsafe extern "C" fn(CRefMut<#vtable_ident #generics>)
     );
 
+    let vtable_ty = quote! { #vtable_ident #generics };
+    let slot_fields = std_traits::slot_fields(slot_traits, &vtable_ty);
+
     let mut needs_statlic = Vec::new();
 
     let super_trait_fields = super_traits
@@ -106,21 +123,72 @@ pub fn create_vtable(
         if let Some(param) = vtable.generics.params.iter_mut().find(|param| match param {
             syn::GenericParam::Type(type_param) => type_param.ident == generic,
             _ => false,
-        }) {
-            if let syn::GenericParam::Type(type_param) = param
-                && !type_param.bounds.iter().any(|b| match b {
-                    syn::TypeParamBound::Lifetime(lifetime) => lifetime.ident == "static",
-                    _ => false,
-                })
-            {
-                type_param.bounds.push(parse_quote!( 'static ));
-            }
+        }) && let syn::GenericParam::Type(type_param) = param
+            && !type_param.bounds.iter().any(|b| match b {
+                syn::TypeParamBound::Lifetime(lifetime) => lifetime.ident == "static",
+                _ => false,
+            })
+        {
+            type_param.bounds.push(parse_quote!( 'static ));
         }
     });
 
-    fields.extend(super_trait_fields);
-
-    fields.push(drop_field);
+    let mut fields = vec![size_field];
+
+    if trait_attrs.stable_layout {
+        // methods, super trait vtables and std trait slots share one numbering, so adding any of
+        // them with a new slot leaves the others in place
+        let mut slotted = methods
+            .into_iter()
+            .map(|(attrs, field)| {
+                let name = field.ident.clone().expect("method fields are named");
+                match attrs.slot {
+                    Some(slot) => Ok(Slotted {
+                        name,
+                        slot,
+                        since: attrs.since,
+                        fields: vec![field],
+                    }),
+                    None => Err(syn::Error::new_spanned(
+                        name,
+                        "methods of a `stable_layout` trait must have a `#[crusty(slot = N)]` attribute",
+                    )),
+                }
+            })
+            .collect::<syn::Result<Vec<_>>>()?;
+        let super_fields = super_traits
+            .iter()
+            .map(|super_trait| &super_trait.ident)
+            .zip(super_trait_fields.into_iter().map(|field| vec![field]));
+        let std_fields = slot_traits.iter().map(|slot_trait| {
+            let fields = std_traits::slot_fields(core::slice::from_ref(slot_trait), &vtable_ty);
+            (slot_trait, fields)
+        });
+        slotted.extend(super_slots(
+            &trait_attrs.super_slots,
+            super_fields.chain(std_fields),
+        )?);
+        fields.push(drop_field);
+        fields.extend(order_by_slot(slotted)?);
+    } else {
+        if let Some((_, field)) = methods.iter().find(|(attrs, _)| attrs.slot.is_some()) {
+            return Err(syn::Error::new_spanned(
+                &field.ident,
+                "`slot` requires the trait to be marked `#[crusty_trait(stable_layout)]`",
+            ));
+        }
+        let (mut since_fields, base_fields): (Vec<_>, Vec<_>) = methods
+            .into_iter()
+            .partition(|(attrs, _)| attrs.since.is_some());
+        // methods added in later versions are appended after `drop` so older vtables stay a prefix
+        since_fields.sort_by_key(|(attrs, _)| attrs.since);
+
+        fields.extend(base_fields.into_iter().map(|(_, field)| field));
+        fields.extend(super_trait_fields);
+        fields.extend(slot_fields);
+        fields.push(drop_field);
+        fields.extend(since_fields.into_iter().map(|(_, field)| field));
+    }
 
     vtable.fields = syn::Fields::Named(syn::FieldsNamed {
         brace_token: syn::token::Brace::default(),
@@ -130,6 +198,95 @@ pub fn create_vtable(
     Ok(vtable)
 }
 
+/// The fields of a method or super trait, placed at one slot of a `stable_layout` vtable.
+struct Slotted {
+    /// The method or super trait, which errors point to.
+    name: Ident,
+    slot: usize,
+    since: Option<u32>,
+    fields: Vec<Field>,
+}
+
+/// Looks up the slots of the super traits of a `stable_layout` trait, skipping std traits like
+/// `Eq` that add no fields to the vtable.
+fn super_slots<'a>(
+    slots: &[(Ident, usize)],
+    super_fields: impl Iterator<Item = (&'a Ident, Vec<Field>)>,
+) -> syn::Result<Vec<Slotted>> {
+    let mut slotted = vec![];
+    for (ident, fields) in super_fields.filter(|(_, fields)| !fields.is_empty()) {
+        let Some((_, slot)) = slots.iter().find(|(name, _)| name == ident) else {
+            return Err(syn::Error::new_spanned(
+                ident,
+                format!(
+                    "super traits of a `stable_layout` trait must have a slot, as in `#[crusty_trait(stable_layout({ident} = N))]`"
+                ),
+            ));
+        };
+        slotted.push(Slotted {
+            name: ident.clone(),
+            slot: *slot,
+            since: None,
+            fields,
+        });
+    }
+    if let Some((name, _)) = slots
+        .iter()
+        .find(|(name, _)| !slotted.iter().any(|slotted| &slotted.name == name))
+    {
+        return Err(syn::Error::new_spanned(
+            name,
+            format!("`{name}` is not a super trait with fields in the vtable"),
+        ));
+    }
+    Ok(slotted)
+}
+
+/// Orders the fields of a `stable_layout` trait by their slot index, checking that the slots
+/// have no gaps or duplicates and that nothing added in a later version comes before something
+/// from an earlier version.
+fn order_by_slot(mut slotted: Vec<Slotted>) -> syn::Result<Vec<Field>> {
+    slotted.sort_by_key(|slotted| slotted.slot);
+
+    let mut latest_since = 0;
+    for (
+        expected,
+        Slotted {
+            name, slot, since, ..
+        },
+    ) in slotted.iter().enumerate()
+    {
+        if *slot < expected {
+            return Err(syn::Error::new_spanned(
+                name,
+                format!("slot {slot} is used more than once"),
+            ));
+        }
+        if *slot > expected {
+            return Err(syn::Error::new_spanned(
+                name,
+                format!("slot {expected} is missing, slots must be numbered from 0 without gaps"),
+            ));
+        }
+        if since.unwrap_or_default() < latest_since {
+            let added = match since {
+                Some(since) => format!("was added in version {since}"),
+                None => "has no `since` version".to_string(),
+            };
+            return Err(syn::Error::new_spanned(
+                name,
+                format!("slot {slot} {added} but follows a slot added in version {latest_since}"),
+            ));
+        }
+        latest_since = since.unwrap_or_default();
+    }
+
+    Ok(slotted
+        .into_iter()
+        .flat_map(|slotted| slotted.fields)
+        .collect())
+}
+
 fn map_inputs(
     inputs: &syn::punctuated::Punctuated<syn::FnArg, Token![,]>,
     name: Option<TokenStream>,
@@ -181,13 +338,15 @@ mod tests {
             pub trait MyTrait {}
         };
         let super_traits = SuperTraits::default();
-        let vtable = create_vtable(&input, &super_traits).unwrap();
+        let vtable = create_vtable(&input, &TraitAttrs::default(), &super_traits, &[]).unwrap();
 
         let expected: ItemStruct = parse_quote! {
             #[repr(C)]
             #[doc = "A repr C vtable for the trait MyTrait"]
             pub struct MyTraitVTable {
-                #[doc = "A function pointer to the drop function for the trait"]
+                #[doc = "The size of the vtable in bytes, used to detect methods added in later versions"]
+                pub vtable_size: usize,
+                #[doc = "Drops the object in place, its memory is freed afterwards by the `dealloc` of its owner"]
                 pub drop: unsafe extern "C" fn(CRefMut<MyTraitVTable>),
             }
         };
@@ -207,15 +366,17 @@ mod tests {
             }
         };
         let super_traits = SuperTraits::default();
-        let vtable = create_vtable(&input, &super_traits).unwrap();
+        let vtable = create_vtable(&input, &TraitAttrs::default(), &super_traits, &[]).unwrap();
 
         let expected: ItemStruct = parse_quote! {
             #[repr(C)]
             #[doc = "A repr C vtable for the trait MyTrait"]
             pub struct MyTraitVTable {
+                #[doc = "The size of the vtable in bytes, used to detect methods added in later versions"]
+                pub vtable_size: usize,
                 pub method1: unsafe extern "C" fn(CRef<MyTraitVTable>),
                 pub method2: unsafe extern "C" fn(CRefMut<MyTraitVTable>, i32) -> i32,
-                #[doc = "A function pointer to the drop function for the trait"]
+                #[doc = "Drops the object in place, its memory is freed afterwards by the `dealloc` of its owner"]
                 pub drop: unsafe extern "C" fn(CRefMut<MyTraitVTable>),
             }
         };
@@ -235,16 +396,24 @@ mod tests {
         };
         let super_traits = crate::super_trait::get_super_traits(&input).unwrap();
 
-        let vtable = create_vtable(&input, &super_traits.super_traits).unwrap();
+        let vtable = create_vtable(
+            &input,
+            &TraitAttrs::default(),
+            &super_traits.super_traits,
+            &[],
+        )
+        .unwrap();
 
         let expected: ItemStruct = parse_quote! {
             #[repr(C)]
             #[doc = "A repr C vtable for the trait MyTrait"]
             pub struct MyTraitVTable {
+                #[doc = "The size of the vtable in bytes, used to detect methods added in later versions"]
+                pub vtable_size: usize,
                 pub method1: unsafe extern "C" fn(CRef<MyTraitVTable>),
                 #[doc = "A vtable point for SuperTrait1"]
                 pub field_super_trait1: &'static SuperTrait1VTable,
-                #[doc = "A function pointer to the drop function for the trait"]
+                #[doc = "Drops the object in place, its memory is freed afterwards by the `dealloc` of its owner"]
                 pub drop: unsafe extern "C" fn(CRefMut<MyTraitVTable>),
             }
         };
@@ -264,16 +433,24 @@ mod tests {
         };
         let super_traits = crate::super_trait::get_super_traits(&input).unwrap();
 
-        let vtable = create_vtable(&input, &super_traits.super_traits).unwrap();
+        let vtable = create_vtable(
+            &input,
+            &TraitAttrs::default(),
+            &super_traits.super_traits,
+            &[],
+        )
+        .unwrap();
 
         let expected: ItemStruct = parse_quote! {
             #[repr(C)]
             #[doc = "A repr C vtable for the trait MyTrait"]
             pub struct MyTraitVTable<T: 'static> {
+                #[doc = "The size of the vtable in bytes, used to detect methods added in later versions"]
+                pub vtable_size: usize,
                 pub method1: unsafe extern "C" fn(CRef<MyTraitVTable<T>>, T),
                 #[doc = "A vtable point for SuperTrait1"]
                 pub field_super_trait1: &'static SuperTrait1VTable<T>,
-                #[doc = "A function pointer to the drop function for the trait"]
+                #[doc = "Drops the object in place, its memory is freed afterwards by the `dealloc` of its owner"]
                 pub drop: unsafe extern "C" fn(CRefMut<MyTraitVTable<T>>),
             }
         };
@@ -283,4 +460,218 @@ mod tests {
             item_to_pretty_string(syn::Item::Struct(expected))
         );
     }
+
+    #[test]
+    fn test_create_vtable_with_since_methods() {
+        let input: syn::ItemTrait = parse_quote! {
+            pub trait MyTrait {
+                #[crusty(since = 3)]
+                fn method3(&self) {}
+                fn method1(&self);
+                /// Added in version 2.
+                #[crusty(since = 2)]
+                fn method2(&self) {}
+            }
+        };
+        let super_traits = SuperTraits::default();
+        let vtable = create_vtable(&input, &TraitAttrs::default(), &super_traits, &[]).unwrap();
+
+        let expected: ItemStruct = parse_quote! {
+            #[repr(C)]
+            #[doc = "A repr C vtable for the trait MyTrait"]
+            pub struct MyTraitVTable {
+                #[doc = "The size of the vtable in bytes, used to detect methods added in later versions"]
+                pub vtable_size: usize,
+                pub method1: unsafe extern "C" fn(CRef<MyTraitVTable>),
+                #[doc = "Drops the object in place, its memory is freed afterwards by the `dealloc` of its owner"]
+                pub drop: unsafe extern "C" fn(CRefMut<MyTraitVTable>),
+                /// Added in version 2.
+                pub method2: unsafe extern "C" fn(CRef<MyTraitVTable>),
+                pub method3: unsafe extern "C" fn(CRef<MyTraitVTable>),
+            }
+        };
+
+        assert_eq!(
+            item_to_pretty_string(syn::Item::Struct(vtable)),
+            item_to_pretty_string(syn::Item::Struct(expected))
+        );
+    }
+
+    #[test]
+    fn test_create_vtable_invalid_since() {
+        let input: syn::ItemTrait = parse_quote! {
+            pub trait MyTrait {
+                #[crusty(since = "two")]
+                fn method(&self);
+            }
+        };
+        let super_traits = SuperTraits::default();
+        assert!(create_vtable(&input, &TraitAttrs::default(), &super_traits, &[]).is_err());
+    }
+
+    #[test]
+    fn test_create_vtable_since_without_default() {
+        let input: syn::ItemTrait = parse_quote! {
+            pub trait MyTrait {
+                #[crusty(since = 2)]
+                fn method(&self);
+            }
+        };
+        let super_traits = SuperTraits::default();
+        let error = create_vtable(&input, &TraitAttrs::default(), &super_traits, &[]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "methods with a `since` version need a default body for vtables without their slot"
+        );
+    }
+
+    #[test]
+    fn test_create_vtable_stable_layout() {
+        let input: syn::ItemTrait = parse_quote! {
+            pub trait MyTrait: SuperTrait1 {
+                #[crusty(slot = 1)]
+                fn method2(&self);
+                #[crusty(slot = 3, since = 2)]
+                fn method3(&self) {}
+                #[crusty(slot = 0)]
+                fn method1(&self);
+            }
+        };
+        let trait_attrs = TraitAttrs::parse(quote!(stable_layout(SuperTrait1 = 2))).unwrap();
+        let super_traits = crate::super_trait::get_super_traits(&input).unwrap();
+        let vtable = create_vtable(&input, &trait_attrs, &super_traits.super_traits, &[]).unwrap();
+
+        let expected: ItemStruct = parse_quote! {
+            #[repr(C)]
+            #[doc = "A repr C vtable for the trait MyTrait"]
+            pub struct MyTraitVTable {
+                #[doc = "The size of the vtable in bytes, used to detect methods added in later versions"]
+                pub vtable_size: usize,
+                #[doc = "Drops the object in place, its memory is freed afterwards by the `dealloc` of its owner"]
+                pub drop: unsafe extern "C" fn(CRefMut<MyTraitVTable>),
+                pub method1: unsafe extern "C" fn(CRef<MyTraitVTable>),
+                pub method2: unsafe extern "C" fn(CRef<MyTraitVTable>),
+                #[doc = "A vtable point for SuperTrait1"]
+                pub field_super_trait1: &'static SuperTrait1VTable,
+                pub method3: unsafe extern "C" fn(CRef<MyTraitVTable>),
+            }
+        };
+
+        assert_eq!(
+            item_to_pretty_string(syn::Item::Struct(vtable)),
+            item_to_pretty_string(syn::Item::Struct(expected))
+        );
+    }
+
+    #[test]
+    fn test_create_vtable_stable_layout_errors() {
+        let stable = TraitAttrs {
+            stable_layout: true,
+            ..TraitAttrs::default()
+        };
+        let error = |input: syn::ItemTrait, trait_attrs: &TraitAttrs| {
+            create_vtable(&input, trait_attrs, &SuperTraits::default(), &[])
+                .unwrap_err()
+                .to_string()
+        };
+
+        let missing_slot = parse_quote! {
+            pub trait MyTrait {
+                #[crusty(slot = 0)]
+                fn method1(&self);
+                fn method2(&self);
+            }
+        };
+        assert_eq!(
+            error(missing_slot, &stable),
+            "methods of a `stable_layout` trait must have a `#[crusty(slot = N)]` attribute"
+        );
+
+        let duplicate = parse_quote! {
+            pub trait MyTrait {
+                #[crusty(slot = 0)]
+                fn method1(&self);
+                #[crusty(slot = 0)]
+                fn method2(&self);
+            }
+        };
+        assert_eq!(error(duplicate, &stable), "slot 0 is used more than once");
+
+        let gap = parse_quote! {
+            pub trait MyTrait {
+                #[crusty(slot = 0)]
+                fn method1(&self);
+                #[crusty(slot = 2)]
+                fn method2(&self);
+            }
+        };
+        assert_eq!(
+            error(gap, &stable),
+            "slot 1 is missing, slots must be numbered from 0 without gaps"
+        );
+
+        let out_of_order = parse_quote! {
+            pub trait MyTrait {
+                #[crusty(slot = 0, since = 2)]
+                fn method1(&self) {}
+                #[crusty(slot = 1)]
+                fn method2(&self);
+            }
+        };
+        assert_eq!(
+            error(out_of_order, &stable),
+            "slot 1 has no `since` version but follows a slot added in version 2"
+        );
+
+        let not_stable = parse_quote! {
+            pub trait MyTrait {
+                #[crusty(slot = 0)]
+                fn method1(&self);
+            }
+        };
+        assert_eq!(
+            error(not_stable, &TraitAttrs::default()),
+            "`slot` requires the trait to be marked `#[crusty_trait(stable_layout)]`"
+        );
+    }
+
+    #[test]
+    fn test_create_vtable_stable_layout_super_slot_errors() {
+        let error = |input: syn::ItemTrait, attrs: TokenStream| {
+            let super_traits = crate::super_trait::get_super_traits(&input).unwrap();
+            create_vtable(
+                &input,
+                &TraitAttrs::parse(attrs).unwrap(),
+                &super_traits.super_traits,
+                &super_traits.slot_traits,
+            )
+            .unwrap_err()
+            .to_string()
+        };
+
+        let input: syn::ItemTrait = parse_quote! {
+            pub trait MyTrait: SuperTrait1 + Clone {
+                #[crusty(slot = 0)]
+                fn method1(&self);
+                #[crusty(slot = 2, since = 2)]
+                fn method2(&self) {}
+            }
+        };
+        assert_eq!(
+            error(input.clone(), quote!(stable_layout(SuperTrait1 = 1))),
+            "super traits of a `stable_layout` trait must have a slot, as in `#[crusty_trait(stable_layout(Clone = N))]`"
+        );
+        assert_eq!(
+            error(
+                input.clone(),
+                quote!(stable_layout(SuperTrait1 = 1, Clone = 3, Debug = 4))
+            ),
+            "`Debug` is not a super trait with fields in the vtable"
+        );
+        // super traits are part of the first version, so old vtables always have them
+        assert_eq!(
+            error(input, quote!(stable_layout(SuperTrait1 = 1, Clone = 3))),
+            "slot 3 has no `since` version but follows a slot added in version 2"
+        );
+    }
 }
@@ -1,35 +1,52 @@
 use quote::{ToTokens, format_ident, quote};
 use syn::{
-    GenericParam, Ident, ItemStruct, ItemTrait, TraitItem, Type, TypeParamBound, parse_quote,
+    GenericParam, Generics, Ident, ItemStruct, ItemTrait, TraitItem, Type, TypeParamBound,
+    parse_quote,
 };
 
 use crate::{
+    lowering,
     utils::{self, map_generics_ident},
     vtable::map_inputs,
 };
 
-pub fn impl_vtable_methods(input: &ItemTrait, vtable: &ItemStruct) -> syn::Item {
-    let generics = &input.generics;
-    let trait_ident = &input.ident;
-    let vtable_ident = &vtable.ident;
-    let mut renamed_generics = generics.clone();
-
+/// Adds a `'static` bound to every type parameter, as required by `&'static` vtables.
+fn static_generics(generics: &Generics) -> Generics {
     let mut static_generics = generics.clone();
 
     static_generics.params.iter_mut().for_each(|param| {
-        if let GenericParam::Type(type_param) = param {
-            if !type_param.bounds.iter().any(|bound| {
+        if let GenericParam::Type(type_param) = param
+            && !type_param.bounds.iter().any(|bound| {
                 if let TypeParamBound::Lifetime(lifetime) = bound {
                     lifetime.ident == "static"
                 } else {
                     false
                 }
-            }) {
-                type_param.bounds.push(parse_quote!('static));
-            }
+            })
+        {
+            type_param.bounds.push(parse_quote!('static));
         }
     });
 
+    static_generics
+}
+
+/// Implements `StaticVTable<GEN>` for the vtable, building it for `GEN` as a constant.
+pub fn impl_static_vtable(
+    input: &ItemTrait,
+    vtable: &ItemStruct,
+    slot_traits: &[Ident],
+) -> syn::Item {
+    let generics = &input.generics;
+    let trait_ident = &input.ident;
+    let vtable_ident = &vtable.ident;
+    let mut renamed_generics = generics.clone();
+
+    let mut impl_generics = static_generics(generics);
+    impl_generics
+        .params
+        .push(parse_quote!(GEN: #trait_ident #generics));
+
     renamed_generics.params.iter_mut().for_each(|g| {
         map_generics_ident(g, &utils::map_method_ident);
     });
@@ -103,6 +120,7 @@ pub fn impl_vtable_methods(input: &ItemTrait, vtable: &ItemStruct) -> syn::Item
                 let name = format_ident!("arg{}", i);
                 arg.name = Some((name, Default::default()));
                 utils::map_ty(&mut arg.ty, &mapper);
+                utils::map_ty_generics(&mut arg.ty, &map_generics);
                 arg
             })
             .collect::<Vec<_>>();
@@ -110,20 +128,25 @@ pub fn impl_vtable_methods(input: &ItemTrait, vtable: &ItemStruct) -> syn::Item
             let pass_in_args = inputs
                 .clone()
                 .into_iter()
-                .map(|arg| {
+                .enumerate()
+                .map(|(i, arg)| {
                     let name = arg.name.unwrap().0;
 
-                    if let Type::Path(path) = &arg.ty {
+                    // only the receiver is cast back to the implementer, other `CRef`s are passed on
+                    if i == 0
+                        && f.sig.receiver().is_some()
+                        && let Type::Path(path) = &arg.ty
+                    {
                         let path = path.path.segments.first().map(|s| s.ident.to_string());
-                        match path.as_ref().map(|s| s.as_str()) {
+                        match path.as_deref() {
                             Some("CRef") => {
                                 quote! {
-                                    &*(#name.as_ptr() as *const GEN)
+                                    &*#name.as_ptr().cast::<GEN>()
                                 }
                             }
                             Some("CRefMut") => {
                                 quote! {
-                                    &mut *(#name.as_ptr() as *mut GEN)
+                                    &mut *#name.as_mut_ptr().cast::<GEN>()
                                 }
                             }
                             _ => {
@@ -140,23 +163,40 @@ pub fn impl_vtable_methods(input: &ItemTrait, vtable: &ItemStruct) -> syn::Item
                 })
                 .collect::<Vec<_>>();
 
-            let mut output = f.sig.output.clone();
+            let mut output = lowering::lowered_output(&f.sig);
 
             if let syn::ReturnType::Type(_, ref mut ty) = output {
                 utils::map_ty(ty, &mapper);
                 utils::map_ty_generics(ty, &map_generics);
             }
 
+            // futures and iterators are boxed in a `'static` vtable for their output
+            let shim_generics = if lowering::needs_static_generics(&f.sig) {
+                let mut generics = static_generics(&renamed_generics);
+                generics
+                    .params
+                    .push(parse_quote!(GEN: #trait_ident #renamed_generics));
+                generics
+            } else {
+                method_generics.clone()
+            };
+            let call = lowering::lower_call(
+                &f.sig,
+                quote! {
+                    GEN::#method_name(
+                        #(#pass_in_args),*
+                    )
+                },
+            );
+
             quote! {
                 #method_name: {
-                    unsafe extern "C" fn #method_name #method_generics(
+                    unsafe extern "C" fn #method_name #shim_generics(
                         #(#inputs),*
                     ) #output {
                         #[allow(unsafe_code)]
                         unsafe {
-                            GEN::#method_name(
-                                #(#pass_in_args),*
-                            )
+                            #call
                         }
                     }
                     #method_name::<#(#method_generics_names),*>
@@ -169,14 +209,14 @@ pub fn impl_vtable_methods(input: &ItemTrait, vtable: &ItemStruct) -> syn::Item
         .fields
         .iter()
         .filter_map(|field| {
-            if let Type::Reference(ty) = &field.ty {
-                if let Type::Path(path) = &*ty.elem {
-                    let ident = field.ident.as_ref().unwrap();
-                    let super_vtable_ty = path.path.segments.first().unwrap().ident.clone();
-                    return Some(quote! {
-                        #ident: #super_vtable_ty::create_vtable::<GEN>()
-                    });
-                }
+            if let Type::Reference(ty) = &field.ty
+                && let Type::Path(path) = &*ty.elem
+            {
+                let ident = field.ident.as_ref().unwrap();
+                let super_vtable_ty = path.path.segments.first().unwrap().ident.clone();
+                return Some(quote! {
+                    #ident: #super_vtable_ty::create_vtable::<GEN>()
+                });
             }
 
             None
@@ -184,8 +224,14 @@ pub fn impl_vtable_methods(input: &ItemTrait, vtable: &ItemStruct) -> syn::Item
         .collect::<Vec<_>>();
 
     methods.extend(super_trait_field);
-
-    let methods = if methods.len() > 0 {
+    methods.extend(crate::std_traits::static_slots(
+        slot_traits,
+        &quote! { #vtable_ident #renamed_generics },
+        &method_generics,
+        &method_generics_names,
+    ));
+
+    let methods = if !methods.is_empty() {
         quote! {
             #(#methods),*,
         }
@@ -195,48 +241,111 @@ pub fn impl_vtable_methods(input: &ItemTrait, vtable: &ItemStruct) -> syn::Item
 
     let vtable_creator = quote! {
     #vtable_ident {
+        vtable_size: ::core::mem::size_of::<Self>(),
         #methods
 
        drop: {
            unsafe extern "C" fn drop #method_generics(arg_0: CRefMut<#vtable_ident #renamed_generics>) {
                #[allow(unsafe_code)]
                unsafe {
-                   ::core::mem::drop(Box::from_raw(arg_0.as_ptr() as *mut GEN));
+                   arg_0.drop_in_place::<GEN>();
                }
            }
            drop::<#(#method_generics_names),*>
        },
       }};
 
-    let mut generics_params = renamed_generics.clone();
-
-    generics_params
-        .params
-        .push(parse_quote!(GEN: #trait_ident #renamed_generics + 'static));
+    // the vtables of super traits may have bounds of their own, and the comparison slots only
+    // identify `'static` implementers, see `implementation_id`
+    let super_vtables = vtable.fields.iter().filter_map(|field| match &field.ty {
+        Type::Reference(ty) => Some(&ty.elem),
+        _ => None,
+    });
+    let static_bound = slot_traits
+        .iter()
+        .any(|slot_trait| slot_trait == "PartialEq")
+        .then(|| quote! { GEN: 'static, });
 
     parse_quote! {
-        impl #static_generics #vtable_ident #generics {
+        impl #impl_generics StaticVTable<GEN> for #vtable_ident #generics
+        where
+            #(#super_vtables: StaticVTable<GEN>,)*
+            #static_bound
+        {
+            const VTABLE: Self = #vtable_creator;
+        }
+    }
+}
+
+pub fn impl_vtable_methods(input: &ItemTrait, vtable: &ItemStruct) -> syn::Item {
+    let generics = &input.generics;
+    let trait_ident = &input.ident;
+    let vtable_ident = &vtable.ident;
+    let static_generics = static_generics(generics);
+
+    let new_boxed = quote! {
+        crusty_alloc! {
             /// Creates a new vtable for the type GEN that implements the trait
             pub fn new_boxed<GEN: #trait_ident #generics + 'static>(input: GEN) -> CRepr<#vtable_ident #generics> {
                 let vtable  = #vtable_ident::create_vtable::<GEN>();
                 CRepr::new_boxed(vtable, input)
             }
 
-            /// Creates a new vtable for the type GEN then store in a static variable in the heap
-            pub fn create_vtable<GEN: #trait_ident #generics + 'static>() -> &'static #vtable_ident #generics {
-                   static FN_MAP: std::sync::LazyLock<std::sync::Mutex<std::collections::HashMap<std::any::TypeId, &'static (dyn std::any::Any + Send + Sync)>>> =
-                        std::sync::LazyLock::new(|| std::sync::Mutex::new(std::collections::HashMap::new()));
+            /// Creates a new boxed object for the type GEN, which may borrow data for `'scope`
+            pub fn new_scoped<'scope, GEN: #trait_ident #generics + 'scope>(input: GEN) -> CReprScoped<'scope, #vtable_ident #generics>
+            where
+                Self: StaticVTable<GEN>,
+            {
+                let vtable  = #vtable_ident::create_vtable::<GEN>();
+                CReprScoped::new_boxed(vtable, input)
+            }
+        }
+    };
 
-                    let type_id = std::any::TypeId::of::<GEN>();
+    parse_quote! {
+        impl #static_generics #vtable_ident #generics {
+            #new_boxed
 
-                    let mut map = FN_MAP.lock().unwrap();
-                    let entry = map.entry(type_id).or_insert_with(|| {
-                        let vtable = Box::new(#vtable_creator);
-                        Box::leak(vtable)
-                    });
-                    entry.downcast_ref().unwrap()
+            /// Creates a new object for the type GEN with its context allocated by `allocator`
+            pub fn new_in<GEN: #trait_ident #generics + 'static, A: Allocator + 'static>(allocator: A, input: GEN) -> Result<CRepr<#vtable_ident #generics>, GEN> {
+                let vtable = #vtable_ident::create_vtable::<GEN>();
+                CRepr::new_in(vtable, allocator, input)
+            }
 
+            /// Creates a new object for the type GEN with its context written into `buffer`
+            /// # Safety
+            /// `buffer` must be valid for reads and writes of `layout` until the object is dropped
+            pub unsafe fn new_in_place<GEN: #trait_ident #generics + 'static>(buffer: ::core::ptr::NonNull<u8>, layout: ::core::alloc::Layout, input: GEN) -> Result<CRepr<#vtable_ident #generics>, GEN> {
+                let vtable = #vtable_ident::create_vtable::<GEN>();
+                unsafe { CRepr::new_in_place(vtable, buffer, layout, input) }
+            }
 
+            /// Borrows `value` as a `CRef` to the trait object for as long as it is borrowed
+            pub fn borrow<GEN: #trait_ident #generics>(value: &GEN) -> CRef<'_, #vtable_ident #generics>
+            where
+                Self: StaticVTable<GEN>,
+            {
+                let vtable = #vtable_ident::create_vtable::<GEN>();
+                #[allow(unsafe_code)]
+                unsafe { CRef::from_raw_parts(::core::ptr::NonNull::from(vtable), ::core::ptr::NonNull::from(value).cast()) }
+            }
+
+            /// Borrows `value` as a `CRefMut` to the trait object for as long as it is borrowed
+            pub fn borrow_mut<GEN: #trait_ident #generics>(value: &mut GEN) -> CRefMut<'_, #vtable_ident #generics>
+            where
+                Self: StaticVTable<GEN>,
+            {
+                let vtable = #vtable_ident::create_vtable::<GEN>();
+                #[allow(unsafe_code)]
+                unsafe { CRefMut::from_raw_parts(::core::ptr::NonNull::from(vtable), ::core::ptr::NonNull::from(value).cast()) }
+            }
+
+            /// Returns the vtable for the type GEN, built at compile time and promoted to a static
+            pub const fn create_vtable<GEN: #trait_ident #generics>() -> &'static #vtable_ident #generics
+            where
+                Self: StaticVTable<GEN>,
+            {
+                &<Self as StaticVTable<GEN>>::VTABLE
             }
         }
     }
@@ -247,6 +356,104 @@ mod tests {
     use super::*;
     use syn::parse_quote;
 
+    #[test]
+    fn test_impl_static_vtable() {
+        let input: ItemTrait = parse_quote! {
+            trait MyTrait<T> {
+                fn my_method(&self, value: T) -> T;
+            }
+        };
+
+        let vtable: ItemStruct = parse_quote! {
+            struct MyTraitVTable<T> {
+                my_method: unsafe extern "C" fn(arg0: CRef<MyTraitVTable<T>>, arg1: T) -> T,
+                drop: unsafe extern "C" fn(arg0: CRefMut<MyTraitVTable<T>>),
+            }
+        };
+
+        let result = impl_static_vtable(&input, &vtable, &[]);
+
+        let expected: syn::ItemImpl = parse_quote! {
+            impl<T: 'static, GEN: MyTrait<T>> StaticVTable<GEN> for MyTraitVTable<T> {
+                const VTABLE: Self = MyTraitVTable {
+                    vtable_size: ::core::mem::size_of::<Self>(),
+                    my_method: {
+                        unsafe extern "C" fn my_method<TMETHOD, GEN: MyTrait<TMETHOD>>(arg0: CRef<MyTraitVTable<TMETHOD>>, arg1: TMETHOD) -> TMETHOD {
+                            #[allow(unsafe_code)]
+                            unsafe {
+                                GEN::my_method(&*arg0.as_ptr().cast::<GEN>(), arg1)
+                            }
+                        }
+                        my_method::<T, GEN>
+                    },
+                    drop: {
+                        unsafe extern "C" fn drop<TMETHOD, GEN: MyTrait<TMETHOD>>(arg_0: CRefMut<MyTraitVTable<TMETHOD>>) {
+                            #[allow(unsafe_code)]
+                            unsafe {
+                                arg_0.drop_in_place::<GEN>();
+                            }
+                        }
+                        drop::<T, GEN>
+                    },
+                };
+            }
+        };
+        let string_expected =
+            utils::test_utils::item_to_pretty_string(syn::Item::Impl(expected.clone()));
+        let string_result = utils::test_utils::item_to_pretty_string(result.clone());
+        assert_eq!(
+            string_result, string_expected,
+            "Generated impl does not match expected impl: expected:\n{}\n\nGot:\n{}",
+            string_expected, string_result
+        );
+    }
+
+    #[test]
+    fn test_impl_static_vtable_object_arguments() {
+        let input: ItemTrait = parse_quote! {
+            trait MyTrait<T> {
+                fn merge(&mut self, other: CRefMut<'_, MyTraitVTable<T>>);
+            }
+        };
+
+        let vtable: ItemStruct = parse_quote! {
+            struct MyTraitVTable<T> {
+                merge: unsafe extern "C" fn(arg0: CRefMut<MyTraitVTable<T>>, arg1: CRefMut<'_, MyTraitVTable<T>>),
+                drop: unsafe extern "C" fn(arg0: CRefMut<MyTraitVTable<T>>),
+            }
+        };
+
+        let result =
+            utils::test_utils::item_to_pretty_string(impl_static_vtable(&input, &vtable, &[]));
+        // only the receiver is cast to the implementer, with the trait's generics renamed
+        assert!(result.contains("arg1: CRefMut<'_, MyTraitVTable<TMETHOD>>"));
+        assert!(result.contains("GEN::merge(&mut *arg0.as_mut_ptr().cast::<GEN>(), arg1)"));
+    }
+
+    #[test]
+    fn test_impl_static_vtable_bounds() {
+        let input: ItemTrait = parse_quote! {
+            trait MyTrait: Named + PartialEq {}
+        };
+
+        let vtable: ItemStruct = parse_quote! {
+            struct MyTraitVTable {
+                super_named: &'static NamedVTable,
+                drop: unsafe extern "C" fn(arg0: CRefMut<MyTraitVTable>),
+            }
+        };
+
+        let slot_traits = [Ident::new("PartialEq", proc_macro2::Span::call_site())];
+        let result = utils::test_utils::item_to_pretty_string(impl_static_vtable(
+            &input,
+            &vtable,
+            &slot_traits,
+        ));
+        // the super trait's vtable is built for `GEN` too, and only `'static` types are compared
+        assert!(result.contains("NamedVTable: StaticVTable<GEN>,"));
+        assert!(result.contains("GEN: 'static,"));
+    }
+
     #[test]
     fn test_impl_vtable_methods() {
         let input: ItemTrait = parse_quote! {
@@ -265,45 +472,65 @@ mod tests {
         let result = impl_vtable_methods(&input, &vtable);
 
         let expected: syn::ItemImpl = parse_quote! {
-        impl<T: 'static> MyTraitVTable<T> {
-            /// Creates a new vtable for the type GEN that implements the trait
-            pub fn new_boxed<GEN: MyTrait<T> + 'static>(input: GEN) -> CRepr<MyTraitVTable<T>> {
-                let vtable  = MyTraitVTable::create_vtable::<GEN>();
-                CRepr::new_boxed(vtable, input)
-            }
+            impl<T: 'static> MyTraitVTable<T> {
+                // the tokens in a macro call are printed as written, so `> >` is not joined
+                crusty_alloc! {
+                    /// Creates a new vtable for the type GEN that implements the trait
+                    pub fn new_boxed<GEN: MyTrait<T> + 'static>(input: GEN) -> CRepr<MyTraitVTable<T> > {
+                        let vtable  = MyTraitVTable::create_vtable::<GEN>();
+                        CRepr::new_boxed(vtable, input)
+                    }
 
-            /// Creates a new vtable for the type GEN then store in a static variable in the heap
-            pub fn create_vtable<GEN: MyTrait<T> + 'static>() -> &'static MyTraitVTable<T> {
-                   static FN_MAP: std::sync::LazyLock<std::sync::Mutex<std::collections::HashMap<std::any::TypeId, &'static (dyn std::any::Any + Send + Sync)>>> =
-                        std::sync::LazyLock::new(|| std::sync::Mutex::new(std::collections::HashMap::new()));
-
-                    let type_id = std::any::TypeId::of::<GEN>();
-
-                    let mut map = FN_MAP.lock().unwrap();
-                    let entry = map.entry(type_id).or_insert_with(|| {
-                        let vtable = Box::new(MyTraitVTable {
-                            my_method: {
-                                unsafe extern "C" fn my_method<TMETHOD, GEN: MyTrait<TMETHOD>>(arg0: CRef<MyTraitVTable<TMETHOD>>, arg1: TMETHOD) -> TMETHOD {
-                                    #[allow(unsafe_code)]
-                                    unsafe {
-                                        GEN::my_method(&*(arg0.as_ptr() as *const GEN), arg1)
-                                    }
-                                }
-                                my_method::<T, GEN>
-                            },
-                            drop: {
-                                unsafe extern "C" fn drop<TMETHOD, GEN: MyTrait<TMETHOD>>(arg_0: CRefMut<MyTraitVTable<TMETHOD>>) {
-                                    #[allow(unsafe_code)]
-                                    unsafe {
-                                        ::core::mem::drop(Box::from_raw(arg_0.as_ptr() as *mut GEN));
-                                    }
-                                }
-                                drop::<T, GEN>
-                            },
-                        });
-                        Box::leak(vtable)
-                    });
-                    entry.downcast_ref().unwrap()
+                    /// Creates a new boxed object for the type GEN, which may borrow data for `'scope`
+                    pub fn new_scoped<'scope, GEN: MyTrait<T> + 'scope>(input: GEN) -> CReprScoped<'scope, MyTraitVTable<T> >
+                    where
+                        Self: StaticVTable<GEN>,
+                    {
+                        let vtable  = MyTraitVTable::create_vtable::<GEN>();
+                        CReprScoped::new_boxed(vtable, input)
+                    }
+                }
+
+                /// Creates a new object for the type GEN with its context allocated by `allocator`
+                pub fn new_in<GEN: MyTrait<T> + 'static, A: Allocator + 'static>(allocator: A, input: GEN) -> Result<CRepr<MyTraitVTable<T>>, GEN> {
+                    let vtable = MyTraitVTable::create_vtable::<GEN>();
+                    CRepr::new_in(vtable, allocator, input)
+                }
+
+                /// Creates a new object for the type GEN with its context written into `buffer`
+                /// # Safety
+                /// `buffer` must be valid for reads and writes of `layout` until the object is dropped
+                pub unsafe fn new_in_place<GEN: MyTrait<T> + 'static>(buffer: ::core::ptr::NonNull<u8>, layout: ::core::alloc::Layout, input: GEN) -> Result<CRepr<MyTraitVTable<T>>, GEN> {
+                    let vtable = MyTraitVTable::create_vtable::<GEN>();
+                    unsafe { CRepr::new_in_place(vtable, buffer, layout, input) }
+                }
+
+                /// Borrows `value` as a `CRef` to the trait object for as long as it is borrowed
+                pub fn borrow<GEN: MyTrait<T>>(value: &GEN) -> CRef<'_, MyTraitVTable<T>>
+                where
+                    Self: StaticVTable<GEN>,
+                {
+                    let vtable = MyTraitVTable::create_vtable::<GEN>();
+                    #[allow(unsafe_code)]
+                    unsafe { CRef::from_raw_parts(::core::ptr::NonNull::from(vtable), ::core::ptr::NonNull::from(value).cast()) }
+                }
+
+                /// Borrows `value` as a `CRefMut` to the trait object for as long as it is borrowed
+                pub fn borrow_mut<GEN: MyTrait<T>>(value: &mut GEN) -> CRefMut<'_, MyTraitVTable<T>>
+                where
+                    Self: StaticVTable<GEN>,
+                {
+                    let vtable = MyTraitVTable::create_vtable::<GEN>();
+                    #[allow(unsafe_code)]
+                    unsafe { CRefMut::from_raw_parts(::core::ptr::NonNull::from(vtable), ::core::ptr::NonNull::from(value).cast()) }
+                }
+
+                /// Returns the vtable for the type GEN, built at compile time and promoted to a static
+                pub const fn create_vtable<GEN: MyTrait<T>>() -> &'static MyTraitVTable<T>
+                where
+                    Self: StaticVTable<GEN>,
+                {
+                    &<Self as StaticVTable<GEN>>::VTABLE
                 }
             }
         };
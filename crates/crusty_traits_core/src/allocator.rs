@@ -0,0 +1,58 @@
+use core::{alloc::Layout, ptr::NonNull};
+
+/// An allocator that can hold the context of a [`CRepr`](crate::CRepr).
+///
+/// This mirrors the unstable `core::alloc::Allocator` so contexts can live in arenas, pools or
+/// shared memory regions on stable Rust.
+///
+/// # Safety
+/// Memory returned by `allocate` must be valid for `layout` until it is passed to `deallocate`,
+/// and moving the allocator must not invalidate it.
+#[allow(unsafe_code)]
+pub unsafe trait Allocator {
+    /// Allocates a block of memory fitting `layout`, returning `None` if it is exhausted.
+    fn allocate(&self, layout: Layout) -> Option<NonNull<u8>>;
+
+    /// Frees a block of memory returned by `allocate`.
+    /// # Safety
+    /// `ptr` must have been returned by `allocate` on this allocator with the same `layout`.
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout);
+}
+
+#[allow(unsafe_code)]
+// SAFETY: Forwards to the referenced allocator.
+unsafe impl<A: Allocator + ?Sized> Allocator for &A {
+    fn allocate(&self, layout: Layout) -> Option<NonNull<u8>> {
+        (**self).allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        // SAFETY: Upheld by the caller.
+        unsafe { (**self).deallocate(ptr, layout) }
+    }
+}
+
+/// The global allocator, as used by `Box`.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Global;
+
+#[cfg(feature = "alloc")]
+#[allow(unsafe_code)]
+// SAFETY: Blocks come from the global allocator, zero sized blocks are never allocated.
+unsafe impl Allocator for Global {
+    fn allocate(&self, layout: Layout) -> Option<NonNull<u8>> {
+        if layout.size() == 0 {
+            return NonNull::new(core::ptr::without_provenance_mut(layout.align()));
+        }
+        // SAFETY: The layout has a non-zero size.
+        NonNull::new(unsafe { alloc::alloc::alloc(layout) })
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() != 0 {
+            // SAFETY: Upheld by the caller.
+            unsafe { alloc::alloc::dealloc(ptr.as_ptr(), layout) }
+        }
+    }
+}
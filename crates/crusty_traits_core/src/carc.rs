@@ -0,0 +1,303 @@
+use alloc::boxed::Box;
+use core::{
+    marker::PhantomData,
+    mem::{ManuallyDrop, offset_of},
+    ops::Deref,
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering, fence},
+};
+
+use crate::{CRef, CSend, CSync, trait_wrapper::Inner};
+
+/// The reference counts shared by a [`CArc`] and its [`CWeak`]s, stored in front of the context.
+///
+/// C code that receives a `CArc` keeps it alive with `retain` and gives it up with `release`.
+#[repr(C)]
+pub struct CArcHeader {
+    strong: AtomicUsize,
+    weak: AtomicUsize,
+    drop_context: unsafe extern "C" fn(header: NonNull<CArcHeader>),
+    dealloc: unsafe extern "C" fn(header: NonNull<CArcHeader>),
+    /// Adds a strong reference.
+    pub retain: unsafe extern "C" fn(header: NonNull<CArcHeader>),
+    /// Removes a strong reference, dropping the context when it was the last one.
+    pub release: unsafe extern "C" fn(header: NonNull<CArcHeader>),
+}
+
+/// The allocation behind a [`CArc`], with the header in front of the context.
+#[repr(C)]
+struct ArcBlock<C> {
+    header: CArcHeader,
+    context: ManuallyDrop<C>,
+}
+
+#[allow(unsafe_code)]
+unsafe extern "C" fn drop_context<C>(header: NonNull<CArcHeader>) {
+    // SAFETY: The header is the start of an `ArcBlock<C>` whose last strong reference is gone.
+    unsafe { ManuallyDrop::drop(&mut (*header.cast::<ArcBlock<C>>().as_ptr()).context) }
+}
+
+#[allow(unsafe_code)]
+unsafe extern "C" fn dealloc<C>(header: NonNull<CArcHeader>) {
+    // SAFETY: The block was allocated as a `Box<ArcBlock<C>>` and has no references left.
+    drop(unsafe { Box::from_raw(header.cast::<ArcBlock<C>>().as_ptr()) });
+}
+
+/// The largest reference count, kept well below `usize::MAX` so that the increments racing
+/// past it can't wrap the count around to zero before one of them aborts.
+const MAX_REFCOUNT: usize = isize::MAX as usize;
+
+/// Aborts the process when a reference count exceeds [`MAX_REFCOUNT`], like `std::sync::Arc`,
+/// as the count could otherwise wrap and free the object while it is still referenced.
+#[cold]
+extern "C" fn refcount_overflow() -> ! {
+    // the panic can't unwind out of an `extern "C"` function, so it aborts even without `std`
+    panic!("reference count overflow");
+}
+
+#[allow(unsafe_code)]
+unsafe extern "C" fn retain(header: NonNull<CArcHeader>) {
+    // SAFETY: The caller holds a strong reference, so the header is live.
+    let header = unsafe { header.as_ref() };
+    if header.strong.fetch_add(1, Ordering::Relaxed) > MAX_REFCOUNT {
+        refcount_overflow();
+    }
+}
+
+#[allow(unsafe_code)]
+unsafe extern "C" fn release(header: NonNull<CArcHeader>) {
+    // SAFETY: The caller holds a strong reference, so the header is live.
+    let counts = unsafe { header.as_ref() };
+    if counts.strong.fetch_sub(1, Ordering::Release) != 1 {
+        return;
+    }
+    fence(Ordering::Acquire);
+    // SAFETY: This was the last strong reference, and the strong references share one weak
+    // reference that keeps the block alive until it is released below.
+    unsafe {
+        (counts.drop_context)(header);
+        release_weak(header);
+    }
+}
+
+#[allow(unsafe_code)]
+unsafe fn release_weak(header: NonNull<CArcHeader>) {
+    // SAFETY: The caller holds a weak reference, so the header is live.
+    let counts = unsafe { header.as_ref() };
+    if counts.weak.fetch_sub(1, Ordering::Release) == 1 {
+        fence(Ordering::Acquire);
+        // SAFETY: This was the last reference of any kind.
+        unsafe { (counts.dealloc)(header) }
+    }
+}
+
+/// A reference counted trait object, shared between its owners.
+///
+/// Only `&self` methods can be called, cloning adds a strong reference and the context is
+/// dropped when the last one is released.
+#[repr(C)]
+pub struct CArc<T: ?Sized> {
+    inner: Inner<T>,
+    header: NonNull<CArcHeader>,
+}
+
+/// A weak reference to a [`CArc`], which does not keep the context alive.
+#[repr(C)]
+pub struct CWeak<T: ?Sized> {
+    inner: Inner<T>,
+    header: NonNull<CArcHeader>,
+}
+
+#[allow(unsafe_code)]
+// SAFETY: The context is `Send + Sync` for a `CSend + CSync` vtable and the counts are atomic.
+unsafe impl<T: CSend + CSync + ?Sized> Send for CArc<T> {}
+#[allow(unsafe_code)]
+// SAFETY: The context is `Send + Sync` for a `CSend + CSync` vtable and the counts are atomic.
+unsafe impl<T: CSend + CSync + ?Sized> Sync for CArc<T> {}
+#[allow(unsafe_code)]
+// SAFETY: The context is `Send + Sync` for a `CSend + CSync` vtable and the counts are atomic.
+unsafe impl<T: CSend + CSync + ?Sized> Send for CWeak<T> {}
+#[allow(unsafe_code)]
+// SAFETY: The context is `Send + Sync` for a `CSend + CSync` vtable and the counts are atomic.
+unsafe impl<T: CSend + CSync + ?Sized> Sync for CWeak<T> {}
+
+impl<T> CArc<T> {
+    /// Creates a new `CArc` from a vtable and context.
+    pub fn new<C: Send + Sync + 'static>(vtable: &'static T, context: C) -> Self {
+        let block = Box::new(ArcBlock {
+            header: CArcHeader {
+                strong: AtomicUsize::new(1),
+                weak: AtomicUsize::new(1),
+                drop_context: drop_context::<C>,
+                dealloc: dealloc::<C>,
+                retain,
+                release,
+            },
+            context: ManuallyDrop::new(context),
+        });
+        let block = NonNull::from(Box::leak(block));
+        #[allow(unsafe_code)]
+        // SAFETY: The offset of the context stays within the block.
+        let context = unsafe { block.byte_add(offset_of!(ArcBlock<C>, context)) };
+
+        Self {
+            inner: Inner {
+                vtable: NonNull::from(vtable),
+                ptr: context.cast(),
+            },
+            header: block.cast(),
+        }
+    }
+}
+
+impl<T: ?Sized> CArc<T> {
+    fn header(&self) -> &CArcHeader {
+        #[allow(unsafe_code)]
+        // SAFETY: The header lives as long as any reference to it.
+        unsafe {
+            self.header.as_ref()
+        }
+    }
+
+    /// Returns the number of strong references.
+    pub fn strong_count(&self) -> usize {
+        self.header().strong.load(Ordering::Acquire)
+    }
+
+    /// Returns the number of weak references.
+    pub fn weak_count(&self) -> usize {
+        // the strong references share one weak reference
+        self.header().weak.load(Ordering::Acquire) - 1
+    }
+
+    /// Creates a weak reference to the object.
+    pub fn downgrade(&self) -> CWeak<T> {
+        if self.header().weak.fetch_add(1, Ordering::Relaxed) > MAX_REFCOUNT {
+            refcount_overflow();
+        }
+        CWeak {
+            inner: self.inner,
+            header: self.header,
+        }
+    }
+
+    /// Maps the vtable to a new type using the provided function.
+    /// # Safety
+    /// The caller must ensure that `methods` is a vtable for the same implementer as this object.
+    #[allow(unsafe_code)]
+    pub unsafe fn as_cref_with_methods<U: ?Sized>(&self, methods: NonNull<U>) -> CRef<'_, U> {
+        CRef {
+            // SAFETY: Upheld by the caller.
+            inner: unsafe { self.inner.map_vtable(|_| methods) },
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns a pointer to the context.
+    pub fn as_ptr(&self) -> *const () {
+        self.inner.ptr.cast::<()>().as_ptr()
+    }
+
+    /// Returns a reference to the vtable.
+    pub fn get_vtable(&self) -> &T {
+        #[allow(unsafe_code)]
+        // SAFETY: The vtable pointer always points to a live `'static` vtable.
+        unsafe {
+            self.inner.vtable.as_ref()
+        }
+    }
+
+    /// Returns a cref
+    pub fn as_cref(&self) -> CRef<'_, T> {
+        CRef {
+            inner: self.inner,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> Deref for CArc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.get_vtable()
+    }
+}
+
+impl<T: ?Sized> Clone for CArc<T> {
+    fn clone(&self) -> Self {
+        #[allow(unsafe_code)]
+        // SAFETY: `self` holds a strong reference.
+        unsafe {
+            (self.header().retain)(self.header);
+        }
+        Self {
+            inner: self.inner,
+            header: self.header,
+        }
+    }
+}
+
+impl<T: ?Sized> Drop for CArc<T> {
+    fn drop(&mut self) {
+        #[allow(unsafe_code)]
+        // SAFETY: `self` holds a strong reference, which is given up here.
+        unsafe {
+            (self.header().release)(self.header);
+        }
+    }
+}
+
+impl<T: ?Sized> CWeak<T> {
+    fn header(&self) -> &CArcHeader {
+        #[allow(unsafe_code)]
+        // SAFETY: The header lives as long as any reference to it.
+        unsafe {
+            self.header.as_ref()
+        }
+    }
+
+    /// Returns a strong reference to the object, or `None` if it has already been dropped.
+    pub fn upgrade(&self) -> Option<CArc<T>> {
+        self.header()
+            .strong
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |strong| {
+                if strong > MAX_REFCOUNT {
+                    refcount_overflow();
+                }
+                (strong != 0).then_some(strong + 1)
+            })
+            .ok()?;
+        Some(CArc {
+            inner: self.inner,
+            header: self.header,
+        })
+    }
+
+    /// Returns the number of strong references.
+    pub fn strong_count(&self) -> usize {
+        self.header().strong.load(Ordering::Acquire)
+    }
+}
+
+impl<T: ?Sized> Clone for CWeak<T> {
+    fn clone(&self) -> Self {
+        if self.header().weak.fetch_add(1, Ordering::Relaxed) > MAX_REFCOUNT {
+            refcount_overflow();
+        }
+        Self {
+            inner: self.inner,
+            header: self.header,
+        }
+    }
+}
+
+impl<T: ?Sized> Drop for CWeak<T> {
+    fn drop(&mut self) {
+        #[allow(unsafe_code)]
+        // SAFETY: `self` holds a weak reference, which is given up here.
+        unsafe {
+            release_weak(self.header);
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use core::{any::TypeId, cmp::Ordering, mem};
+
+#[cfg(feature = "alloc")]
+use crate::CArc;
+use crate::{CDrop, CRef, CRefMut, CRepr, CReprInline, CReprScoped};
+
+/// A trait for the vtables of traits with a `PartialEq` super trait.
+///
+/// Implemented by the `crusty_trait` macro so the wrappers of the vtable are `PartialEq`.
+/// Objects of different implementations are never equal, unless the vtable overrides
+/// [`CPartialEq::eq_objects`].
+pub trait CPartialEq: CDrop {
+    /// Returns the identifier of the implementation of the object with the `implementation`
+    /// slot, see [`implementation_id`].
+    fn implementation(repr: CRef<Self>) -> CImplementationId;
+
+    /// Compares two objects with the `eq` slot.
+    /// # Safety
+    /// Both objects must share an implementation, see [`same_implementation`].
+    #[allow(unsafe_code)]
+    unsafe fn eq(a: CRef<Self>, b: CRef<Self>) -> bool;
+
+    /// Compares two objects that may be of different implementations.
+    fn eq_objects(a: CRef<Self>, b: CRef<Self>) -> bool
+    where
+        Self: Sized,
+    {
+        if !same_implementation(a, b) {
+            return false;
+        }
+        #[allow(unsafe_code)]
+        // SAFETY: The objects share an implementation.
+        unsafe {
+            Self::eq(a, b)
+        }
+    }
+}
+
+/// A trait for the vtables of traits with an `Eq` super trait.
+pub trait CEq: CPartialEq {}
+
+/// A trait for the vtables of traits with a `PartialOrd` super trait.
+///
+/// Objects of different implementations are ordered by the identifier of their implementation.
+pub trait CPartialOrd: CPartialEq {
+    /// Compares two objects with the `partial_cmp` slot.
+    /// # Safety
+    /// Both objects must share an implementation, see [`same_implementation`].
+    #[allow(unsafe_code)]
+    unsafe fn partial_cmp(a: CRef<Self>, b: CRef<Self>) -> Option<Ordering>;
+}
+
+/// A trait for the vtables of traits with an `Ord` super trait.
+///
+/// Objects of different implementations are ordered by the identifier of their implementation.
+pub trait COrd: CEq + CPartialOrd {
+    /// Compares two objects with the `cmp` slot.
+    /// # Safety
+    /// Both objects must share an implementation, see [`same_implementation`].
+    #[allow(unsafe_code)]
+    unsafe fn cmp(a: CRef<Self>, b: CRef<Self>) -> Ordering;
+}
+
+/// Returns whether two objects share an implementation, which is the case when their vtables
+/// return the same [`implementation_id`].
+///
+/// The address of the vtable is not compared, as the vtable of a Rust implementer is a promoted
+/// constant that may be duplicated between crates, or shared with another implementer whose
+/// vtable holds the same function pointers.
+pub fn same_implementation<T: CPartialEq>(a: CRef<'_, T>, b: CRef<'_, T>) -> bool {
+    T::implementation(a) == T::implementation(b)
+}
+
+/// Orders objects of different implementations by the identifier of their implementation.
+fn implementation_order<T: CPartialEq>(a: CRef<'_, T>, b: CRef<'_, T>) -> Ordering {
+    T::implementation(a).cmp(&T::implementation(b))
+}
+
+/// The identifier of an implementation returned by the `implementation` slot, the 128 bits of a
+/// [`TypeId`] for Rust implementers.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CImplementationId {
+    /// The high 64 bits of the identifier.
+    pub high: u64,
+    /// The low 64 bits of the identifier.
+    pub low: u64,
+}
+
+impl From<u128> for CImplementationId {
+    fn from(id: u128) -> Self {
+        Self {
+            high: (id >> 64) as u64,
+            low: id as u64,
+        }
+    }
+}
+
+impl From<CImplementationId> for u128 {
+    fn from(id: CImplementationId) -> Self {
+        (u128::from(id.high) << 64) | u128::from(id.low)
+    }
+}
+
+/// Returns the identifier of the type `T` returned by the `implementation` slot of the vtables
+/// built for `T`, the full width of its [`TypeId`].
+///
+/// `T` must be `'static`, so the vtables of traits with comparison super traits are only built
+/// for `'static` implementers. An identifier ignoring lifetimes would let the slots compare a
+/// `Foo<'a>` with a `Foo<'b>`, which may move a borrow into an object that outlives it. C
+/// implementers return any identifier that is unique to their implementation.
+///
+/// ```compile_fail
+/// use crusty_traits_core::*;
+/// use crusty_traits_macros::crusty_trait;
+///
+/// #[crusty_trait]
+/// pub trait Name: PartialEq {
+///     fn size(&self) -> usize;
+/// }
+///
+/// impl Name for &str {
+///     fn size(&self) -> usize {
+///         self.len()
+///     }
+/// }
+///
+/// let local = String::from("local");
+/// let name = local.as_str();
+/// NameVTable::borrow(&name);
+/// ```
+pub fn implementation_id<T: ?Sized + 'static>() -> CImplementationId {
+    #[allow(unsafe_code)]
+    // SAFETY: `TypeId` is 128 bits wide, which `transmute` checks at compile time, and any bits
+    // are a valid `u128`.
+    let id = unsafe { mem::transmute::<TypeId, u128>(TypeId::of::<T>()) };
+    id.into()
+}
+
+impl<T: CPartialEq> PartialEq for CRef<'_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        T::eq_objects(*self, *other)
+    }
+}
+
+impl<T: CEq> Eq for CRef<'_, T> {}
+
+impl<T: CPartialOrd> PartialOrd for CRef<'_, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if !same_implementation(*self, *other) {
+            return Some(implementation_order(*self, *other));
+        }
+        #[allow(unsafe_code)]
+        // SAFETY: The objects share an implementation.
+        unsafe {
+            T::partial_cmp(*self, *other)
+        }
+    }
+}
+
+impl<T: COrd> Ord for CRef<'_, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if !same_implementation(*self, *other) {
+            return implementation_order(*self, *other);
+        }
+        #[allow(unsafe_code)]
+        // SAFETY: The objects share an implementation.
+        unsafe {
+            T::cmp(*self, *other)
+        }
+    }
+}
+
+/// Implements the comparison traits for an owning or mutable wrapper by comparing its `CRef`.
+macro_rules! impl_cmp {
+    ($([$($generics:tt)*] $wrapper:ty),* $(,)?) => {$(
+        impl<$($generics)* T: CPartialEq> PartialEq for $wrapper {
+            fn eq(&self, other: &Self) -> bool {
+                self.as_cref() == other.as_cref()
+            }
+        }
+
+        impl<$($generics)* T: CEq> Eq for $wrapper {}
+
+        impl<$($generics)* T: CPartialOrd> PartialOrd for $wrapper {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                self.as_cref().partial_cmp(&other.as_cref())
+            }
+        }
+
+        impl<$($generics)* T: COrd> Ord for $wrapper {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.as_cref().cmp(&other.as_cref())
+            }
+        }
+    )*};
+}
+
+impl_cmp!(
+    ['a,] CRefMut<'a, T>,
+    [] CRepr<T>,
+    ['a,] CReprScoped<'a, T>,
+    [const N: usize,] CReprInline<T, N>,
+);
+#[cfg(feature = "alloc")]
+impl_cmp!([] CArc<T>);
@@ -0,0 +1,133 @@
+use core::{
+    fmt::{self, Write},
+    marker::PhantomData,
+    ptr::NonNull,
+};
+
+#[cfg(feature = "alloc")]
+use crate::CArc;
+use crate::{CDrop, CRef, CRefMut, CRepr, CReprInline, CReprScoped};
+
+/// Writes `len` bytes of UTF-8 text to the destination of a [`CFormatter`].
+///
+/// Returns `false` if the destination failed, invalid UTF-8 is replaced with `U+FFFD`.
+pub type CWriteStr =
+    unsafe extern "C" fn(context: NonNull<()>, text: *const u8, len: usize) -> bool;
+
+/// An FFI-safe string sink passed to the `debug` and `display` slots of a vtable.
+///
+/// Rust implementers are formatted into it with `write!`, C implementers call `write_str` with
+/// their own text.
+#[repr(C)]
+pub struct CFormatter<'a> {
+    /// The destination passed to `write_str`.
+    pub context: NonNull<()>,
+    /// Writes text to the formatter.
+    pub write_str: CWriteStr,
+    /// Whether the alternate `#` flag was requested.
+    pub alternate: bool,
+    phantom: PhantomData<&'a mut ()>,
+}
+
+#[allow(unsafe_code)]
+unsafe extern "C" fn write_formatter(context: NonNull<()>, text: *const u8, len: usize) -> bool {
+    // SAFETY: The context is the `Formatter` borrowed by the `CFormatter`, and the caller passes
+    // `len` readable bytes.
+    let (f, text) = unsafe {
+        (
+            context.cast::<fmt::Formatter<'_>>().as_mut(),
+            core::slice::from_raw_parts(text, len),
+        )
+    };
+    text.utf8_chunks()
+        .try_for_each(|chunk| {
+            f.write_str(chunk.valid())?;
+            if chunk.invalid().is_empty() {
+                Ok(())
+            } else {
+                f.write_char(char::REPLACEMENT_CHARACTER)
+            }
+        })
+        .is_ok()
+}
+
+impl<'a> CFormatter<'a> {
+    /// Creates a sink that writes into a Rust formatter.
+    pub fn new(f: &'a mut fmt::Formatter<'_>) -> Self {
+        Self {
+            alternate: f.alternate(),
+            context: NonNull::from(f).cast(),
+            write_str: write_formatter,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl Write for CFormatter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        #[allow(unsafe_code)]
+        // SAFETY: The text is valid for `s.len()` bytes.
+        let written = unsafe { (self.write_str)(self.context, s.as_ptr(), s.len()) };
+        written.then_some(()).ok_or(fmt::Error)
+    }
+}
+
+/// A trait for the vtables of traits with a `Debug` super trait.
+///
+/// Implemented by the `crusty_trait` macro so the wrappers of the vtable are `Debug`.
+pub trait CDebug: CDrop {
+    /// Formats the object behind the given `CRef` with its `debug` slot.
+    fn fmt(repr: CRef<Self>, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+}
+
+/// A trait for the vtables of traits with a `Display` super trait.
+///
+/// Implemented by the `crusty_trait` macro so the wrappers of the vtable are `Display`.
+pub trait CDisplay: CDrop {
+    /// Formats the object behind the given `CRef` with its `display` slot.
+    fn fmt(repr: CRef<Self>, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+}
+
+macro_rules! impl_fmt {
+    ($c_trait:ident, $fmt_trait:ident) => {
+        impl<T: $c_trait> fmt::$fmt_trait for CRef<'_, T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                T::fmt(self.as_cref(), f)
+            }
+        }
+
+        impl<T: $c_trait> fmt::$fmt_trait for CRefMut<'_, T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                T::fmt(self.as_cref(), f)
+            }
+        }
+
+        impl<T: $c_trait> fmt::$fmt_trait for CRepr<T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                T::fmt(self.as_cref(), f)
+            }
+        }
+
+        impl<T: $c_trait> fmt::$fmt_trait for CReprScoped<'_, T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                T::fmt(self.as_cref(), f)
+            }
+        }
+
+        impl<T: $c_trait, const N: usize> fmt::$fmt_trait for CReprInline<T, N> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                T::fmt(self.as_cref(), f)
+            }
+        }
+
+        #[cfg(feature = "alloc")]
+        impl<T: $c_trait> fmt::$fmt_trait for CArc<T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                T::fmt(self.as_cref(), f)
+            }
+        }
+    };
+}
+
+impl_fmt!(CDebug, Debug);
+impl_fmt!(CDisplay, Display);
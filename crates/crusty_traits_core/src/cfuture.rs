@@ -0,0 +1,38 @@
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::{CDrop, CRefMut, CRepr, CReprInline, CReprScoped};
+
+/// A trait for the vtables of futures, such as the `CFutureVTable` of `crusty_traits_types`.
+///
+/// The wrappers of the vtable implement [`Future`] by calling [`CPollable::poll`]. Objects are
+/// not pinned, so contexts must stay valid when moved between polls.
+pub trait CPollable: CDrop {
+    /// The value the future resolves to.
+    type Output;
+
+    /// Polls the object behind the given `CRefMut` with its vtable.
+    fn poll(repr: CRefMut<Self>, cx: &mut Context<'_>) -> Poll<Self::Output>;
+}
+
+macro_rules! impl_future {
+    ($([$($generics:tt)*] $wrapper:ty),* $(,)?) => {$(
+        impl<$($generics)* T: CPollable> Future for $wrapper {
+            type Output = T::Output;
+
+            fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+                T::poll(self.get_mut().as_cref_mut(), cx)
+            }
+        }
+    )*};
+}
+
+impl_future!(
+    ['a,] CRefMut<'a, T>,
+    [] CRepr<T>,
+    ['a,] CReprScoped<'a, T>,
+    [const N: usize,] CReprInline<T, N>,
+);
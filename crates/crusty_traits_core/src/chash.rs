@@ -0,0 +1,100 @@
+use core::{
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    ptr::NonNull,
+};
+
+#[cfg(feature = "alloc")]
+use crate::CArc;
+use crate::{CDrop, CRef, CRefMut, CRepr, CReprInline, CReprScoped};
+
+/// An FFI-safe hasher passed to the `hash` slot of a vtable, which feeds a Rust [`Hasher`].
+///
+/// Rust implementers are hashed into it with [`Hash`], C implementers call `write` with the
+/// bytes that identify their value.
+#[repr(C)]
+pub struct CHasher<'a> {
+    /// The hasher passed to `write` and `finish`.
+    pub context: NonNull<()>,
+    /// Writes `len` bytes into the hasher.
+    pub write: unsafe extern "C" fn(context: NonNull<()>, bytes: *const u8, len: usize),
+    /// Returns the hash of the bytes written so far.
+    pub finish: unsafe extern "C" fn(context: NonNull<()>) -> u64,
+    phantom: PhantomData<&'a mut ()>,
+}
+
+#[allow(unsafe_code)]
+unsafe extern "C" fn write_hasher<H: Hasher>(context: NonNull<()>, bytes: *const u8, len: usize) {
+    // SAFETY: The context is the hasher borrowed by the `CHasher`, and the caller passes `len`
+    // readable bytes.
+    unsafe {
+        context
+            .cast::<H>()
+            .as_mut()
+            .write(core::slice::from_raw_parts(bytes, len));
+    }
+}
+
+#[allow(unsafe_code)]
+unsafe extern "C" fn finish_hasher<H: Hasher>(context: NonNull<()>) -> u64 {
+    // SAFETY: The context is the hasher borrowed by the `CHasher`.
+    unsafe { context.cast::<H>().as_ref().finish() }
+}
+
+impl<'a> CHasher<'a> {
+    /// Creates a hasher that writes into a Rust hasher.
+    pub fn new<H: Hasher>(state: &'a mut H) -> Self {
+        Self {
+            context: NonNull::from(state).cast(),
+            write: write_hasher::<H>,
+            finish: finish_hasher::<H>,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl Hasher for CHasher<'_> {
+    fn write(&mut self, bytes: &[u8]) {
+        #[allow(unsafe_code)]
+        // SAFETY: The bytes are valid for their length.
+        unsafe {
+            (self.write)(self.context, bytes.as_ptr(), bytes.len());
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        #[allow(unsafe_code)]
+        // SAFETY: The context is valid for the lifetime of the `CHasher`.
+        unsafe {
+            (self.finish)(self.context)
+        }
+    }
+}
+
+/// A trait for the vtables of traits with a `Hash` super trait.
+///
+/// Implemented by the `crusty_trait` macro so the wrappers of the vtable are `Hash`.
+pub trait CHash: CDrop {
+    /// Hashes the object behind the given `CRef` with its `hash` slot.
+    fn hash<H: Hasher>(repr: CRef<Self>, state: &mut H);
+}
+
+macro_rules! impl_hash {
+    ($([$($generics:tt)*] $wrapper:ty),* $(,)?) => {$(
+        impl<$($generics)* T: CHash> Hash for $wrapper {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                T::hash(self.as_cref(), state);
+            }
+        }
+    )*};
+}
+
+impl_hash!(
+    ['a,] CRef<'a, T>,
+    ['a,] CRefMut<'a, T>,
+    [] CRepr<T>,
+    ['a,] CReprScoped<'a, T>,
+    [const N: usize,] CReprInline<T, N>,
+);
+#[cfg(feature = "alloc")]
+impl_hash!([] CArc<T>);
@@ -0,0 +1,39 @@
+use crate::{CDrop, CRef, CRefMut, CRepr, CReprInline, CReprScoped};
+
+/// A trait for the vtables of iterators, such as the `CIteratorVTable` of `crusty_traits_types`.
+///
+/// The owning and mutable wrappers of the vtable implement [`Iterator`] by calling
+/// [`CIterable::next`].
+pub trait CIterable: CDrop {
+    /// The type of the items yielded by the iterator.
+    type Item;
+
+    /// Advances the object behind the given `CRefMut` with its vtable.
+    fn next(repr: CRefMut<Self>) -> Option<Self::Item>;
+
+    /// Returns the bounds on the remaining length of the object behind the given `CRef`.
+    fn size_hint(repr: CRef<Self>) -> (usize, Option<usize>);
+}
+
+macro_rules! impl_iterator {
+    ($([$($generics:tt)*] $wrapper:ty),* $(,)?) => {$(
+        impl<$($generics)* T: CIterable> Iterator for $wrapper {
+            type Item = T::Item;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                T::next(self.as_cref_mut())
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                T::size_hint(self.as_cref())
+            }
+        }
+    )*};
+}
+
+impl_iterator!(
+    ['a,] CRefMut<'a, T>,
+    [] CRepr<T>,
+    ['a,] CReprScoped<'a, T>,
+    [const N: usize,] CReprInline<T, N>,
+);
@@ -0,0 +1,303 @@
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+use core::{
+    alloc::Layout, ffi::c_void, marker::PhantomData, mem::ManuallyDrop, ops::Deref, ptr::NonNull,
+};
+
+use crate::{Allocator, CClone, CDrop, CRef, CRefMut, CSend, CSync, trait_wrapper::Inner};
+
+/// Frees the memory of a context after its vtable has dropped it in place.
+pub type CDealloc = unsafe extern "C" fn(context: NonNull<c_void>);
+
+/// A trait that represents a buffer that can be converted to a C-compatible slice.
+///
+/// The vtable's `drop` only drops the context in place, `dealloc` then frees its memory with
+/// the strategy chosen when the object was created. A `drop` implemented in C must not also free
+/// the context when the object has a `dealloc`, or it is freed twice. Only objects created with
+/// [`CRepr::from_raw_parts`] have none, and their `drop` frees the context itself.
+#[repr(C)]
+pub struct CRepr<T: CDrop + ?Sized> {
+    inner: Inner<T>,
+    // spelled out instead of `Option<CDealloc>` so cbindgen emits a nullable function pointer
+    dealloc: Option<unsafe extern "C" fn(context: NonNull<c_void>)>,
+}
+
+/// A context allocated by [`CRepr::new_in`], followed by the allocator that frees it.
+#[repr(C)]
+struct WithAllocator<C, A> {
+    context: C,
+    allocator: A,
+}
+
+#[cfg(feature = "alloc")]
+#[allow(unsafe_code)]
+pub(crate) unsafe extern "C" fn dealloc_boxed<C>(context: NonNull<c_void>) {
+    // SAFETY: The context was allocated as a `Box<C>` and has already been dropped.
+    drop(unsafe { Box::from_raw(context.cast::<ManuallyDrop<C>>().as_ptr()) });
+}
+
+#[allow(unsafe_code)]
+unsafe extern "C" fn dealloc_in<C, A: Allocator>(context: NonNull<c_void>) {
+    let block = context.cast::<WithAllocator<C, A>>();
+    // SAFETY: The context was allocated by `CRepr::new_in` and has already been dropped, the
+    // allocator is moved out before the block is freed.
+    unsafe {
+        let allocator = (&raw const (*block.as_ptr()).allocator).read();
+        allocator.deallocate(context.cast(), Layout::new::<WithAllocator<C, A>>());
+    }
+}
+
+#[allow(unsafe_code)]
+// SAFETY: `CRepr` uniquely owns its context, which is `Send` for a `CSend` vtable.
+unsafe impl<T: CSend + CDrop + ?Sized> Send for CRepr<T> {}
+#[allow(unsafe_code)]
+// SAFETY: `CRepr` only hands out shared access to its context through `&self`, and the context
+// is `Sync` for a `CSync` vtable.
+unsafe impl<T: CSync + CDrop + ?Sized> Sync for CRepr<T> {}
+
+impl<T: CDrop> Deref for CRepr<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        #[allow(unsafe_code)]
+        // SAFETY: The vtable pointer always points to a live `'static` vtable.
+        unsafe {
+            self.inner.vtable.as_ref()
+        }
+    }
+}
+
+impl<T: CDrop> CRepr<T> {
+    /// Creates a new `CRepr` from a vtable and context.
+    #[cfg(feature = "alloc")]
+    pub fn new_boxed<C: 'static>(vtable: &'static T, context: C) -> Self {
+        Self::boxed(vtable, context)
+    }
+
+    /// Creates a new `CRepr` with the context allocated by `allocator`.
+    ///
+    /// The allocator is stored after the context and used to free it when the `CRepr` is dropped.
+    /// Returns the context back if the allocator is exhausted.
+    pub fn new_in<C: 'static, A: Allocator + 'static>(
+        vtable: &'static T,
+        allocator: A,
+        context: C,
+    ) -> Result<Self, C> {
+        Self::allocated_in(vtable, allocator, context)
+    }
+
+    /// Creates a new `CRepr` with the context written into a caller provided buffer.
+    ///
+    /// `layout` describes the buffer, the context is returned back if it does not fit. The
+    /// buffer is not freed when the `CRepr` is dropped.
+    /// # Safety
+    /// `buffer` must be valid for reads and writes of `layout`, and must not be used by
+    /// anything else until the `CRepr` is dropped.
+    #[allow(unsafe_code)]
+    pub unsafe fn new_in_place<C: 'static>(
+        vtable: &'static T,
+        buffer: NonNull<u8>,
+        layout: Layout,
+        context: C,
+    ) -> Result<Self, C> {
+        // SAFETY: Upheld by the caller.
+        unsafe { Self::placed(vtable, buffer, layout, context) }
+    }
+
+    /// Boxes the context, which must outlive the returned `CRepr`.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn boxed<C>(vtable: &'static T, context: C) -> Self {
+        #[allow(unsafe_code)]
+        // SAFETY: The vtable is `'static` and the context outlives the `CRepr`.
+        unsafe {
+            Self::new_boxed_unchecked(NonNull::from(vtable), context)
+        }
+    }
+
+    /// Creates a new `CRepr` with the context in a box, without checking lifetimes.
+    ///
+    /// Used by the `clone` slot of a vtable, which copies a context of any lifetime.
+    /// # Safety
+    /// `vtable` must be a vtable for `C`, and both must outlive the returned `CRepr`.
+    #[cfg(feature = "alloc")]
+    #[allow(unsafe_code)]
+    pub unsafe fn new_boxed_unchecked<C>(vtable: NonNull<T>, context: C) -> Self {
+        let context = NonNull::from(Box::leak(Box::new(context))).cast();
+
+        // SAFETY: The vtable is valid for `C` and the context is freed as the box it was
+        // allocated in.
+        unsafe { Self::from_raw_parts_with_dealloc(vtable, context, Some(dealloc_boxed::<C>)) }
+    }
+
+    /// Allocates the context in `allocator`, both must outlive the returned `CRepr`.
+    pub(crate) fn allocated_in<C, A: Allocator>(
+        vtable: &'static T,
+        allocator: A,
+        context: C,
+    ) -> Result<Self, C> {
+        let Some(block) = allocator.allocate(Layout::new::<WithAllocator<C, A>>()) else {
+            return Err(context);
+        };
+        let block = block.cast::<WithAllocator<C, A>>();
+
+        #[allow(unsafe_code)]
+        // SAFETY: The block was allocated for a `WithAllocator<C, A>`, which starts with the
+        // context, and is freed by `dealloc_in` with the same allocator and layout.
+        unsafe {
+            block.write(WithAllocator { context, allocator });
+            Ok(Self::from_raw_parts_with_dealloc(
+                NonNull::from(vtable),
+                block.cast(),
+                Some(dealloc_in::<C, A>),
+            ))
+        }
+    }
+
+    /// Writes the context into `buffer`, which must outlive the returned `CRepr`.
+    /// # Safety
+    /// See [`CRepr::new_in_place`].
+    #[allow(unsafe_code)]
+    pub(crate) unsafe fn placed<C>(
+        vtable: &'static T,
+        buffer: NonNull<u8>,
+        layout: Layout,
+        context: C,
+    ) -> Result<Self, C> {
+        let context_layout = Layout::new::<C>();
+        if layout.size() < context_layout.size()
+            || layout.align() < context_layout.align()
+            || !buffer.cast::<C>().is_aligned()
+        {
+            return Err(context);
+        }
+
+        // SAFETY: The buffer is large enough and aligned for `C`, and valid per the caller.
+        unsafe {
+            buffer.cast::<C>().write(context);
+            Ok(Self::from_raw_parts(NonNull::from(vtable), buffer.cast()))
+        }
+    }
+
+    /// Creates a new `CRepr` from a vtable and context.
+    ///
+    /// Nothing is freed after the vtable's `drop`, which must release the context itself.
+    /// # Safety
+    /// The caller must ensure that the vtable and context are valid and properly aligned.
+    #[allow(unsafe_code)]
+    pub unsafe fn from_raw_parts(vtable: NonNull<T>, context: NonNull<c_void>) -> Self {
+        // SAFETY: Upheld by the caller.
+        unsafe { Self::from_raw_parts_with_dealloc(vtable, context, None) }
+    }
+
+    /// Creates a new `CRepr` from a vtable, context and the function freeing the context.
+    /// # Safety
+    /// The caller must ensure that the vtable and context are valid and properly aligned, and
+    /// that `dealloc` frees the context once the vtable's `drop` has dropped it.
+    #[allow(unsafe_code)]
+    pub unsafe fn from_raw_parts_with_dealloc(
+        vtable: NonNull<T>,
+        context: NonNull<c_void>,
+        dealloc: Option<CDealloc>,
+    ) -> Self {
+        Self {
+            inner: Inner {
+                vtable,
+                ptr: context,
+            },
+            dealloc,
+        }
+    }
+}
+
+impl<T: CDrop + ?Sized> CRepr<T> {
+    /// Maps the vtable to a new type using the provided function.
+    /// # Safety
+    /// The caller must ensure that `methods` is a vtable for the same implementer as this object.
+    #[allow(unsafe_code)]
+    pub unsafe fn as_cref_with_methods<'a, U: ?Sized>(
+        &'a self,
+        methods: NonNull<U>,
+    ) -> CRef<'a, U> {
+        CRef {
+            // SAFETY: Upheld by the caller.
+            inner: unsafe { self.inner.map_vtable(|_| methods) },
+            phantom: PhantomData,
+        }
+    }
+
+    /// Maps the vtable to a new type using the provided function.
+    /// # Safety
+    /// The caller must ensure that `methods` is a vtable for the same implementer as this object.
+    #[allow(unsafe_code)]
+    pub unsafe fn as_cref_mut_with_methods<'a, U: ?Sized>(
+        &'a mut self,
+        methods: NonNull<U>,
+    ) -> CRefMut<'a, U> {
+        CRefMut {
+            // SAFETY: Upheld by the caller.
+            inner: unsafe { self.inner.map_vtable(|_| methods) },
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns a pointer to the context.
+    pub fn as_ptr(&self) -> *const () {
+        self.inner.ptr.cast::<()>().as_ptr()
+    }
+
+    /// Returns a reference to the vtable.
+    pub fn get_vtable(&self) -> &T {
+        #[allow(unsafe_code)]
+        // SAFETY: The vtable pointer always points to a live `'static` vtable.
+        unsafe {
+            self.inner.vtable.as_ref()
+        }
+    }
+
+    /// Returns a cref
+    pub fn as_cref<'a>(&'a self) -> CRef<'a, T> {
+        CRef {
+            inner: self.inner,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns a cref mut
+    pub fn as_cref_mut<'a>(&'a mut self) -> CRefMut<'a, T> {
+        CRefMut {
+            inner: self.inner,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Splits the `CRepr` into its object and the function freeing the context, without
+    /// dropping it.
+    pub(crate) fn into_raw_parts(self) -> (Inner<T>, Option<CDealloc>) {
+        let repr = ManuallyDrop::new(self);
+        (repr.inner, repr.dealloc)
+    }
+}
+
+impl<T: CClone> Clone for CRepr<T> {
+    fn clone(&self) -> Self {
+        #[allow(unsafe_code)]
+        // SAFETY: The context is `'static`, and the clone only borrows what it borrows, see
+        // `CClone`.
+        unsafe {
+            T::clone(self.as_cref()).into_unscoped()
+        }
+    }
+}
+
+impl<T: CDrop + ?Sized> Drop for CRepr<T> {
+    fn drop(&mut self) {
+        T::drop(self.as_cref_mut());
+        if let Some(dealloc) = self.dealloc {
+            #[allow(unsafe_code)]
+            // SAFETY: The context has been dropped and is not used again.
+            unsafe {
+                dealloc(self.inner.ptr);
+            }
+        }
+    }
+}
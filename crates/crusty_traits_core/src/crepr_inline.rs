@@ -0,0 +1,207 @@
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+use core::{
+    cell::UnsafeCell,
+    ffi::c_void,
+    marker::PhantomData,
+    mem::{MaybeUninit, align_of, size_of},
+    ops::Deref,
+    ptr::NonNull,
+};
+
+#[cfg(feature = "alloc")]
+use crate::crepr::dealloc_boxed;
+use crate::{CClone, CDealloc, CDrop, CRef, CRefMut, CSend, CSync, trait_wrapper::Inner};
+
+/// A `CRepr` that stores contexts of up to `N` words inline instead of on the heap.
+///
+/// The context pointer is derived from the object on every access, so it can be moved freely.
+/// The inline storage is an `UnsafeCell`, as `&self` methods of the context may mutate interior
+/// mutable state through the shared pointer. Contexts that do not fit are boxed and freed with
+/// `dealloc`.
+#[repr(C)]
+pub struct CReprInline<T: CDrop + ?Sized, const N: usize> {
+    vtable: NonNull<T>,
+    boxed: Option<NonNull<c_void>>,
+    dealloc: Option<CDealloc>,
+    inline: UnsafeCell<[MaybeUninit<usize>; N]>,
+}
+
+#[allow(unsafe_code)]
+// SAFETY: `CReprInline` uniquely owns its context, which is `Send` for a `CSend` vtable.
+unsafe impl<T: CSend + CDrop + ?Sized, const N: usize> Send for CReprInline<T, N> {}
+#[allow(unsafe_code)]
+// SAFETY: `CReprInline` only hands out shared access to its context through `&self`, and the
+// context is `Sync` for a `CSync` vtable.
+unsafe impl<T: CSync + CDrop + ?Sized, const N: usize> Sync for CReprInline<T, N> {}
+
+impl<T: CDrop, const N: usize> Deref for CReprInline<T, N> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.get_vtable()
+    }
+}
+
+impl<T: CDrop, const N: usize> CReprInline<T, N> {
+    /// Returns whether a context of type `C` fits in the inline storage.
+    pub const fn fits_inline<C>() -> bool {
+        size_of::<C>() <= size_of::<[usize; N]>() && align_of::<C>() <= align_of::<usize>()
+    }
+
+    /// Creates a new `CReprInline`, storing the context inline if it fits and boxing it otherwise.
+    #[cfg(feature = "alloc")]
+    pub fn new<C: 'static>(vtable: &'static T, context: C) -> Self {
+        match Self::new_inline(vtable, context) {
+            Ok(repr) => repr,
+            Err(context) => Self {
+                vtable: NonNull::from(vtable),
+                boxed: Some(NonNull::from(Box::leak(Box::new(context))).cast()),
+                dealloc: Some(dealloc_boxed::<C>),
+                inline: UnsafeCell::new([MaybeUninit::uninit(); N]),
+            },
+        }
+    }
+
+    /// Creates a new `CReprInline` with the context stored inline.
+    ///
+    /// Returns the context back if it is too large or too aligned for `N` words.
+    pub fn new_inline<C: 'static>(vtable: &'static T, context: C) -> Result<Self, C> {
+        if !Self::fits_inline::<C>() {
+            return Err(context);
+        }
+
+        let mut repr = Self {
+            vtable: NonNull::from(vtable),
+            boxed: None,
+            dealloc: None,
+            inline: UnsafeCell::new([MaybeUninit::uninit(); N]),
+        };
+        #[allow(unsafe_code)]
+        // SAFETY: The inline storage is large enough and aligned for `C`.
+        unsafe {
+            repr.inline
+                .get_mut()
+                .as_mut_ptr()
+                .cast::<C>()
+                .write(context);
+        }
+        Ok(repr)
+    }
+}
+
+impl<T: CDrop + ?Sized, const N: usize> CReprInline<T, N> {
+    /// Returns whether the context is stored inline rather than boxed.
+    pub fn is_inline(&self) -> bool {
+        self.boxed.is_none()
+    }
+
+    fn inner(&self) -> Inner<T> {
+        Inner {
+            vtable: self.vtable,
+            // a pointer derived from `&UnsafeCell` may be written through
+            ptr: self
+                .boxed
+                .unwrap_or_else(|| NonNull::from(&self.inline).cast()),
+        }
+    }
+
+    fn inner_mut(&mut self) -> Inner<T> {
+        Inner {
+            vtable: self.vtable,
+            ptr: self
+                .boxed
+                .unwrap_or_else(|| NonNull::from(self.inline.get_mut()).cast()),
+        }
+    }
+
+    /// Maps the vtable to a new type using the provided function.
+    /// # Safety
+    /// The caller must ensure that `methods` is a vtable for the same implementer as this object.
+    #[allow(unsafe_code)]
+    pub unsafe fn as_cref_with_methods<'a, U: ?Sized>(
+        &'a self,
+        methods: NonNull<U>,
+    ) -> CRef<'a, U> {
+        CRef {
+            // SAFETY: Upheld by the caller.
+            inner: unsafe { self.inner().map_vtable(|_| methods) },
+            phantom: PhantomData,
+        }
+    }
+
+    /// Maps the vtable to a new type using the provided function.
+    /// # Safety
+    /// The caller must ensure that `methods` is a vtable for the same implementer as this object.
+    #[allow(unsafe_code)]
+    pub unsafe fn as_cref_mut_with_methods<'a, U: ?Sized>(
+        &'a mut self,
+        methods: NonNull<U>,
+    ) -> CRefMut<'a, U> {
+        CRefMut {
+            // SAFETY: Upheld by the caller.
+            inner: unsafe { self.inner_mut().map_vtable(|_| methods) },
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns a pointer to the context.
+    pub fn as_ptr(&self) -> *const () {
+        self.inner().ptr.cast::<()>().as_ptr()
+    }
+
+    /// Returns a reference to the vtable.
+    pub fn get_vtable(&self) -> &T {
+        #[allow(unsafe_code)]
+        // SAFETY: The vtable pointer always points to a live `'static` vtable.
+        unsafe {
+            self.vtable.as_ref()
+        }
+    }
+
+    /// Returns a cref
+    pub fn as_cref<'a>(&'a self) -> CRef<'a, T> {
+        CRef {
+            inner: self.inner(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns a cref mut
+    pub fn as_cref_mut<'a>(&'a mut self) -> CRefMut<'a, T> {
+        CRefMut {
+            inner: self.inner_mut(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: CClone, const N: usize> Clone for CReprInline<T, N> {
+    /// Clones the object through its vtable, the copy is always boxed.
+    fn clone(&self) -> Self {
+        #[allow(unsafe_code)]
+        // SAFETY: The context is `'static`, and the clone only borrows what it borrows, see
+        // `CClone`.
+        let clone = unsafe { T::clone(self.as_cref()).into_unscoped() };
+        let (inner, dealloc) = clone.into_raw_parts();
+        Self {
+            vtable: inner.vtable,
+            boxed: Some(inner.ptr),
+            dealloc,
+            inline: UnsafeCell::new([MaybeUninit::uninit(); N]),
+        }
+    }
+}
+
+impl<T: CDrop + ?Sized, const N: usize> Drop for CReprInline<T, N> {
+    fn drop(&mut self) {
+        T::drop(self.as_cref_mut());
+        if let (Some(dealloc), Some(boxed)) = (self.dealloc, self.boxed) {
+            #[allow(unsafe_code)]
+            // SAFETY: The boxed context has been dropped and is not used again.
+            unsafe {
+                dealloc(boxed);
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use core::{alloc::Layout, marker::PhantomData, ops::Deref, ptr::NonNull};
+
+use crate::{Allocator, CClone, CDrop, CRef, CRefMut, CRepr};
+
+/// A `CRepr` whose context may borrow data that lives for `'a`.
+///
+/// It has the same layout as [`CRepr`], but cannot be turned back into one, so the context
+/// never outlives what it borrows.
+#[repr(transparent)]
+pub struct CReprScoped<'a, T: CDrop + ?Sized> {
+    repr: CRepr<T>,
+    phantom: PhantomData<&'a ()>,
+}
+
+impl<T: CDrop> Deref for CReprScoped<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.repr.get_vtable()
+    }
+}
+
+impl<T: CDrop + ?Sized> From<CRepr<T>> for CReprScoped<'_, T> {
+    fn from(repr: CRepr<T>) -> Self {
+        Self {
+            repr,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: CClone> Clone for CReprScoped<'_, T> {
+    fn clone(&self) -> Self {
+        #[allow(unsafe_code)]
+        // SAFETY: The clone only borrows what the context borrows, which outlives this object,
+        // see `CClone`.
+        unsafe { T::clone(self.as_cref()).into_unscoped() }.into()
+    }
+}
+
+impl<'a, T: CDrop> CReprScoped<'a, T> {
+    /// Creates a new `CReprScoped` with the context in a box.
+    #[cfg(feature = "alloc")]
+    pub fn new_boxed<C: 'a>(vtable: &'static T, context: C) -> Self {
+        CRepr::boxed(vtable, context).into()
+    }
+
+    /// Creates a new `CReprScoped` with the context allocated by `allocator`.
+    ///
+    /// Returns the context back if the allocator is exhausted.
+    pub fn new_in<C: 'a, A: Allocator + 'a>(
+        vtable: &'static T,
+        allocator: A,
+        context: C,
+    ) -> Result<Self, C> {
+        CRepr::allocated_in(vtable, allocator, context).map(Self::from)
+    }
+
+    /// Creates a new `CReprScoped` with the context written into a caller provided buffer.
+    /// # Safety
+    /// `buffer` must be valid for reads and writes of `layout`, and must not be used by
+    /// anything else until the `CReprScoped` is dropped.
+    #[allow(unsafe_code)]
+    pub unsafe fn new_in_place<C: 'a>(
+        vtable: &'static T,
+        buffer: NonNull<u8>,
+        layout: Layout,
+        context: C,
+    ) -> Result<Self, C> {
+        // SAFETY: Upheld by the caller.
+        unsafe { CRepr::placed(vtable, buffer, layout, context) }.map(Self::from)
+    }
+}
+
+impl<T: CDrop + ?Sized> CReprScoped<'_, T> {
+    /// Maps the vtable to a new type using the provided function.
+    /// # Safety
+    /// The caller must ensure that `methods` is a vtable for the same implementer as this object.
+    #[allow(unsafe_code)]
+    pub unsafe fn as_cref_with_methods<U: ?Sized>(&self, methods: NonNull<U>) -> CRef<'_, U> {
+        // SAFETY: Upheld by the caller.
+        unsafe { self.repr.as_cref_with_methods(methods) }
+    }
+
+    /// Maps the vtable to a new type using the provided function.
+    /// # Safety
+    /// The caller must ensure that `methods` is a vtable for the same implementer as this object.
+    #[allow(unsafe_code)]
+    pub unsafe fn as_cref_mut_with_methods<U: ?Sized>(
+        &mut self,
+        methods: NonNull<U>,
+    ) -> CRefMut<'_, U> {
+        // SAFETY: Upheld by the caller.
+        unsafe { self.repr.as_cref_mut_with_methods(methods) }
+    }
+
+    /// Returns a pointer to the context.
+    pub fn as_ptr(&self) -> *const () {
+        self.repr.as_ptr()
+    }
+
+    /// Returns a reference to the vtable.
+    pub fn get_vtable(&self) -> &T {
+        self.repr.get_vtable()
+    }
+
+    /// Returns a cref
+    pub fn as_cref(&self) -> CRef<'_, T> {
+        self.repr.as_cref()
+    }
+
+    /// Returns a cref mut
+    pub fn as_cref_mut(&mut self) -> CRefMut<'_, T> {
+        self.repr.as_cref_mut()
+    }
+
+    /// Returns the `CRepr`, without the lifetime of the data its context borrows.
+    /// # Safety
+    /// The caller must ensure that the borrowed data outlives the returned `CRepr`.
+    #[allow(unsafe_code)]
+    pub(crate) unsafe fn into_unscoped(self) -> CRepr<T> {
+        self.repr
+    }
+}
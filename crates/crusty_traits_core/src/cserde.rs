@@ -0,0 +1,50 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[cfg(feature = "alloc")]
+use crate::CArc;
+use crate::{CDrop, CRef, CRefMut, CRepr, CReprInline, CReprScoped};
+
+/// A trait for the vtables of objects that can be serialized, such as the `CVecVTable` of
+/// `crusty_traits_types`, so the wrappers of the vtable implement [`Serialize`].
+pub trait CSerialize: CDrop {
+    /// Serializes the object behind the given `CRef`.
+    fn serialize<S: Serializer>(repr: CRef<'_, Self>, serializer: S) -> Result<S::Ok, S::Error>;
+}
+
+/// A trait for the vtables of objects that can be deserialized into a default implementation,
+/// so [`CRepr`] implements [`Deserialize`].
+pub trait CDeserialize<'de>: CDrop + Sized {
+    /// Deserializes an object of the vtable.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<CRepr<Self>, D::Error>;
+}
+
+impl<T: CSerialize> Serialize for CRef<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        T::serialize(*self, serializer)
+    }
+}
+
+macro_rules! impl_serialize {
+    ($([$($generics:tt)*] $wrapper:ty),* $(,)?) => {$(
+        impl<$($generics)* T: CSerialize> Serialize for $wrapper {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                T::serialize(self.as_cref(), serializer)
+            }
+        }
+    )*};
+}
+
+impl_serialize!(
+    ['a,] CRefMut<'a, T>,
+    [] CRepr<T>,
+    ['a,] CReprScoped<'a, T>,
+    [const N: usize,] CReprInline<T, N>,
+);
+#[cfg(feature = "alloc")]
+impl_serialize!([] CArc<T>);
+
+impl<'de, T: CDeserialize<'de>> Deserialize<'de> for CRepr<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer)
+    }
+}
@@ -0,0 +1,124 @@
+use core::{
+    ops::{Index, IndexMut},
+    slice::{Iter, SliceIndex},
+};
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::{CDrop, CRef, CRefMut, CRepr, CReprInline, CReprScoped};
+
+/// A trait for the vtables of contiguous sequences, such as the `CSliceVTable` and `CVecVTable`
+/// of `crusty_traits_types`.
+///
+/// The wrappers of the vtable implement [`Index`], [`AsRef`] and [`IntoIterator`] for shared
+/// references like a slice. They can't deref to the slice, as every wrapper derefs to its vtable.
+pub trait CAsSlice: CDrop {
+    /// The type of the elements.
+    type Item;
+
+    /// Returns the elements of the object behind the given `CRef`.
+    fn as_slice(repr: CRef<'_, Self>) -> &[Self::Item];
+}
+
+/// A trait for the vtables of sequences whose objects own their elements, such as the
+/// `CVecVTable` of `crusty_traits_types`, so the wrappers implement [`IndexMut`] and [`AsMut`].
+///
+/// Objects of a [`CAsSlice`] vtable may be views of elements shared with others, so mutable
+/// access needs this trait. `&mut` wrappers are already iterators for [`CIterable`] vtables, so
+/// mutable iteration goes through [`AsMut`].
+///
+/// [`CIterable`]: crate::CIterable
+pub trait CAsMutSlice: CAsSlice {
+    /// Returns the elements of the object behind the given `CRefMut`.
+    fn as_mut_slice(repr: CRefMut<'_, Self>) -> &mut [Self::Item];
+}
+
+/// A trait for the vtables of sequences that can grow, so their wrappers implement [`Extend`].
+pub trait CExtend: CAsSlice {
+    /// Appends the items of `iter` to the object behind the given `CRefMut`.
+    fn extend<I: IntoIterator<Item = Self::Item>>(repr: CRefMut<'_, Self>, iter: I);
+}
+
+/// A trait for the vtables of sequences that can be created from a `Vec`, so [`CRepr`]
+/// implements `From<Vec<_>>` and [`FromIterator`].
+#[cfg(feature = "alloc")]
+pub trait CFromVec: CAsSlice + Sized {
+    /// Returns `vec` as an object of the vtable.
+    fn from_vec(vec: Vec<Self::Item>) -> CRepr<Self>;
+}
+
+/// Implements the slice traits for the wrappers giving shared access to the object.
+macro_rules! impl_slice_ref {
+    ($([$($generics:tt)*] $wrapper:ty),* $(,)?) => {$(
+        impl<$($generics)* T: CAsSlice, I: SliceIndex<[T::Item]>> Index<I> for $wrapper {
+            type Output = I::Output;
+
+            fn index(&self, index: I) -> &Self::Output {
+                &T::as_slice(self.as_cref())[index]
+            }
+        }
+
+        impl<$($generics)* T: CAsSlice> AsRef<[T::Item]> for $wrapper {
+            fn as_ref(&self) -> &[T::Item] {
+                T::as_slice(self.as_cref())
+            }
+        }
+
+        impl<'s, $($generics)* T: CAsSlice> IntoIterator for &'s $wrapper {
+            type Item = &'s T::Item;
+            type IntoIter = Iter<'s, T::Item>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                T::as_slice(self.as_cref()).iter()
+            }
+        }
+    )*};
+}
+
+/// Implements the slice traits for the wrappers giving mutable access to the object.
+macro_rules! impl_slice_mut {
+    ($([$($generics:tt)*] $wrapper:ty),* $(,)?) => {$(
+        impl_slice_ref!([$($generics)*] $wrapper);
+
+        impl<$($generics)* T: CAsMutSlice, I: SliceIndex<[T::Item]>> IndexMut<I> for $wrapper {
+            fn index_mut(&mut self, index: I) -> &mut Self::Output {
+                &mut T::as_mut_slice(self.as_cref_mut())[index]
+            }
+        }
+
+        impl<$($generics)* T: CAsMutSlice> AsMut<[T::Item]> for $wrapper {
+            fn as_mut(&mut self) -> &mut [T::Item] {
+                T::as_mut_slice(self.as_cref_mut())
+            }
+        }
+
+        impl<$($generics)* T: CExtend> Extend<T::Item> for $wrapper {
+            fn extend<It: IntoIterator<Item = T::Item>>(&mut self, iter: It) {
+                T::extend(self.as_cref_mut(), iter);
+            }
+        }
+    )*};
+}
+
+impl_slice_ref!(['a,] CRef<'a, T>);
+impl_slice_mut!(
+    ['a,] CRefMut<'a, T>,
+    [] CRepr<T>,
+    ['a,] CReprScoped<'a, T>,
+    [const N: usize,] CReprInline<T, N>,
+);
+
+#[cfg(feature = "alloc")]
+impl<T: CFromVec> From<Vec<T::Item>> for CRepr<T> {
+    fn from(vec: Vec<T::Item>) -> Self {
+        T::from_vec(vec)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: CFromVec> FromIterator<T::Item> for CRepr<T> {
+    fn from_iter<I: IntoIterator<Item = T::Item>>(iter: I) -> Self {
+        T::from_vec(iter.into_iter().collect())
+    }
+}
@@ -1,20 +1,81 @@
 //! # Crusty Traits Core
 //!
 //! This module provides core traits and types for creating C-compatible vtables for Rust traits.
-//! It includes the fundamental building blocks: `CRef`, `CRefMut`, `CRepr`, `CDrop`, and `AsVTable`.
+//! It includes the fundamental building blocks: `CRef`, `CRefMut`, `CRepr`, `CDrop`, `AsVTable` and
+//! `StaticVTable`.
 //!
 //! ## Core Types
 //!
 //! - [`CRef`] - A C-compatible reference to a trait object
 //! - [`CRefMut`] - A C-compatible mutable reference to a trait object  
 //! - [`CRepr`] - A C-compatible representation of a trait object with its vtable
+//! - [`CReprInline`] - A [`CRepr`] that stores small objects inline instead of on the heap
+//! - [`CReprScoped`] - A [`CRepr`] whose object may borrow data for a lifetime
+//! - `CArc` and `CWeak` - Reference counted trait objects, with the `alloc` feature
 //! - [`CDrop`] - A trait for dropping objects in a C-compatible way
+//! - [`CSend`] and [`CSync`] - Markers for vtables whose objects are thread safe
+//! - [`CClone`] - A trait for cloning objects in a C-compatible way
+//! - [`CDebug`] and [`CDisplay`] - Traits for formatting objects into a [`CFormatter`]
+//! - [`CPartialEq`], [`CEq`], [`CPartialOrd`], [`COrd`] and [`CHash`] - Traits for comparing and
+//!   hashing objects of the same implementation, identified by [`implementation_id`]
+//! - [`CPollable`] - A trait for the vtables of futures, which makes the wrappers `Future`
+//! - [`CIterable`] - A trait for the vtables of iterators, which makes the wrappers `Iterator`
+//! - [`CAsSlice`], [`CAsMutSlice`] and [`CExtend`] - Traits for the vtables of sequences, which
+//!   make the wrappers index and iterate like a slice
+//! - `CSerialize` and `CDeserialize` - Traits for the vtables of objects that can be serialized
+//!   with serde, with the `serde` feature
 //! - [`AsVTable`] - A trait for converting types to vtables
+//! - [`StaticVTable`] - A trait for vtables built at compile time
+//! - [`Allocator`] - A trait for allocators that can hold the context of a [`CRepr`]
 //!
 //! These types work together to enable safe FFI interactions with Rust trait objects.
+//!
+//! ## Features
+//!
+//! - `std` (default) - Enables `alloc`.
+//! - `alloc` - Enables [`CRepr::new_boxed`], [`Global`], `CArc` and the other types that need a
+//!   heap allocator.
+//! - `serde` - Enables `CSerialize` and `CDeserialize`, which make the wrappers `Serialize` and
+//!   `Deserialize`.
+//!
+//! Without `alloc` the crate is `no_std`, a [`CRepr`] can still be created with a custom
+//! [`Allocator`] or in place in a caller provided buffer.
+
+#![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+mod allocator;
+#[cfg(feature = "alloc")]
+mod carc;
+mod ccmp;
+mod cfmt;
+mod cfuture;
+mod chash;
+mod citer;
+mod crepr;
+mod crepr_inline;
+mod crepr_scoped;
+#[cfg(feature = "serde")]
+mod cserde;
+mod cslice;
 mod trait_wrapper;
 
+pub use allocator::*;
+#[cfg(feature = "alloc")]
+pub use carc::*;
+pub use ccmp::*;
+pub use cfmt::*;
+pub use cfuture::*;
+pub use chash::*;
+pub use citer::*;
+pub use crepr::*;
+pub use crepr_inline::*;
+pub use crepr_scoped::*;
+#[cfg(feature = "serde")]
+pub use cserde::*;
+pub use cslice::*;
 pub use trait_wrapper::*;
 
 /// A trait that represents dropping a Rust object in a C-compatible way.
@@ -23,8 +84,99 @@ pub trait CDrop {
     fn drop(repr: CRefMut<Self>);
 }
 
+/// A trait for the vtables of traits with a `Clone` super trait.
+///
+/// Implemented by the `crusty_trait` macro so `CRepr` and the other owning wrappers are `Clone`.
+/// The clone of a borrowed or scoped object borrows the same data, so it only lives as long as
+/// the `CRef` it was cloned from:
+///
+/// ```compile_fail
+/// use crusty_traits_core::*;
+/// use crusty_traits_macros::crusty_trait;
+///
+/// #[crusty_trait]
+/// pub trait Text: Clone {
+///     fn len(&self) -> usize;
+/// }
+///
+/// #[derive(Clone)]
+/// struct Borrowed<'a>(&'a str);
+///
+/// impl Text for Borrowed<'_> {
+///     fn len(&self) -> usize {
+///         self.0.len()
+///     }
+/// }
+///
+/// fn escape(text: &str) -> CReprScoped<'static, TextVTable> {
+///     let borrowed = Borrowed(text);
+///     <TextVTable as CClone>::clone(TextVTable::borrow(&borrowed))
+/// }
+/// ```
+/// # Safety
+/// The context of the clone may only borrow data that the context of `repr` borrows, not the
+/// object behind `repr` itself, as the owning wrappers keep the clone for as long as that data.
+#[allow(unsafe_code)]
+pub unsafe trait CClone: CDrop + Sized {
+    /// Clones the object behind the given `CRef` into a new object.
+    fn clone<'a>(repr: CRef<'a, Self>) -> CReprScoped<'a, Self>;
+}
+
+/// A marker for vtables whose objects may be sent to another thread.
+///
+/// Implemented by the `crusty_trait` macro for traits with a `Send` super trait, and required for
+/// the wrappers of the vtable to be `Send`.
+/// # Safety
+/// The context of every object using the vtable must be `Send`.
+#[allow(unsafe_code)]
+pub unsafe trait CSend {}
+
+/// A marker for vtables whose objects may be shared between threads.
+///
+/// Implemented by the `crusty_trait` macro for traits with a `Sync` super trait, and required for
+/// the wrappers of the vtable to be `Sync`.
+/// # Safety
+/// The context of every object using the vtable must be `Sync`.
+#[allow(unsafe_code)]
+pub unsafe trait CSync {}
+
+/// A trait for vtables that can be built for the implementer `GEN` at compile time.
+///
+/// Implemented by the `crusty_trait` macro so `create_vtable` can return a reference to the
+/// promoted constant, without locking or allocating.
+pub trait StaticVTable<GEN>: Sized + 'static {
+    /// The vtable for `GEN`.
+    const VTABLE: Self;
+}
+
 /// A trait that provides a way to convert a type into a C-compatible vtable.
 pub trait AsVTable<T: ?Sized> {
     /// Return a vtable for the type.
     fn as_vtable(&self) -> T;
 }
+
+/// Expands to the given tokens when this crate has the `alloc` feature, or to the tokens after
+/// `else` without it.
+///
+/// The `crusty_trait` macro wraps the items that need an allocator in it, as a proc macro only
+/// sees its own features and not those of the crate it expands in.
+#[doc(hidden)]
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! crusty_alloc {
+    ({ $($alloc:tt)* } else { $($no_alloc:tt)* }) => { $($alloc)* };
+    ($($alloc:tt)*) => { $($alloc)* };
+}
+
+/// Expands to the given tokens when this crate has the `alloc` feature, or to the tokens after
+/// `else` without it.
+///
+/// The `crusty_trait` macro wraps the items that need an allocator in it, as a proc macro only
+/// sees its own features and not those of the crate it expands in.
+#[doc(hidden)]
+#[cfg(not(feature = "alloc"))]
+#[macro_export]
+macro_rules! crusty_alloc {
+    ({ $($alloc:tt)* } else { $($no_alloc:tt)* }) => { $($no_alloc)* };
+    ($($alloc:tt)*) => {};
+}
@@ -1,21 +1,25 @@
-use std::{
-    ops::{Deref, DerefMut},
-    ptr::NonNull,
-};
+use core::{ffi::c_void, marker::PhantomData, ops::Deref, ptr::NonNull};
 
-use crate::CDrop;
+use crate::{CSend, CSync};
 
+/// A vtable and the type erased context it is called with.
+///
+/// The vtable is a shared `'static`, so it is only ever read. The context keeps the provenance
+/// of the allocation or borrow it was created from.
 #[repr(C)]
-struct Inner<T: ?Sized> {
+pub(crate) struct Inner<T: ?Sized> {
     pub vtable: NonNull<T>,
-    pub ptr: NonNull<u8>,
+    pub ptr: NonNull<c_void>,
 }
 
 impl<T: ?Sized> Inner<T> {
     #[allow(unsafe_code)]
     /// Creates a new `Inner` from a vtable and context.
+    /// # Safety
+    /// The vtable returned by `map` must be valid for the context.
     pub unsafe fn map_vtable<U: ?Sized, F: FnOnce(&T) -> NonNull<U>>(self, map: F) -> Inner<U> {
         Inner {
+            // SAFETY: The vtable pointer always points to a live `'static` vtable.
             vtable: map(unsafe { self.vtable.as_ref() }),
             ptr: self.ptr,
         }
@@ -34,201 +38,222 @@ impl<T> Deref for Inner<T> {
 
     fn deref(&self) -> &Self::Target {
         #[allow(unsafe_code)]
+        // SAFETY: The vtable pointer always points to a live `'static` vtable.
         unsafe {
             self.vtable.as_ref()
         }
     }
 }
 
-/// A trait that represents a buffer that can be converted to a C-compatible slice.
 #[repr(transparent)]
-pub struct CRepr<T: CDrop + ?Sized> {
-    inner: Inner<T>,
-}
-
-#[allow(unsafe_code)]
-unsafe impl<T: Send + CDrop + ?Sized> Send for CRepr<T> {}
-#[allow(unsafe_code)]
-unsafe impl<T: Sync + CDrop + ?Sized> Sync for CRepr<T> {}
-
-impl<T: CDrop> Deref for CRepr<T> {
-    type Target = T;
-
-    fn deref(&self) -> &Self::Target {
-        #[allow(unsafe_code)]
-        unsafe {
-            self.inner.vtable.as_ref()
-        }
-    }
+/// A reference to a C-compatible object.
+pub struct CRef<'a, T: ?Sized> {
+    pub(crate) inner: Inner<T>,
+    pub(crate) phantom: PhantomData<&'a T>,
 }
 
-impl<T: CDrop> CRepr<T> {
-    /// Creates a new `CRepr` from a vtable and context.
-    pub fn new_boxed<C>(vtable: &'static T, context: C) -> Self {
-        let context = Box::new(context);
-        let vtable = NonNull::from(vtable);
-        let context = NonNull::from(Box::leak(context)).cast();
-
-        #[allow(unsafe_code)]
-        // SAFETY: The vtable and context are valid and properly aligned.
-        unsafe {
-            Self::from_raw_parts(vtable, context)
-        }
-    }
-
-    /// Creates a new `CRepr` from a vtable and context.
+impl<'a, T: ?Sized> CRef<'a, T> {
+    /// Creates a new `CRef` from a vtable and context.
     /// # Safety
-    /// The caller must ensure that the vtable and context are valid and properly aligned.
+    /// The caller must ensure that the vtable and context are valid for `'a`.
     #[allow(unsafe_code)]
-    pub unsafe fn from_raw_parts(vtable: NonNull<T>, context: NonNull<u8>) -> Self {
+    pub unsafe fn from_raw_parts(vtable: NonNull<T>, context: NonNull<c_void>) -> Self {
         Self {
             inner: Inner {
                 vtable,
                 ptr: context,
             },
-        }
-    }
-}
-
-impl<T: CDrop + ?Sized> CRepr<T> {
-    /// Maps the vtable to a new type using the provided function.
-    #[allow(unsafe_code)]
-    pub unsafe fn as_cref_with_methods<'a, U: ?Sized>(
-        &'a self,
-        methods: NonNull<U>,
-    ) -> CRef<'a, U> {
-        CRef {
-            inner: unsafe { self.inner.map_vtable(|_| methods) },
-            phantom: std::marker::PhantomData,
-        }
-    }
-
-    /// Maps the vtable to a new type using the provided function.
-    #[allow(unsafe_code)]
-    pub unsafe fn as_cref_mut_with_methods<'a, U: ?Sized>(
-        &'a mut self,
-        methods: NonNull<U>,
-    ) -> CRefMut<'a, U> {
-        CRefMut {
-            inner: unsafe { self.inner.map_vtable(|_| methods) },
-            phantom: std::marker::PhantomData,
+            phantom: PhantomData,
         }
     }
 
     /// Returns a pointer to the context.
-    pub fn as_ptr(&self) -> *const u8 {
-        self.inner.ptr.as_ptr()
+    pub fn as_ptr(&self) -> *const () {
+        self.inner.ptr.cast::<()>().as_ptr()
     }
 
     /// Returns a reference to the vtable.
     pub fn get_vtable(&self) -> &T {
         #[allow(unsafe_code)]
+        // SAFETY: The vtable pointer always points to a live `'static` vtable.
         unsafe {
             self.inner.vtable.as_ref()
         }
     }
 
-    /// Returns a cref
-    pub fn as_cref<'a>(&'a self) -> CRef<'a, T> {
-        CRef {
-            inner: self.inner,
-            phantom: std::marker::PhantomData,
-        }
+    /// Returns a copy of the reference, borrowed for the lifetime of `self`.
+    pub fn as_cref(&self) -> CRef<'_, T> {
+        *self
     }
 
-    /// Returns a cref mut
-    pub fn as_cref_mut<'a>(&'a mut self) -> CRefMut<'a, T> {
-        CRefMut {
-            inner: self.inner,
-            phantom: std::marker::PhantomData,
+    /// Maps the vtable to a new type using the provided function.
+    /// # Safety
+    /// The caller must ensure that `methods` is a vtable for the same implementer as this object.
+    #[allow(unsafe_code)]
+    pub unsafe fn as_cref_with_methods<U: ?Sized>(&self, methods: NonNull<U>) -> CRef<'_, U> {
+        CRef {
+            // SAFETY: Upheld by the caller.
+            inner: unsafe { self.inner.map_vtable(|_| methods) },
+            phantom: PhantomData,
         }
     }
 }
 
-impl<T: CDrop + ?Sized> Drop for CRepr<T> {
-    fn drop(&mut self) {
-        T::drop(self.as_cref_mut());
-    }
-}
-
 #[repr(transparent)]
 /// A reference to a C-compatible object.
-pub struct CRef<'a, T: ?Sized> {
-    inner: Inner<T>,
-    phantom: std::marker::PhantomData<&'a T>,
+///
+/// It is a unique borrow of the object, so unlike [`CRef`] it is not `Copy`, and
+/// [`CRefMut::reborrow`] passes it on without giving it up:
+///
+/// ```compile_fail
+/// use crusty_traits_core::*;
+/// use crusty_traits_macros::crusty_trait;
+///
+/// #[crusty_trait]
+/// pub trait Counter {
+///     fn add(&mut self, value: u32);
+/// }
+///
+/// impl Counter for u32 {
+///     fn add(&mut self, value: u32) {
+///         *self += value;
+///     }
+/// }
+///
+/// let mut count = 0;
+/// let mut counter = CounterVTable::borrow_mut(&mut count);
+/// let mut copy = counter;
+/// copy.add(1);
+/// counter.add(2);
+/// ```
+pub struct CRefMut<'a, T: ?Sized> {
+    pub(crate) inner: Inner<T>,
+    pub(crate) phantom: PhantomData<&'a mut T>,
 }
 
-impl<'a, T: ?Sized> CRef<'a, T> {
-    /// Creates a new `CRef` from a vtable and context.
+impl<'a, T: ?Sized> CRefMut<'a, T> {
+    /// Creates a new `CRefMut` from a vtable and context.
+    /// # Safety
+    /// The caller must ensure that the vtable and context are valid for `'a`, and that the
+    /// context is not accessed through anything else for `'a`.
     #[allow(unsafe_code)]
-    pub unsafe fn from_raw_parts(vtable: NonNull<T>, context: NonNull<u8>) -> Self {
+    pub unsafe fn from_raw_parts(vtable: NonNull<T>, context: NonNull<c_void>) -> Self {
         Self {
             inner: Inner {
                 vtable,
                 ptr: context,
             },
-            phantom: std::marker::PhantomData,
+            phantom: PhantomData,
         }
     }
 
     /// Returns a pointer to the context.
-    pub fn as_ptr(&self) -> *const u8 {
-        self.inner.ptr.as_ptr()
+    pub fn as_ptr(&self) -> *const () {
+        self.inner.ptr.cast::<()>().as_ptr()
+    }
+
+    /// Returns a pointer to the context that may be written through, as the context is borrowed
+    /// uniquely.
+    pub fn as_mut_ptr(&self) -> *mut () {
+        self.inner.ptr.cast::<()>().as_ptr()
+    }
+
+    /// Drops the context in place without freeing its memory.
+    /// # Safety
+    /// The context must be a valid `GEN` that is not used again after this call.
+    #[allow(unsafe_code)]
+    pub unsafe fn drop_in_place<GEN>(self) {
+        // SAFETY: Upheld by the caller.
+        unsafe { core::ptr::drop_in_place(self.inner.ptr.cast::<GEN>().as_ptr()) }
     }
 
     /// Returns a reference to the vtable.
     pub fn get_vtable(&self) -> &T {
         #[allow(unsafe_code)]
+        // SAFETY: The vtable pointer always points to a live `'static` vtable.
         unsafe {
             self.inner.vtable.as_ref()
         }
     }
-}
 
-#[repr(transparent)]
-/// A reference to a C-compatible object.
-pub struct CRefMut<'a, T: ?Sized> {
-    inner: Inner<T>,
-    phantom: std::marker::PhantomData<&'a mut T>,
-}
+    /// Returns a shared reference to the object, borrowed for the lifetime of `self`.
+    pub fn as_cref(&self) -> CRef<'_, T> {
+        CRef {
+            inner: self.inner,
+            phantom: PhantomData,
+        }
+    }
 
-impl<'a, T: ?Sized> CRefMut<'a, T> {
-    /// Returns a pointer to the context.
-    pub fn as_ptr(&self) -> *const u8 {
-        self.inner.ptr.as_ptr()
+    /// Reborrows the reference for the lifetime of `self`, so it can be passed on without
+    /// being consumed.
+    pub fn reborrow(&mut self) -> CRefMut<'_, T> {
+        CRefMut {
+            inner: self.inner,
+            phantom: PhantomData,
+        }
     }
 
-    /// Returns a reference to the vtable.
-    pub fn get_vtable(&self) -> &T {
-        #[allow(unsafe_code)]
-        unsafe {
-            self.inner.vtable.as_ref()
+    /// Reborrows the reference, the same as [`CRefMut::reborrow`].
+    pub fn as_cref_mut(&mut self) -> CRefMut<'_, T> {
+        self.reborrow()
+    }
+
+    /// Maps the vtable to a new type using the provided function.
+    /// # Safety
+    /// The caller must ensure that `methods` is a vtable for the same implementer as this object.
+    #[allow(unsafe_code)]
+    pub unsafe fn as_cref_with_methods<U: ?Sized>(&self, methods: NonNull<U>) -> CRef<'_, U> {
+        CRef {
+            // SAFETY: Upheld by the caller.
+            inner: unsafe { self.inner.map_vtable(|_| methods) },
+            phantom: PhantomData,
         }
     }
-}
 
-impl<T: ?Sized> DerefMut for CRef<'_, T> {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        #[allow(unsafe_code)]
-        unsafe {
-            self.inner.vtable.as_mut()
+    /// Maps the vtable to a new type using the provided function.
+    /// # Safety
+    /// The caller must ensure that `methods` is a vtable for the same implementer as this object.
+    #[allow(unsafe_code)]
+    pub unsafe fn as_cref_mut_with_methods<U: ?Sized>(
+        &mut self,
+        methods: NonNull<U>,
+    ) -> CRefMut<'_, U> {
+        CRefMut {
+            // SAFETY: Upheld by the caller.
+            inner: unsafe { self.inner.map_vtable(|_| methods) },
+            phantom: PhantomData,
         }
     }
 }
 
+#[allow(unsafe_code)]
+// SAFETY: A `CRef` is a shared borrow of the context, which is `Sync` for a `CSync` vtable.
+unsafe impl<T: CSync + ?Sized> Send for CRef<'_, T> {}
+#[allow(unsafe_code)]
+// SAFETY: A `CRef` only gives shared access to a context that is `Sync` for a `CSync` vtable.
+unsafe impl<T: CSync + ?Sized> Sync for CRef<'_, T> {}
+#[allow(unsafe_code)]
+// SAFETY: A `CRefMut` is a unique borrow of the context, which is `Send` for a `CSend` vtable.
+unsafe impl<T: CSend + ?Sized> Send for CRefMut<'_, T> {}
+#[allow(unsafe_code)]
+// SAFETY: Through `&CRefMut` the context is only shared, and it is `Sync` for a `CSync` vtable.
+unsafe impl<T: CSync + ?Sized> Sync for CRefMut<'_, T> {}
+
+// a `CRefMut` is a unique borrow, so only `CRef` is `Copy`, use `CRefMut::reborrow` instead
+impl<T: ?Sized> Copy for CRef<'_, T> {}
+impl<T: ?Sized> Clone for CRef<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
 macro_rules! impl_c_ref {
     ($name:ident) => {
-        impl<T: ?Sized> Copy for $name<'_, T> {}
-        impl<T: ?Sized> Clone for $name<'_, T> {
-            fn clone(&self) -> Self {
-                *self
-            }
-        }
-
         impl<T: ?Sized> Deref for $name<'_, T> {
             type Target = T;
             fn deref(&self) -> &Self::Target {
                 #[allow(unsafe_code)]
+                // SAFETY: The vtable pointer always points to a live `'static` vtable.
                 unsafe {
                     self.inner.vtable.as_ref()
                 }
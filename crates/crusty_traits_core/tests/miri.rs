@@ -0,0 +1,218 @@
+//! Exercises the pointer handling of the wrappers, run with `cargo +nightly miri test -p
+//! crusty_traits_core` to check it under Stacked and Tree Borrows.
+#![allow(missing_docs, unsafe_code)]
+
+use core::{
+    alloc::Layout,
+    ffi::c_void,
+    mem::MaybeUninit,
+    ptr::NonNull,
+    sync::atomic::{AtomicU32, AtomicUsize, Ordering},
+};
+
+use crusty_traits_core::*;
+use crusty_traits_macros::crusty_trait;
+
+#[crusty_trait]
+trait Named {
+    fn id(&self) -> u32;
+}
+
+#[crusty_trait]
+trait Counter: Named {
+    fn get(&self) -> u32;
+    fn bump(&self);
+    fn add(&mut self, value: u32);
+}
+
+struct Tally {
+    id: u32,
+    count: AtomicU32,
+    dropped: Option<&'static AtomicUsize>,
+}
+
+impl Tally {
+    fn new(id: u32) -> Self {
+        Self {
+            id,
+            count: AtomicU32::new(0),
+            dropped: None,
+        }
+    }
+
+    /// Creates a tally that counts its drops in `dropped`, which belongs to a single test.
+    fn counted(id: u32, dropped: &'static AtomicUsize) -> Self {
+        Self {
+            id,
+            count: AtomicU32::new(0),
+            dropped: Some(dropped),
+        }
+    }
+}
+
+impl Drop for Tally {
+    fn drop(&mut self) {
+        if let Some(dropped) = self.dropped {
+            dropped.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+impl Named for Tally {
+    fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+impl Counter for Tally {
+    fn get(&self) -> u32 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    fn bump(&self) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn add(&mut self, value: u32) {
+        *self.count.get_mut() += value;
+    }
+}
+
+/// Calls every method of `counter`, both directly and through a `CRef` to the super trait.
+fn exercise(mut counter: CRefMut<CounterVTable>) {
+    let before = counter.get();
+    counter.bump();
+    counter.add(2);
+    assert_eq!(counter.get(), before + 3);
+
+    let named: &'static NamedVTable = counter.get_vtable().as_vtable();
+    // SAFETY: The super trait vtable belongs to the same implementer.
+    let named = unsafe { counter.as_cref_with_methods(NonNull::from(named)) };
+    assert_eq!(named.id(), 7);
+    assert_eq!(counter.id(), 7);
+}
+
+#[test]
+fn boxed_objects() {
+    let mut counter = CounterVTable::new_boxed(Tally::new(7));
+    exercise(counter.as_cref_mut());
+    counter.as_cref().bump();
+    assert_eq!(counter.get(), 4);
+}
+
+#[test]
+fn allocated_objects() {
+    let mut counter = CounterVTable::new_in(Global, Tally::new(7)).ok().unwrap();
+    exercise(counter.as_cref_mut());
+}
+
+#[test]
+fn objects_in_a_buffer() {
+    let mut buffer = MaybeUninit::<[u64; 2]>::uninit();
+    let layout = Layout::new::<[u64; 2]>();
+    // SAFETY: The buffer outlives the object and is not used by anything else.
+    let mut counter = unsafe {
+        CounterVTable::new_in_place(NonNull::from(&mut buffer).cast(), layout, Tally::new(7))
+    }
+    .ok()
+    .unwrap();
+    exercise(counter.as_cref_mut());
+}
+
+#[test]
+fn inline_objects_mutate_through_shared_references() {
+    let mut counter: CReprInline<CounterVTable, 2> =
+        CReprInline::new(CounterVTable::create_vtable::<Tally>(), Tally::new(7));
+    assert!(counter.is_inline());
+    counter.bump();
+    exercise(counter.as_cref_mut());
+
+    // the inline context moves with the object
+    let moved = counter;
+    moved.as_cref().bump();
+    assert_eq!(moved.get(), 5);
+}
+
+#[test]
+fn scoped_objects() {
+    let mut counter = CounterVTable::new_scoped(Tally::new(7));
+    exercise(counter.as_cref_mut());
+    assert_eq!(counter.get(), 3);
+}
+
+#[test]
+fn borrowed_objects() {
+    let mut tally = Tally::new(7);
+    exercise(CounterVTable::borrow_mut(&mut tally));
+    CounterVTable::borrow(&tally).bump();
+    assert_eq!(tally.get(), 4);
+}
+
+#[test]
+fn reborrowed_objects() {
+    let mut tally = Tally::new(7);
+    let mut counter = CounterVTable::borrow_mut(&mut tally);
+    let mut reborrowed = counter.reborrow();
+    reborrowed.add(2);
+    exercise(reborrowed);
+    // the original is usable again once the reborrow is gone
+    counter.add(1);
+    assert_eq!(counter.get(), 6);
+    exercise(counter);
+    assert_eq!(tally.get(), 9);
+}
+
+#[test]
+fn shared_objects() {
+    let counter = CArc::new(CounterVTable::create_vtable::<Tally>(), Tally::new(7));
+    let other = counter.clone();
+    let weak = counter.downgrade();
+    counter.bump();
+    assert_eq!(other.get(), 1);
+    drop(counter);
+    drop(other);
+    assert!(weak.upgrade().is_none());
+}
+
+#[test]
+fn every_wrapper_drops_its_context_once() {
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+    let tally = || Tally::counted(7, &DROPPED);
+    drop(CounterVTable::new_boxed(tally()));
+    drop(CounterVTable::new_in(Global, tally()));
+    drop(CounterVTable::new_scoped(tally()));
+    drop(CReprInline::<CounterVTable, 2>::new(
+        CounterVTable::create_vtable::<Tally>(),
+        tally(),
+    ));
+    drop(CReprInline::<CounterVTable, 0>::new(
+        CounterVTable::create_vtable::<Tally>(),
+        tally(),
+    ));
+    drop(CArc::new(CounterVTable::create_vtable::<Tally>(), tally()));
+    assert_eq!(DROPPED.load(Ordering::SeqCst), 6);
+}
+
+unsafe extern "C" fn c_id(_: CRef<NamedVTable>) -> u32 {
+    9
+}
+
+unsafe extern "C" fn c_drop(this: CRefMut<NamedVTable>) {
+    // SAFETY: The context was allocated as a `Box<u32>` by the test.
+    drop(unsafe { Box::from_raw(this.as_mut_ptr().cast::<u32>()) });
+}
+
+static C_VTABLE: NamedVTable = NamedVTable {
+    vtable_size: size_of::<NamedVTable>(),
+    id: c_id,
+    drop: c_drop,
+};
+
+#[test]
+fn c_objects_own_their_context() {
+    let context = NonNull::from(Box::leak(Box::new(5u32))).cast::<c_void>();
+    // SAFETY: The vtable is static and its `drop` frees the context.
+    let named = unsafe { CRepr::from_raw_parts(NonNull::from(&C_VTABLE), context) };
+    assert_eq!(named.id(), 9);
+    assert_eq!(named.as_ptr(), context.cast::<()>().as_ptr().cast_const());
+}
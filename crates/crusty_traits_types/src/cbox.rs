@@ -0,0 +1,233 @@
+//! A C-compatible owned pointer to a single value, for handing heap values across FFI without
+//! defining a trait.
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+use core::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    mem::{ManuallyDrop, size_of},
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+};
+
+/// Drops the value behind a [`CBox`] and frees its memory.
+pub type CBoxDrop<T> = unsafe extern "C" fn(ptr: NonNull<T>);
+
+/// An owned pointer to a value, along with the function that drops and frees it, recorded when
+/// the box was created, so the library that allocated the value always frees it.
+///
+/// Both fields are non-null, so `Option<CBox<T>>` is the same size as `CBox<T>`.
+#[repr(C)]
+pub struct CBox<T> {
+    ptr: NonNull<T>,
+    drop: CBoxDrop<T>,
+}
+
+const _: () = assert!(size_of::<Option<CBox<u8>>>() == size_of::<CBox<u8>>());
+
+#[allow(unsafe_code)]
+// SAFETY: A `CBox` owns its value, like `Box`, and its drop function may be called on any thread,
+// see `CBox::from_raw_parts`.
+unsafe impl<T: Send> Send for CBox<T> {}
+#[allow(unsafe_code)]
+// SAFETY: A `CBox` only gives shared access to its value through `&self`.
+unsafe impl<T: Sync> Sync for CBox<T> {}
+
+/// The drop function of values allocated by a Rust `Box`.
+#[cfg(feature = "alloc")]
+#[allow(unsafe_code)]
+unsafe extern "C" fn drop_box<T>(ptr: NonNull<T>) {
+    // SAFETY: The value was allocated by a `Box`, see `CBox::new`.
+    drop(unsafe { Box::from_raw(ptr.as_ptr()) });
+}
+
+impl<T> CBox<T> {
+    /// Moves `value` to the heap.
+    #[cfg(feature = "alloc")]
+    pub fn new(value: T) -> Self {
+        Box::new(value).into()
+    }
+
+    /// Takes ownership of the value at `ptr`.
+    /// # Safety
+    /// `ptr` must point to a valid value that `drop` drops and frees, from any thread if `T` is
+    /// `Send`.
+    #[allow(unsafe_code)]
+    pub const unsafe fn from_raw_parts(ptr: NonNull<T>, drop: CBoxDrop<T>) -> Self {
+        Self { ptr, drop }
+    }
+
+    /// Returns the pointer and drop function of the box, without dropping the value.
+    pub fn into_raw_parts(self) -> (NonNull<T>, CBoxDrop<T>) {
+        let boxed = ManuallyDrop::new(self);
+        (boxed.ptr, boxed.drop)
+    }
+
+    /// Returns the value as a `Box`, without going through the drop function.
+    /// # Safety
+    /// The value must have been allocated by the global allocator of this library, with the
+    /// layout of `T`, such as by [`CBox::new`].
+    #[cfg(feature = "alloc")]
+    #[allow(unsafe_code)]
+    pub unsafe fn into_box(self) -> Box<T> {
+        let (ptr, _) = self.into_raw_parts();
+        // SAFETY: Upheld by the caller.
+        unsafe { Box::from_raw(ptr.as_ptr()) }
+    }
+
+    /// Returns a pointer to the value.
+    pub fn as_ptr(&self) -> NonNull<T> {
+        self.ptr
+    }
+}
+
+impl<T> Drop for CBox<T> {
+    fn drop(&mut self) {
+        #[allow(unsafe_code)]
+        // SAFETY: The box owns its value, which `drop` drops and frees.
+        unsafe {
+            (self.drop)(self.ptr);
+        }
+    }
+}
+
+impl<T> Deref for CBox<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        #[allow(unsafe_code)]
+        // SAFETY: The box owns a valid value, see `CBox::from_raw_parts`.
+        unsafe {
+            self.ptr.as_ref()
+        }
+    }
+}
+
+impl<T> DerefMut for CBox<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        #[allow(unsafe_code)]
+        // SAFETY: The box owns a valid value, and `&mut self` gives exclusive access to it.
+        unsafe {
+            self.ptr.as_mut()
+        }
+    }
+}
+
+impl<T> AsRef<T> for CBox<T> {
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<T> AsMut<T> for CBox<T> {
+    fn as_mut(&mut self) -> &mut T {
+        self
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T> From<Box<T>> for CBox<T> {
+    fn from(boxed: Box<T>) -> Self {
+        let ptr = NonNull::from(Box::leak(boxed));
+        #[allow(unsafe_code)]
+        // SAFETY: The value was allocated by a `Box`, which `drop_box` frees.
+        unsafe {
+            Self::from_raw_parts(ptr, drop_box::<T>)
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T> From<T> for CBox<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Clone> Clone for CBox<T> {
+    fn clone(&self) -> Self {
+        Self::new(T::clone(self))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Default> Default for CBox<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for CBox<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        T::fmt(self, f)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for CBox<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        T::fmt(self, f)
+    }
+}
+
+impl<T: PartialEq> PartialEq for CBox<T> {
+    fn eq(&self, other: &Self) -> bool {
+        T::eq(self, other)
+    }
+}
+
+impl<T: Eq> Eq for CBox<T> {}
+
+impl<T: PartialOrd> PartialOrd for CBox<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        T::partial_cmp(self, other)
+    }
+}
+
+impl<T: Ord> Ord for CBox<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        T::cmp(self, other)
+    }
+}
+
+impl<T: Hash> Hash for CBox<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        T::hash(self, state);
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    #![allow(unsafe_code)]
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn test_cbox() {
+        let mut boxed = CBox::new(1);
+        *boxed += 1;
+        assert_eq!(*boxed, 2);
+        assert_eq!(boxed.clone(), boxed);
+        // SAFETY: The value was allocated by `CBox::new`.
+        assert_eq!(unsafe { boxed.into_box() }, Box::new(2));
+    }
+
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    unsafe extern "C" fn counted_drop(ptr: NonNull<usize>) {
+        // SAFETY: The value is borrowed from the test.
+        DROPPED.fetch_add(unsafe { *ptr.as_ref() }, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn test_cbox_from_raw_parts() {
+        let mut value = 3;
+        // SAFETY: The value outlives the box, and `counted_drop` doesn't free it.
+        let boxed = unsafe { CBox::from_raw_parts(NonNull::from(&mut value), counted_drop) };
+        assert_eq!(*boxed, 3);
+        drop(boxed);
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,342 @@
+//! C-compatible futures, polled by the host's executor through a vtable.
+//!
+//! Async methods of a `crusty_trait` return a [`CFuture`] object, and the waker of the task
+//! polling it is passed across as a [`CContext`].
+use alloc::boxed::Box;
+use core::{
+    future::Future,
+    mem::ManuallyDrop,
+    pin::Pin,
+    ptr::{self, NonNull},
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+};
+use crusty_traits_core::*;
+use crusty_traits_macros::crusty_trait;
+
+/// The result of polling a [`CFuture`], a C-compatible [`Poll`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CPoll<T> {
+    /// The future resolved to a value.
+    Ready(T),
+    /// The future will wake the task once it can make progress.
+    Pending,
+}
+
+impl<T> From<Poll<T>> for CPoll<T> {
+    fn from(poll: Poll<T>) -> Self {
+        match poll {
+            Poll::Ready(value) => CPoll::Ready(value),
+            Poll::Pending => CPoll::Pending,
+        }
+    }
+}
+
+impl<T> From<CPoll<T>> for Poll<T> {
+    fn from(poll: CPoll<T>) -> Self {
+        match poll {
+            CPoll::Ready(value) => Poll::Ready(value),
+            CPoll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// The functions of a [`CWaker`], the C-compatible counterpart of a [`RawWakerVTable`].
+#[repr(C)]
+pub struct CWakerVTable {
+    /// Returns a new waker for the same task.
+    pub clone: unsafe extern "C" fn(data: *const ()) -> CWaker,
+    /// Wakes the task and frees the waker.
+    pub wake: unsafe extern "C" fn(data: *const ()),
+    /// Wakes the task without freeing the waker.
+    pub wake_by_ref: unsafe extern "C" fn(data: *const ()),
+    /// Frees the waker.
+    pub drop: unsafe extern "C" fn(data: *const ()),
+}
+
+/// A C-compatible [`Waker`], which wakes a task through its vtable.
+///
+/// Like a `Waker` it may be cloned, woken and dropped from any thread.
+#[repr(C)]
+pub struct CWaker {
+    data: *const (),
+    vtable: &'static CWakerVTable,
+}
+
+#[allow(unsafe_code)]
+// SAFETY: The functions of the vtable may be called from any thread, see `CWaker::new`.
+unsafe impl Send for CWaker {}
+#[allow(unsafe_code)]
+// SAFETY: The functions of the vtable may be called from any thread, see `CWaker::new`.
+unsafe impl Sync for CWaker {}
+
+impl CWaker {
+    /// Creates a new `CWaker` from its data and vtable.
+    /// # Safety
+    /// The functions of `vtable` must uphold the contract of a [`RawWakerVTable`] for `data`,
+    /// including being callable from any thread.
+    #[allow(unsafe_code)]
+    pub const unsafe fn new(data: *const (), vtable: &'static CWakerVTable) -> Self {
+        Self { data, vtable }
+    }
+
+    /// Returns the data passed to the functions of the vtable.
+    pub fn data(&self) -> *const () {
+        self.data
+    }
+
+    /// Returns the vtable of the waker.
+    pub fn vtable(&self) -> &'static CWakerVTable {
+        self.vtable
+    }
+
+    /// Wakes the task, consuming the waker.
+    pub fn wake(self) {
+        let this = ManuallyDrop::new(self);
+        #[allow(unsafe_code)]
+        // SAFETY: The waker is consumed and not dropped again.
+        unsafe {
+            (this.vtable.wake)(this.data);
+        }
+    }
+
+    /// Wakes the task without consuming the waker.
+    pub fn wake_by_ref(&self) {
+        #[allow(unsafe_code)]
+        // SAFETY: The data is valid for the vtable.
+        unsafe {
+            (self.vtable.wake_by_ref)(self.data);
+        }
+    }
+
+    /// Returns the Rust waker wrapped by this waker, if it was created from one.
+    fn as_waker(&self) -> Option<&Waker> {
+        let wraps_waker =
+            ptr::eq(self.vtable, &OWNED_WAKER) || ptr::eq(self.vtable, &BORROWED_WAKER);
+        #[allow(unsafe_code)]
+        // SAFETY: Both vtables are only used with a `Waker` as their data.
+        wraps_waker.then(|| unsafe { &*self.data.cast::<Waker>() })
+    }
+}
+
+impl Clone for CWaker {
+    fn clone(&self) -> Self {
+        #[allow(unsafe_code)]
+        // SAFETY: The data is valid for the vtable.
+        unsafe {
+            (self.vtable.clone)(self.data)
+        }
+    }
+}
+
+impl Drop for CWaker {
+    fn drop(&mut self) {
+        #[allow(unsafe_code)]
+        // SAFETY: The waker is not used again.
+        unsafe {
+            (self.vtable.drop)(self.data);
+        }
+    }
+}
+
+impl From<Waker> for CWaker {
+    fn from(waker: Waker) -> Self {
+        Self {
+            data: Box::into_raw(Box::new(waker)).cast_const().cast(),
+            vtable: &OWNED_WAKER,
+        }
+    }
+}
+
+impl From<CWaker> for Waker {
+    fn from(waker: CWaker) -> Self {
+        if ptr::eq(waker.vtable, &OWNED_WAKER) {
+            let waker = ManuallyDrop::new(waker);
+            #[allow(unsafe_code)]
+            // SAFETY: The data of `OWNED_WAKER` is a boxed `Waker`, which is moved out once.
+            return *unsafe { Box::from_raw(waker.data.cast::<Waker>().cast_mut()) };
+        }
+        let data = Box::into_raw(Box::new(waker)).cast_const().cast();
+        #[allow(unsafe_code)]
+        // SAFETY: `C_WAKER` upholds the contract of `RawWakerVTable` for a boxed `CWaker`.
+        unsafe {
+            Waker::from_raw(RawWaker::new(data, &C_WAKER))
+        }
+    }
+}
+
+/// Wraps a boxed `Waker`.
+static OWNED_WAKER: CWakerVTable = CWakerVTable {
+    clone: clone_waker,
+    wake: wake_owned_waker,
+    wake_by_ref: wake_waker_by_ref,
+    drop: drop_owned_waker,
+};
+
+/// Wraps a `Waker` borrowed for the duration of a poll, see [`CContext::scope`].
+static BORROWED_WAKER: CWakerVTable = CWakerVTable {
+    clone: clone_waker,
+    wake: wake_waker_by_ref,
+    wake_by_ref: wake_waker_by_ref,
+    drop: drop_borrowed,
+};
+
+#[allow(unsafe_code)]
+unsafe extern "C" fn clone_waker(data: *const ()) -> CWaker {
+    // SAFETY: The data is a live `Waker`.
+    CWaker::from(unsafe { &*data.cast::<Waker>() }.clone())
+}
+
+#[allow(unsafe_code)]
+unsafe extern "C" fn wake_owned_waker(data: *const ()) {
+    // SAFETY: The data is a boxed `Waker`, which is consumed.
+    unsafe { Box::from_raw(data.cast::<Waker>().cast_mut()) }.wake();
+}
+
+#[allow(unsafe_code)]
+unsafe extern "C" fn wake_waker_by_ref(data: *const ()) {
+    // SAFETY: The data is a live `Waker`.
+    unsafe { &*data.cast::<Waker>() }.wake_by_ref();
+}
+
+#[allow(unsafe_code)]
+unsafe extern "C" fn drop_owned_waker(data: *const ()) {
+    // SAFETY: The data is a boxed `Waker`, which is not used again.
+    drop(unsafe { Box::from_raw(data.cast::<Waker>().cast_mut()) });
+}
+
+extern "C" fn drop_borrowed(_: *const ()) {}
+
+/// Wraps a boxed `CWaker` in a Rust `Waker`.
+static C_WAKER: RawWakerVTable = RawWakerVTable::new(
+    clone_c_waker,
+    wake_owned_c_waker,
+    wake_c_waker_by_ref,
+    drop_owned_c_waker,
+);
+
+/// Wraps a `CWaker` borrowed from a `CContext` in a Rust `Waker`.
+static BORROWED_C_WAKER: RawWakerVTable = RawWakerVTable::new(
+    clone_c_waker,
+    wake_c_waker_by_ref,
+    wake_c_waker_by_ref,
+    |_| {},
+);
+
+#[allow(unsafe_code)]
+unsafe fn clone_c_waker(data: *const ()) -> RawWaker {
+    // SAFETY: The data is a live `CWaker`.
+    let waker = unsafe { &*data.cast::<CWaker>() }.clone();
+    RawWaker::new(Box::into_raw(Box::new(waker)).cast_const().cast(), &C_WAKER)
+}
+
+#[allow(unsafe_code)]
+unsafe fn wake_owned_c_waker(data: *const ()) {
+    // SAFETY: The data is a boxed `CWaker`, which is consumed.
+    unsafe { Box::from_raw(data.cast::<CWaker>().cast_mut()) }.wake();
+}
+
+#[allow(unsafe_code)]
+unsafe fn wake_c_waker_by_ref(data: *const ()) {
+    // SAFETY: The data is a live `CWaker`.
+    unsafe { &*data.cast::<CWaker>() }.wake_by_ref();
+}
+
+#[allow(unsafe_code)]
+unsafe fn drop_owned_c_waker(data: *const ()) {
+    // SAFETY: The data is a boxed `CWaker`, which is not used again.
+    drop(unsafe { Box::from_raw(data.cast::<CWaker>().cast_mut()) });
+}
+
+/// The C-compatible [`Context`] passed to [`CFuture::poll`], borrowing the waker of the task.
+///
+/// Like a `Context`, the waker may be cloned or woken by reference but is not owned.
+#[repr(C)]
+pub struct CContext<'a> {
+    waker: &'a CWaker,
+}
+
+impl<'a> CContext<'a> {
+    /// Creates a context for polling with `waker`.
+    pub fn new(waker: &'a CWaker) -> Self {
+        Self { waker }
+    }
+
+    /// Returns the waker of the task.
+    pub fn waker(&self) -> &'a CWaker {
+        self.waker
+    }
+
+    /// Calls `f` with a Rust context that wakes the same task.
+    pub fn with_context<R>(&self, f: impl FnOnce(&mut Context<'_>) -> R) -> R {
+        if let Some(waker) = self.waker.as_waker() {
+            return f(&mut Context::from_waker(waker));
+        }
+        let data = ptr::from_ref(self.waker).cast();
+        #[allow(unsafe_code)]
+        // SAFETY: `BORROWED_C_WAKER` upholds the contract of `RawWakerVTable` for a `CWaker` that
+        // outlives the waker, and never frees it.
+        let waker = unsafe { Waker::from_raw(RawWaker::new(data, &BORROWED_C_WAKER)) };
+        f(&mut Context::from_waker(&waker))
+    }
+
+    /// Calls `f` with a context that wakes the task of the Rust context `cx`.
+    pub fn scope<R>(cx: &mut Context<'_>, f: impl FnOnce(CContext<'_>) -> R) -> R {
+        let waker = CWaker {
+            data: ptr::from_ref(cx.waker()).cast(),
+            vtable: &BORROWED_WAKER,
+        };
+        f(CContext::new(&waker))
+    }
+}
+
+#[crusty_trait]
+/// A future that is polled through a vtable, returned by the async methods of a `crusty_trait`.
+///
+/// Unlike [`Future::poll`] the object is not pinned, so implementers must stay valid when moved
+/// between polls. Rust futures are pinned in a box, see [`CFutureVTable::new_future`].
+pub trait CFuture<T> {
+    /// Attempts to resolve the future, waking the task of `cx` once it can make progress.
+    fn poll(&mut self, cx: CContext<'_>) -> CPoll<T>;
+}
+
+impl<F: Future + ?Sized> CFuture<F::Output> for Pin<Box<F>> {
+    fn poll(&mut self, cx: CContext<'_>) -> CPoll<F::Output> {
+        cx.with_context(|cx| Future::poll(self.as_mut(), cx)).into()
+    }
+}
+
+impl<T: 'static> CFutureVTable<T> {
+    /// Pins `future` in a box and returns it as a `CFuture` object.
+    pub fn new_future<F: Future<Output = T> + 'static>(future: F) -> CRepr<Self> {
+        Self::new_boxed(Box::pin(future))
+    }
+
+    /// Pins `future` in a box and returns it as a `CFuture` object borrowing data for `'a`.
+    pub fn new_scoped_future<'a, F: Future<Output = T> + 'a>(future: F) -> CReprScoped<'a, Self> {
+        Self::new_scoped(Box::pin(future))
+    }
+
+    /// Pins `future` in a box without checking that it lives for `'a`.
+    ///
+    /// Used by the async methods of a vtable, whose futures borrow the object they were called on.
+    /// # Safety
+    /// Everything `future` borrows must be valid for `'a`.
+    #[allow(unsafe_code)]
+    pub unsafe fn new_scoped_future_unchecked<'a, F: Future<Output = T>>(
+        future: F,
+    ) -> CReprScoped<'a, Self> {
+        let future: Pin<Box<F>> = Box::pin(future);
+        let vtable = Self::create_vtable::<Pin<Box<F>>>();
+        // SAFETY: The vtable is for `Pin<Box<F>>`, which is valid for `'a` per the caller.
+        unsafe { CRepr::new_boxed_unchecked(NonNull::from(vtable), future) }.into()
+    }
+}
+
+impl<T> CPollable for CFutureVTable<T> {
+    type Output = T;
+
+    fn poll(mut repr: CRefMut<Self>, cx: &mut Context<'_>) -> Poll<T> {
+        CContext::scope(cx, |cx| CFuture::poll(&mut repr, cx)).into()
+    }
+}
@@ -0,0 +1,124 @@
+//! C-compatible iterators, advanced by the host through a vtable.
+//!
+//! Methods of a `crusty_trait` returning `impl Iterator<Item = T>` or
+//! `Box<dyn Iterator<Item = T>>` return a [`CIterator`] object across the vtable.
+use crusty_traits_core::*;
+use crusty_traits_macros::crusty_trait;
+
+use crate::coption::COption;
+
+/// The bounds on the remaining length of a [`CIterator`], a C-compatible
+/// [`Iterator::size_hint`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CSizeHint {
+    /// The lower bound.
+    pub lower: usize,
+    /// The upper bound, or `None` if it is unknown or larger than `usize`.
+    pub upper: COption<usize>,
+}
+
+impl From<(usize, Option<usize>)> for CSizeHint {
+    fn from((lower, upper): (usize, Option<usize>)) -> Self {
+        Self {
+            lower,
+            upper: upper.into(),
+        }
+    }
+}
+
+impl From<CSizeHint> for (usize, Option<usize>) {
+    fn from(hint: CSizeHint) -> Self {
+        (hint.lower, hint.upper.into())
+    }
+}
+
+#[crusty_trait]
+/// An iterator that is advanced through a vtable.
+///
+/// The wrappers of the vtable implement [`Iterator`], Rust iterators are wrapped in an
+/// [`IterAdapter`], see [`CIteratorVTable::new_iter`]. The prelude of `crusty_traits` only
+/// exports the vtable, as importing this trait makes `next` ambiguous on its wrappers.
+pub trait CIterator<T> {
+    /// Advances the iterator and returns the next item.
+    fn next(&mut self) -> COption<T>;
+    /// Returns the bounds on the remaining length of the iterator.
+    fn size_hint(&self) -> CSizeHint;
+}
+
+/// Implements [`CIterator`] for any Rust [`Iterator`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IterAdapter<I>(pub I);
+
+impl<I: Iterator> CIterator<I::Item> for IterAdapter<I> {
+    fn next(&mut self) -> COption<I::Item> {
+        self.0.next().into()
+    }
+
+    fn size_hint(&self) -> CSizeHint {
+        self.0.size_hint().into()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: 'static> CIteratorVTable<T> {
+    /// Boxes `iter` and returns it as a `CIterator` object.
+    pub fn new_iter<I: Iterator<Item = T> + 'static>(iter: I) -> CRepr<Self> {
+        Self::new_boxed(IterAdapter(iter))
+    }
+
+    /// Boxes `iter` and returns it as a `CIterator` object borrowing data for `'a`.
+    pub fn new_scoped_iter<'a, I: Iterator<Item = T> + 'a>(iter: I) -> CReprScoped<'a, Self> {
+        Self::new_scoped(IterAdapter(iter))
+    }
+
+    /// Boxes `iter` without checking that it lives for `'a`.
+    ///
+    /// Used by the vtable methods returning iterators, which may borrow the object they were
+    /// called on.
+    /// # Safety
+    /// Everything `iter` borrows must be valid for `'a`.
+    #[allow(unsafe_code)]
+    pub unsafe fn new_scoped_iter_unchecked<'a, I: Iterator<Item = T>>(
+        iter: I,
+    ) -> CReprScoped<'a, Self> {
+        let vtable = Self::create_vtable::<IterAdapter<I>>();
+        // SAFETY: The vtable is for `IterAdapter<I>`, which is valid for `'a` per the caller.
+        unsafe { CRepr::new_boxed_unchecked(core::ptr::NonNull::from(vtable), IterAdapter(iter)) }
+            .into()
+    }
+}
+
+impl<T> CIterable for CIteratorVTable<T> {
+    type Item = T;
+
+    fn next(mut repr: CRefMut<Self>) -> Option<T> {
+        CIterator::next(&mut repr).into()
+    }
+
+    fn size_hint(repr: CRef<Self>) -> (usize, Option<usize>) {
+        CIteratorRef::size_hint(&repr).into()
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_citerator() {
+        let iter = CIteratorVTable::new_iter([1, 2, 3].into_iter());
+        assert_eq!(Iterator::size_hint(&iter), (3, Some(3)));
+        assert_eq!(iter.map(|i| i * 2).collect::<Vec<_>>(), [2, 4, 6]);
+    }
+
+    #[test]
+    fn test_scoped_citerator() {
+        let words = ["a", "b"];
+        let mut iter = CIteratorVTable::new_scoped_iter(words.iter().copied().filter(|_| true));
+        assert_eq!(Iterator::size_hint(&iter), (0, Some(2)));
+        assert_eq!(Iterator::next(&mut iter), Some("a"));
+        assert_eq!(Iterator::next(&mut iter.as_cref_mut()), Some("b"));
+        assert_eq!(Iterator::next(&mut iter), None);
+    }
+}
@@ -0,0 +1,290 @@
+//! C-compatible maps, for exchanging key-value data such as configuration through a vtable.
+//!
+//! [`CMap`] is implemented for `BTreeMap` and, with the `std` feature, `HashMap`. Its lookups
+//! return pointers into the map, which [`CMapExt`] borrows with the map like the `HashMap` API.
+#[cfg(feature = "std")]
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, Hash},
+};
+
+use alloc::collections::BTreeMap;
+use core::{fmt, ptr};
+use crusty_traits_core::*;
+use crusty_traits_macros::crusty_trait;
+
+use crate::{citerator::CIteratorVTable, coption::COption};
+
+/// An entry of a [`CMap`], pointing to a key and its value in the map.
+#[repr(C)]
+pub struct CMapEntry<K, V> {
+    /// The key of the entry.
+    pub key: *const K,
+    /// The value of the entry.
+    pub value: *const V,
+}
+
+impl<K, V> CMapEntry<K, V> {
+    /// Points to `key` and `value`.
+    pub fn new(key: &K, value: &V) -> Self {
+        Self { key, value }
+    }
+}
+
+impl<K, V> Clone for CMapEntry<K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, V> Copy for CMapEntry<K, V> {}
+
+impl<K, V> fmt::Debug for CMapEntry<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CMapEntry")
+            .field("key", &self.key)
+            .field("value", &self.value)
+            .finish()
+    }
+}
+
+#[crusty_trait]
+/// A trait that represents a C-compatible map.
+///
+/// The pointers returned by `get_ptr`, `get_mut_ptr` and `entries` are null for missing keys and
+/// valid until the map is modified, see [`CMapExt`] for borrowing them.
+#[allow(clippy::len_without_is_empty)]
+pub trait CMap<K, V> {
+    /// Returns the number of entries in the map.
+    fn len(&self) -> usize;
+    /// Returns a pointer to the value of `key`, or null if it is missing.
+    fn get_ptr(&self, key: &K) -> *const V;
+    /// Returns a mutable pointer to the value of `key`, or null if it is missing.
+    fn get_mut_ptr(&mut self, key: &K) -> *mut V;
+    /// Inserts a value for `key`, returning the value it replaced.
+    fn insert(&mut self, key: K, value: V) -> COption<V>;
+    /// Removes `key` from the map, returning its value.
+    fn remove(&mut self, key: &K) -> COption<V>;
+    /// Removes all entries of the map.
+    fn clear(&mut self);
+    /// Returns an iterator over the entries of the map, in the map's order.
+    fn entries(&self) -> impl Iterator<Item = CMapEntry<K, V>>;
+}
+
+/// Extension methods for the `CMap` trait, borrowing the pointers it returns.
+pub trait CMapExt<K, V>: CMap<K, V> {
+    /// Returns whether the map has no entries.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the value of `key`.
+    fn get(&self, key: &K) -> Option<&V> {
+        #[allow(unsafe_code)]
+        // SAFETY: Implementers return null or a pointer to the value, borrowed with the map.
+        unsafe {
+            self.get_ptr(key).as_ref()
+        }
+    }
+
+    /// Returns the value of `key` mutably.
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        #[allow(unsafe_code)]
+        // SAFETY: As in `get`, and `&mut self` gives exclusive access to the value.
+        unsafe {
+            self.get_mut_ptr(key).as_mut()
+        }
+    }
+
+    /// Returns whether the map has a value for `key`.
+    fn contains_key(&self, key: &K) -> bool {
+        !self.get_ptr(key).is_null()
+    }
+
+    /// Returns an iterator over the keys and values of the map.
+    fn iter<'a>(&'a self) -> impl Iterator<Item = (&'a K, &'a V)>
+    where
+        K: 'a,
+        V: 'a,
+    {
+        self.entries().map(|entry| {
+            #[allow(unsafe_code)]
+            // SAFETY: Implementers return entries of the map, borrowed with it.
+            unsafe {
+                (&*entry.key, &*entry.value)
+            }
+        })
+    }
+
+    /// Returns an iterator over the keys of the map.
+    fn keys<'a>(&'a self) -> impl Iterator<Item = &'a K>
+    where
+        K: 'a,
+        V: 'a,
+    {
+        self.iter().map(|(key, _)| key)
+    }
+
+    /// Returns an iterator over the values of the map.
+    fn values<'a>(&'a self) -> impl Iterator<Item = &'a V>
+    where
+        K: 'a,
+        V: 'a,
+    {
+        self.iter().map(|(_, value)| value)
+    }
+}
+
+impl<T, K, V> CMapExt<K, V> for T where T: CMap<K, V> {}
+
+/// Returns a pointer to `value`, or null.
+fn value_ptr<V>(value: Option<&V>) -> *const V {
+    value.map_or(ptr::null(), ptr::from_ref)
+}
+
+/// Returns a mutable pointer to `value`, or null.
+fn value_mut_ptr<V>(value: Option<&mut V>) -> *mut V {
+    value.map_or(ptr::null_mut(), ptr::from_mut)
+}
+
+#[cfg(feature = "std")]
+impl<K: Eq + Hash, V, S: BuildHasher> CMap<K, V> for HashMap<K, V, S> {
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn get_ptr(&self, key: &K) -> *const V {
+        value_ptr(self.get(key))
+    }
+
+    fn get_mut_ptr(&mut self, key: &K) -> *mut V {
+        value_mut_ptr(self.get_mut(key))
+    }
+
+    fn insert(&mut self, key: K, value: V) -> COption<V> {
+        self.insert(key, value).into()
+    }
+
+    fn remove(&mut self, key: &K) -> COption<V> {
+        self.remove(key).into()
+    }
+
+    fn clear(&mut self) {
+        self.clear();
+    }
+
+    fn entries(&self) -> impl Iterator<Item = CMapEntry<K, V>> {
+        self.iter().map(|(key, value)| CMapEntry::new(key, value))
+    }
+}
+
+impl<K: Ord, V> CMap<K, V> for BTreeMap<K, V> {
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn get_ptr(&self, key: &K) -> *const V {
+        value_ptr(self.get(key))
+    }
+
+    fn get_mut_ptr(&mut self, key: &K) -> *mut V {
+        value_mut_ptr(self.get_mut(key))
+    }
+
+    fn insert(&mut self, key: K, value: V) -> COption<V> {
+        self.insert(key, value).into()
+    }
+
+    fn remove(&mut self, key: &K) -> COption<V> {
+        self.remove(key).into()
+    }
+
+    fn clear(&mut self) {
+        self.clear();
+    }
+
+    fn entries(&self) -> impl Iterator<Item = CMapEntry<K, V>> {
+        self.iter().map(|(key, value)| CMapEntry::new(key, value))
+    }
+}
+
+/// Returns the entries of the object behind `repr` as references.
+pub(crate) fn borrowed_entries<'a, K, V>(
+    repr: &'a CRef<'_, CMapVTable<K, V>>,
+) -> impl Iterator<Item = (&'a K, &'a V)> {
+    CMapRef::entries(repr).map(|entry| {
+        #[allow(unsafe_code)]
+        // SAFETY: Implementers return entries of the map, borrowed with `repr`.
+        unsafe {
+            (&*entry.key, &*entry.value)
+        }
+    })
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> CDebug for CMapVTable<K, V> {
+    fn fmt(repr: CRef<Self>, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(borrowed_entries(&repr)).finish()
+    }
+}
+
+impl<K, V: PartialEq> CPartialEq for CMapVTable<K, V> {
+    // every object is compared by its entries, so they all share one implementation
+    fn implementation(_repr: CRef<Self>) -> CImplementationId {
+        CImplementationId::default()
+    }
+
+    #[allow(unsafe_code)]
+    unsafe fn eq(a: CRef<Self>, b: CRef<Self>) -> bool {
+        Self::eq_objects(a, b)
+    }
+
+    fn eq_objects(a: CRef<Self>, b: CRef<Self>) -> bool {
+        CMapRef::len(&a) == CMapRef::len(&b)
+            && borrowed_entries(&a).all(|(key, value)| {
+                #[allow(unsafe_code)]
+                // SAFETY: `get_ptr` returns null or a pointer to the value, borrowed with `b`.
+                unsafe { CMapRef::get_ptr(&b, key).as_ref() }.is_some_and(|other| value == other)
+            })
+    }
+}
+
+impl<K, V: Eq> CEq for CMapVTable<K, V> {}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use std::string::{String, ToString};
+
+    use super::*;
+
+    fn test_cmap_inner(mut map: CRepr<CMapVTable<String, u32>>) {
+        assert!(map.is_empty());
+        assert_eq!(map.insert("a".to_string(), 1), COption::None);
+        assert_eq!(map.insert("b".to_string(), 2), COption::None);
+        assert_eq!(map.insert("a".to_string(), 3), COption::Some(1));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&"a".to_string()), Some(&3));
+        assert!(!map.contains_key(&"c".to_string()));
+        *map.get_mut(&"b".to_string()).unwrap() += 1;
+        assert_eq!(map.values().sum::<u32>(), 6);
+        assert_eq!(map.remove(&"a".to_string()), COption::Some(3));
+        assert_eq!(map.keys().collect::<Vec<_>>(), ["b"]);
+        map.clear();
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_cmap() {
+        test_cmap_inner(CMapVTable::new_boxed(HashMap::new()));
+        test_cmap_inner(CMapVTable::new_boxed(BTreeMap::new()));
+    }
+
+    #[test]
+    fn test_cmap_std_traits() {
+        let map = CMapVTable::new_boxed(BTreeMap::from([(1, "one"), (2, "two")]));
+        assert_eq!(format!("{map:?}"), r#"{1: "one", 2: "two"}"#);
+        let other = CMapVTable::new_boxed(HashMap::from([(2, "two"), (1, "one")]));
+        assert_eq!(map, other);
+        let other = CMapVTable::new_boxed(HashMap::from([(1, "one")]));
+        assert_ne!(map, other);
+    }
+}
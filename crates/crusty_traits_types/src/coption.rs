@@ -0,0 +1,50 @@
+//! A C-compatible option type, for values that may be missing across FFI.
+
+/// A C-compatible [`Option`].
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum COption<T> {
+    /// No value.
+    #[default]
+    None,
+    /// Some value of type `T`.
+    Some(T),
+}
+
+impl<T> COption<T> {
+    /// Returns `true` if the option is a `Some` value.
+    pub fn is_some(&self) -> bool {
+        matches!(self, COption::Some(_))
+    }
+
+    /// Returns `true` if the option is a `None` value.
+    pub fn is_none(&self) -> bool {
+        matches!(self, COption::None)
+    }
+
+    /// Converts from `&COption<T>` to `Option<&T>`.
+    pub fn as_option(&self) -> Option<&T> {
+        match self {
+            COption::Some(value) => Some(value),
+            COption::None => None,
+        }
+    }
+}
+
+impl<T> From<Option<T>> for COption<T> {
+    fn from(option: Option<T>) -> Self {
+        match option {
+            Some(value) => COption::Some(value),
+            None => COption::None,
+        }
+    }
+}
+
+impl<T> From<COption<T>> for Option<T> {
+    fn from(option: COption<T>) -> Self {
+        match option {
+            COption::Some(value) => Some(value),
+            COption::None => None,
+        }
+    }
+}
@@ -0,0 +1,41 @@
+//! A C-compatible result type, for operations that may fail across FFI.
+
+/// A C-compatible [`Result`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CResult<T, E> {
+    /// The operation succeeded with a value of type `T`.
+    Ok(T),
+    /// The operation failed with an error of type `E`.
+    Err(E),
+}
+
+impl<T, E> CResult<T, E> {
+    /// Returns `true` if the result is an `Ok` value.
+    pub fn is_ok(&self) -> bool {
+        matches!(self, CResult::Ok(_))
+    }
+
+    /// Returns `true` if the result is an `Err` value.
+    pub fn is_err(&self) -> bool {
+        matches!(self, CResult::Err(_))
+    }
+}
+
+impl<T, E> From<Result<T, E>> for CResult<T, E> {
+    fn from(result: Result<T, E>) -> Self {
+        match result {
+            Ok(value) => CResult::Ok(value),
+            Err(error) => CResult::Err(error),
+        }
+    }
+}
+
+impl<T, E> From<CResult<T, E>> for Result<T, E> {
+    fn from(result: CResult<T, E>) -> Self {
+        match result {
+            CResult::Ok(value) => Ok(value),
+            CResult::Err(error) => Err(error),
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Serde support for the types of this crate, with the `serde` feature.
+//!
+//! Objects are serialized like the Rust types they stand for, so a `CVec` object is a sequence
+//! and a `CMap` object a map. Deserializing an object boxes the default implementation, a `Vec`
+//! or a `BTreeMap`.
+#[cfg(feature = "alloc")]
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+use crusty_traits_core::*;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[cfg(feature = "alloc")]
+use crate::{cbox::CBox, cmap::CMapVTable, cstring::CRString};
+use crate::{
+    coption::COption,
+    cslice::{CSliceMut, CSliceRef, CSliceVTable},
+    cstring::CStrRef,
+    cvec::CVecVTable,
+};
+
+impl<T: Serialize> CSerialize for CSliceVTable<T> {
+    fn serialize<S: Serializer>(repr: CRef<'_, Self>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(Self::as_slice(repr))
+    }
+}
+
+impl<T: Serialize> CSerialize for CVecVTable<T> {
+    fn serialize<S: Serializer>(repr: CRef<'_, Self>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(Self::as_slice(repr))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'de, T: Deserialize<'de> + 'static> CDeserialize<'de> for CVecVTable<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<CRepr<Self>, D::Error> {
+        Vec::deserialize(deserializer).map(Self::new_boxed)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<K: Serialize, V: Serialize> CSerialize for CMapVTable<K, V> {
+    fn serialize<S: Serializer>(repr: CRef<'_, Self>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(crate::cmap::borrowed_entries(&repr))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'de, K, V> CDeserialize<'de> for CMapVTable<K, V>
+where
+    K: Deserialize<'de> + Ord + 'static,
+    V: Deserialize<'de> + 'static,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<CRepr<Self>, D::Error> {
+        BTreeMap::deserialize(deserializer).map(Self::new_boxed)
+    }
+}
+
+impl<T: Serialize> Serialize for CSliceRef<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.as_slice())
+    }
+}
+
+impl<T: Serialize> Serialize for CSliceMut<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for CSliceRef<'a, u8> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <&[u8]>::deserialize(deserializer).map(Self::from)
+    }
+}
+
+impl Serialize for CStrRef<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self)
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for CStrRef<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <&str>::deserialize(deserializer).map(Self::from)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Serialize for CRString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'de> Deserialize<'de> for CRString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::from)
+    }
+}
+
+impl<T: Serialize> Serialize for COption<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            COption::Some(value) => serializer.serialize_some(value),
+            COption::None => serializer.serialize_none(),
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for COption<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Option::deserialize(deserializer).map(Self::from)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Serialize> Serialize for CBox<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        T::serialize(self, serializer)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for CBox<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Self::new)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_serde_cvec() {
+        let vec = CVecVTable::new_boxed(vec![1, 2, 3]);
+        let json = serde_json::to_string(&vec).unwrap();
+        assert_eq!(json, "[1,2,3]");
+        let vec: CRepr<CVecVTable<i32>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(vec.as_ref(), [1, 2, 3]);
+        let slice = CSliceVTable::new_boxed(vec![4, 5]);
+        assert_eq!(serde_json::to_string(&slice.as_cref()).unwrap(), "[4,5]");
+    }
+
+    #[test]
+    fn test_serde_cmap() {
+        let map = CMapVTable::new_boxed(HashMap::from([("key".to_string(), 1)]));
+        let json = serde_json::to_string(&map).unwrap();
+        assert_eq!(json, r#"{"key":1}"#);
+        let other: CRepr<CMapVTable<String, i32>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(map, other);
+    }
+
+    #[test]
+    fn test_serde_values() {
+        let json = r#"["text",null,5]"#;
+        let (s, option, boxed): (CRString, COption<u8>, CBox<u8>) =
+            serde_json::from_str(json).unwrap();
+        assert_eq!((s.as_str(), option, *boxed), ("text", COption::None, 5));
+        assert_eq!(serde_json::to_string(&(s, option, boxed)).unwrap(), json);
+        let s: CStrRef<'_> = serde_json::from_str(r#""borrowed""#).unwrap();
+        assert_eq!(s, "borrowed");
+        assert_eq!(serde_json::to_string(&s).unwrap(), r#""borrowed""#);
+    }
+}
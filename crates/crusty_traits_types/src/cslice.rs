@@ -1,11 +1,19 @@
 //! C-compatible slice types and traits. that converts to the rust slice type.
 //!
 
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::{
+    fmt,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
 use crusty_traits_core::*;
 use crusty_traits_macros::crusty_trait;
 
 #[crusty_trait]
 /// A trait that represents a C-compatible slice.
+#[allow(clippy::len_without_is_empty)]
 pub trait CSlice<V> {
     /// Returns a pointer to the first element of the slice.
     fn as_ptr(&self) -> *const V;
@@ -18,16 +26,9 @@ pub trait CSliceExt<V>: CSlice<V> {
     /// Returns the slice as a Rust slice.
     fn as_slice(&self) -> &[V] {
         #[allow(unsafe_code)]
+        // SAFETY: Implementers guarantee `as_ptr` points to `len` initialised elements.
         unsafe {
-            std::slice::from_raw_parts(self.as_ptr(), self.len())
-        }
-    }
-
-    /// Returns the slice as a mutable Rust slice.
-    fn as_mut_slice(&mut self) -> &mut [V] {
-        #[allow(unsafe_code)]
-        unsafe {
-            std::slice::from_raw_parts_mut(self.as_ptr() as *mut V, self.len())
+            elements(self.as_ptr(), self.len())
         }
     }
 }
@@ -44,6 +45,7 @@ impl<V> CSlice<V> for [V] {
     }
 }
 
+#[cfg(feature = "alloc")]
 impl<V> CSlice<V> for Vec<V> {
     fn as_ptr(&self) -> *const V {
         self.as_ptr()
@@ -53,3 +55,332 @@ impl<V> CSlice<V> for Vec<V> {
         self.len()
     }
 }
+
+/// A borrowed, read-only view of a slice, passed across FFI as a pointer and a length.
+///
+/// Unlike a `CSlice` object it needs no vtable. The pointer may be null when the view is empty.
+#[repr(C)]
+pub struct CSliceRef<'a, T> {
+    ptr: *const T,
+    len: usize,
+    phantom: PhantomData<&'a [T]>,
+}
+
+#[allow(unsafe_code)]
+// SAFETY: A `CSliceRef` is a shared borrow of its elements, like `&[T]`.
+unsafe impl<T: Sync> Send for CSliceRef<'_, T> {}
+#[allow(unsafe_code)]
+// SAFETY: A `CSliceRef` is a shared borrow of its elements, like `&[T]`.
+unsafe impl<T: Sync> Sync for CSliceRef<'_, T> {}
+
+impl<'a, T> CSliceRef<'a, T> {
+    /// Creates a view of the `len` elements at `ptr`, which may be null if `len` is zero.
+    /// # Safety
+    /// `ptr` must point to `len` initialised elements that are not mutated for `'a`.
+    #[allow(unsafe_code)]
+    pub const unsafe fn from_raw_parts(ptr: *const T, len: usize) -> Self {
+        Self {
+            ptr,
+            len,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the elements of the view for `'a`.
+    pub fn as_slice(&self) -> &'a [T] {
+        #[allow(unsafe_code)]
+        // SAFETY: The elements are valid for `'a`, see `CSliceRef::from_raw_parts`.
+        unsafe {
+            elements(self.ptr, self.len)
+        }
+    }
+}
+
+impl<T> Clone for CSliceRef<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for CSliceRef<'_, T> {}
+
+impl<T> Default for CSliceRef<'_, T> {
+    fn default() -> Self {
+        <&[T]>::default().into()
+    }
+}
+
+impl<'a, T> From<&'a [T]> for CSliceRef<'a, T> {
+    fn from(slice: &'a [T]) -> Self {
+        #[allow(unsafe_code)]
+        // SAFETY: The elements are borrowed for `'a`.
+        unsafe {
+            Self::from_raw_parts(slice.as_ptr(), slice.len())
+        }
+    }
+}
+
+impl<'a, T> From<CSliceRef<'a, T>> for &'a [T] {
+    fn from(view: CSliceRef<'a, T>) -> Self {
+        view.as_slice()
+    }
+}
+
+impl<'a, V: CAsSlice> From<CRef<'a, V>> for CSliceRef<'a, V::Item> {
+    fn from(repr: CRef<'a, V>) -> Self {
+        V::as_slice(repr).into()
+    }
+}
+
+/// A borrowed, mutable view of a slice, passed across FFI as a pointer and a length.
+///
+/// Unlike a `CSlice` object it needs no vtable. The pointer may be null when the view is empty.
+#[repr(C)]
+pub struct CSliceMut<'a, T> {
+    ptr: *mut T,
+    len: usize,
+    phantom: PhantomData<&'a mut [T]>,
+}
+
+#[allow(unsafe_code)]
+// SAFETY: A `CSliceMut` is a unique borrow of its elements, like `&mut [T]`.
+unsafe impl<T: Send> Send for CSliceMut<'_, T> {}
+#[allow(unsafe_code)]
+// SAFETY: A `CSliceMut` is a unique borrow of its elements, like `&mut [T]`.
+unsafe impl<T: Sync> Sync for CSliceMut<'_, T> {}
+
+impl<'a, T> CSliceMut<'a, T> {
+    /// Creates a mutable view of the `len` elements at `ptr`, which may be null if `len` is zero.
+    /// # Safety
+    /// `ptr` must point to `len` initialised elements that are not accessed otherwise for `'a`.
+    #[allow(unsafe_code)]
+    pub const unsafe fn from_raw_parts(ptr: *mut T, len: usize) -> Self {
+        Self {
+            ptr,
+            len,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the elements of the view for `'a`.
+    pub fn into_slice(self) -> &'a mut [T] {
+        #[allow(unsafe_code)]
+        // SAFETY: The elements are borrowed uniquely for `'a`, see `CSliceMut::from_raw_parts`.
+        unsafe {
+            elements_mut(self.ptr, self.len)
+        }
+    }
+
+    /// Reborrows the view as a shared `CSliceRef`.
+    pub fn as_slice_ref(&self) -> CSliceRef<'_, T> {
+        self.deref().into()
+    }
+}
+
+impl<T> Default for CSliceMut<'_, T> {
+    fn default() -> Self {
+        <&mut [T]>::default().into()
+    }
+}
+
+impl<'a, T> From<&'a mut [T]> for CSliceMut<'a, T> {
+    fn from(slice: &'a mut [T]) -> Self {
+        #[allow(unsafe_code)]
+        // SAFETY: The elements are borrowed uniquely for `'a`.
+        unsafe {
+            Self::from_raw_parts(slice.as_mut_ptr(), slice.len())
+        }
+    }
+}
+
+impl<'a, T> From<CSliceMut<'a, T>> for &'a mut [T] {
+    fn from(view: CSliceMut<'a, T>) -> Self {
+        view.into_slice()
+    }
+}
+
+impl<'a, V: CAsMutSlice> From<CRefMut<'a, V>> for CSliceMut<'a, V::Item> {
+    fn from(repr: CRefMut<'a, V>) -> Self {
+        V::as_mut_slice(repr).into()
+    }
+}
+
+/// Implements the slice traits shared by the views.
+macro_rules! impl_view {
+    ($view:ident) => {
+        impl<T> Deref for $view<'_, T> {
+            type Target = [T];
+
+            fn deref(&self) -> &[T] {
+                #[allow(unsafe_code)]
+                // SAFETY: The view borrows `len` initialised elements.
+                unsafe {
+                    elements(self.ptr, self.len)
+                }
+            }
+        }
+
+        impl<T> CSlice<T> for $view<'_, T> {
+            fn as_ptr(&self) -> *const T {
+                self.ptr
+            }
+
+            fn len(&self) -> usize {
+                self.len
+            }
+        }
+
+        impl<T: fmt::Debug> fmt::Debug for $view<'_, T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.deref().fmt(f)
+            }
+        }
+
+        impl<T: PartialEq> PartialEq for $view<'_, T> {
+            fn eq(&self, other: &Self) -> bool {
+                self.deref() == other.deref()
+            }
+        }
+
+        impl<T: Eq> Eq for $view<'_, T> {}
+    };
+}
+
+impl_view!(CSliceRef);
+impl_view!(CSliceMut);
+
+impl<T> DerefMut for CSliceMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        #[allow(unsafe_code)]
+        // SAFETY: The view uniquely borrows `len` initialised elements.
+        unsafe {
+            elements_mut(self.ptr, self.len)
+        }
+    }
+}
+
+/// Returns the `len` elements at `ptr`, which may be null if there are none.
+/// # Safety
+/// `ptr` must point to `len` initialised elements that are not mutated for `'a`.
+#[allow(unsafe_code)]
+pub(crate) unsafe fn elements<'a, V>(ptr: *const V, len: usize) -> &'a [V] {
+    if len == 0 {
+        return &[];
+    }
+    // SAFETY: The caller guarantees `ptr` points to `len` initialised elements.
+    unsafe { core::slice::from_raw_parts(ptr, len) }
+}
+
+/// Returns the `len` elements at `ptr` mutably, which may be null if there are none.
+/// # Safety
+/// `ptr` must point to `len` initialised elements that are not accessed otherwise for `'a`.
+#[allow(unsafe_code)]
+pub(crate) unsafe fn elements_mut<'a, V>(ptr: *const V, len: usize) -> &'a mut [V] {
+    if len == 0 {
+        return &mut [];
+    }
+    // SAFETY: The caller guarantees `ptr` points to `len` initialised elements.
+    unsafe { core::slice::from_raw_parts_mut(ptr.cast_mut(), len) }
+}
+
+/// Implements the core traits for the vtable of a slice trait, whose objects are compared,
+/// formatted and cloned by their elements regardless of their implementation.
+macro_rules! impl_slice_vtable {
+    ($vtable:ident) => {
+        impl<T> CAsSlice for $vtable<T> {
+            type Item = T;
+
+            fn as_slice(repr: CRef<'_, Self>) -> &[T] {
+                #[allow(unsafe_code)]
+                // SAFETY: Implementers guarantee `as_ptr` points to `len` initialised elements,
+                // which are borrowed with the object.
+                unsafe {
+                    $crate::cslice::elements(CSlice::as_ptr(&repr), CSlice::len(&repr))
+                }
+            }
+        }
+
+        impl<T: core::fmt::Debug> CDebug for $vtable<T> {
+            fn fmt(repr: CRef<Self>, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                f.debug_list().entries(Self::as_slice(repr)).finish()
+            }
+        }
+
+        impl<T: PartialEq> CPartialEq for $vtable<T> {
+            // every object is compared by its elements, so they all share one implementation
+            fn implementation(_repr: CRef<Self>) -> CImplementationId {
+                CImplementationId::default()
+            }
+
+            #[allow(unsafe_code)]
+            unsafe fn eq(a: CRef<Self>, b: CRef<Self>) -> bool {
+                Self::eq_objects(a, b)
+            }
+
+            fn eq_objects(a: CRef<Self>, b: CRef<Self>) -> bool {
+                Self::as_slice(a) == Self::as_slice(b)
+            }
+        }
+
+        impl<T: Eq> CEq for $vtable<T> {}
+
+        #[cfg(feature = "alloc")]
+        #[allow(unsafe_code)]
+        // SAFETY: The clone owns its elements and borrows nothing.
+        unsafe impl<T: Clone + 'static> CClone for $vtable<T> {
+            fn clone<'a>(repr: CRef<'a, Self>) -> CReprScoped<'a, Self> {
+                Self::from_vec(Self::as_slice(repr).to_vec()).into()
+            }
+        }
+
+        #[cfg(feature = "alloc")]
+        impl<T: 'static> CFromVec for $vtable<T> {
+            fn from_vec(vec: Vec<T>) -> CRepr<Self> {
+                Self::new_boxed(vec)
+            }
+        }
+    };
+}
+
+pub(crate) use impl_slice_vtable;
+
+impl_slice_vtable!(CSliceVTable);
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    #![allow(unsafe_code)]
+    use core::ptr;
+
+    use super::*;
+    use crate::cvec::CVecVTable;
+
+    #[test]
+    fn test_slice_ref() {
+        let values = [1, 2, 3];
+        let view = CSliceRef::from(&values[..]);
+        assert_eq!(&*view, [1, 2, 3]);
+        assert_eq!(view.iter().sum::<i32>(), 6);
+        assert_eq!(view, CSliceRef::from(&[1, 2, 3][..]));
+        let slice = CSliceVTable::new_boxed(values.to_vec());
+        assert_eq!(CSliceRef::from(slice.as_cref()).as_slice(), [1, 2, 3]);
+        // SAFETY: An empty view may be null.
+        let empty = unsafe { CSliceRef::<i32>::from_raw_parts(ptr::null(), 0) };
+        assert_eq!(empty, CSliceRef::default());
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_slice_mut() {
+        let mut values = [3, 1, 2];
+        let mut view = CSliceMut::from(&mut values[..]);
+        view.sort();
+        assert_eq!(view.as_slice_ref().as_slice(), [1, 2, 3]);
+        view.into_slice()[0] = 0;
+        assert_eq!(values, [0, 2, 3]);
+        let mut vec = CVecVTable::new_boxed(values.to_vec());
+        CSliceMut::from(vec.as_cref_mut())[1] = 5;
+        assert_eq!(vec.as_ref(), [0, 5, 3]);
+        let view = CSliceVTable::new_scoped(CSliceMut::from(&mut values[..]));
+        assert_eq!(view.len(), 3);
+    }
+}
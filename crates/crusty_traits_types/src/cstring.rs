@@ -0,0 +1,477 @@
+//! C-compatible string types for passing UTF-8 text across FFI.
+//!
+//! [`CStrRef`] borrows a string, while [`CRString`] owns one and frees it with the drop function
+//! of the side that allocated it.
+#[cfg(feature = "alloc")]
+use alloc::{borrow::ToOwned, string::String, vec::Vec};
+use core::{
+    ffi::{CStr, c_char},
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    mem::ManuallyDrop,
+    ops::Deref,
+    str::Utf8Error,
+};
+
+use crate::cslice::{CSlice, CSliceRef, elements};
+
+/// A borrowed string, passed across FFI as a pointer to UTF-8 bytes and a length.
+///
+/// The bytes are not NUL-terminated, and the pointer may be null when the string is empty.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct CStrRef<'a> {
+    ptr: *const u8,
+    len: usize,
+    phantom: PhantomData<&'a str>,
+}
+
+#[allow(unsafe_code)]
+// SAFETY: A `CStrRef` is a shared borrow of its bytes, like `&str`.
+unsafe impl Send for CStrRef<'_> {}
+#[allow(unsafe_code)]
+// SAFETY: A `CStrRef` is a shared borrow of its bytes, like `&str`.
+unsafe impl Sync for CStrRef<'_> {}
+
+impl<'a> CStrRef<'a> {
+    /// Creates a view of the `len` bytes at `ptr`, which may be null if `len` is zero.
+    /// # Safety
+    /// `ptr` must point to `len` bytes of valid UTF-8 that are not mutated for `'a`.
+    #[allow(unsafe_code)]
+    pub const unsafe fn from_raw_parts(ptr: *const u8, len: usize) -> Self {
+        Self {
+            ptr,
+            len,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Checks that `bytes` are valid UTF-8 and returns them as a string.
+    pub fn from_utf8(bytes: CSliceRef<'a, u8>) -> Result<Self, Utf8Error> {
+        Ok(core::str::from_utf8(bytes.as_slice())?.into())
+    }
+
+    /// Returns the string for `'a`.
+    pub fn as_str(&self) -> &'a str {
+        #[allow(unsafe_code)]
+        // SAFETY: The bytes are valid UTF-8 for `'a`, see `CStrRef::from_raw_parts`.
+        unsafe {
+            core::str::from_utf8_unchecked(elements(self.ptr, self.len))
+        }
+    }
+
+    /// Returns the bytes of the string.
+    pub fn as_bytes(&self) -> CSliceRef<'a, u8> {
+        self.as_str().as_bytes().into()
+    }
+}
+
+impl Default for CStrRef<'_> {
+    fn default() -> Self {
+        "".into()
+    }
+}
+
+impl<'a> From<&'a str> for CStrRef<'a> {
+    fn from(s: &'a str) -> Self {
+        #[allow(unsafe_code)]
+        // SAFETY: The bytes of a `str` are valid UTF-8 and borrowed for `'a`.
+        unsafe {
+            Self::from_raw_parts(s.as_ptr(), s.len())
+        }
+    }
+}
+
+impl<'a> From<CStrRef<'a>> for &'a str {
+    fn from(s: CStrRef<'a>) -> Self {
+        s.as_str()
+    }
+}
+
+impl Deref for CStrRef<'_> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl CSlice<u8> for CStrRef<'_> {
+    fn as_ptr(&self) -> *const u8 {
+        self.ptr
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl fmt::Debug for CStrRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl fmt::Display for CStrRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl PartialEq for CStrRef<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for CStrRef<'_> {}
+
+impl PartialEq<str> for CStrRef<'_> {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for CStrRef<'_> {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl Hash for CStrRef<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+/// Frees the buffer of a [`CRString`], given its pointer, length and capacity.
+pub type CRStringDrop = unsafe extern "C" fn(ptr: *mut u8, len: usize, capacity: usize);
+
+/// An owned string, passed across FFI as a pointer to UTF-8 bytes, a length, a capacity and the
+/// function freeing the buffer.
+///
+/// The bytes are always followed by a NUL, so [`CRString::as_c_ptr`] can be handed to C. The
+/// capacity is that of the whole buffer, including the NUL.
+#[repr(C)]
+pub struct CRString {
+    ptr: *mut u8,
+    len: usize,
+    capacity: usize,
+    drop: CRStringDrop,
+}
+
+#[allow(unsafe_code)]
+// SAFETY: A `CRString` owns its bytes, like `String`, and its drop function may be called on
+// any thread, see `CRString::from_raw_parts`.
+unsafe impl Send for CRString {}
+#[allow(unsafe_code)]
+// SAFETY: A `CRString` only gives shared access to its bytes through `&self`.
+unsafe impl Sync for CRString {}
+
+/// The drop function of strings that don't own their buffer.
+#[allow(unsafe_code)]
+unsafe extern "C" fn drop_static(_ptr: *mut u8, _len: usize, _capacity: usize) {}
+
+/// The drop function of strings allocated by a Rust `String`.
+#[cfg(feature = "alloc")]
+#[allow(unsafe_code)]
+unsafe extern "C" fn drop_string(ptr: *mut u8, _len: usize, capacity: usize) {
+    // SAFETY: The buffer was allocated by a `Vec<u8>` with this capacity, see `From<String>`.
+    drop(unsafe { Vec::from_raw_parts(ptr, 0, capacity) });
+}
+
+impl CRString {
+    /// Returns an empty string, which doesn't allocate.
+    pub const fn new() -> Self {
+        Self {
+            ptr: c"".as_ptr() as *mut u8,
+            len: 0,
+            capacity: 0,
+            drop: drop_static,
+        }
+    }
+
+    /// Takes ownership of a NUL-terminated buffer, checking that its `len` bytes are valid UTF-8.
+    ///
+    /// If they aren't, the buffer is freed and the error is returned.
+    /// # Safety
+    /// `ptr` must point to `len` initialised bytes followed by a NUL, in a buffer of `capacity`
+    /// bytes that `drop` frees, from any thread, when given the same arguments.
+    #[allow(unsafe_code)]
+    pub unsafe fn from_raw_parts(
+        ptr: *mut u8,
+        len: usize,
+        capacity: usize,
+        drop: CRStringDrop,
+    ) -> Result<Self, Utf8Error> {
+        // SAFETY: Upheld by the caller, apart from the UTF-8 checked below.
+        let string = unsafe { Self::from_raw_parts_unchecked(ptr, len, capacity, drop) };
+        // SAFETY: The bytes are initialised.
+        core::str::from_utf8(unsafe { elements(ptr, len) })?;
+        Ok(string)
+    }
+
+    /// Takes ownership of a NUL-terminated buffer of UTF-8 bytes without checking them.
+    /// # Safety
+    /// As [`CRString::from_raw_parts`], and the `len` bytes must be valid UTF-8.
+    #[allow(unsafe_code)]
+    pub const unsafe fn from_raw_parts_unchecked(
+        ptr: *mut u8,
+        len: usize,
+        capacity: usize,
+        drop: CRStringDrop,
+    ) -> Self {
+        Self {
+            ptr,
+            len,
+            capacity,
+            drop,
+        }
+    }
+
+    /// Returns the pointer, length, capacity and drop function of the string, without freeing it.
+    pub fn into_raw_parts(self) -> (*mut u8, usize, usize, CRStringDrop) {
+        let string = ManuallyDrop::new(self);
+        (string.ptr, string.len, string.capacity, string.drop)
+    }
+
+    /// Returns the string.
+    pub fn as_str(&self) -> &str {
+        #[allow(unsafe_code)]
+        // SAFETY: The bytes are valid UTF-8, see `CRString::from_raw_parts`.
+        unsafe {
+            core::str::from_utf8_unchecked(elements(self.ptr, self.len))
+        }
+    }
+
+    /// Borrows the string as a [`CStrRef`].
+    pub fn as_str_ref(&self) -> CStrRef<'_> {
+        self.as_str().into()
+    }
+
+    /// Returns a pointer to the NUL-terminated bytes, for C.
+    ///
+    /// C will see the string end at its first NUL, if it contains any.
+    pub fn as_c_ptr(&self) -> *const c_char {
+        self.ptr as *const c_char
+    }
+
+    /// Returns the string as a [`CStr`], if it doesn't contain any NUL.
+    pub fn as_c_str(&self) -> Option<&CStr> {
+        #[allow(unsafe_code)]
+        // SAFETY: The `len` bytes are followed by a NUL, see `CRString::from_raw_parts`.
+        let bytes = unsafe { elements(self.ptr, self.len + 1) };
+        CStr::from_bytes_with_nul(bytes).ok()
+    }
+
+    /// Returns the capacity of the buffer, including the NUL.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl Drop for CRString {
+    fn drop(&mut self) {
+        #[allow(unsafe_code)]
+        // SAFETY: The string owns its buffer, which `drop` frees.
+        unsafe {
+            (self.drop)(self.ptr, self.len, self.capacity);
+        }
+    }
+}
+
+impl Default for CRString {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<String> for CRString {
+    fn from(s: String) -> Self {
+        let len = s.len();
+        let mut bytes = s.into_bytes();
+        bytes.push(0);
+        let mut bytes = ManuallyDrop::new(bytes);
+        #[allow(unsafe_code)]
+        // SAFETY: The bytes of a `String` are valid UTF-8 and are now followed by a NUL, in a
+        // buffer that `drop_string` frees.
+        unsafe {
+            Self::from_raw_parts_unchecked(bytes.as_mut_ptr(), len, bytes.capacity(), drop_string)
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<&str> for CRString {
+    fn from(s: &str) -> Self {
+        s.to_owned().into()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<CStrRef<'_>> for CRString {
+    fn from(s: CStrRef<'_>) -> Self {
+        s.as_str().into()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl TryFrom<&CStr> for CRString {
+    type Error = Utf8Error;
+
+    fn try_from(s: &CStr) -> Result<Self, Utf8Error> {
+        Ok(s.to_str()?.into())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<CRString> for String {
+    fn from(s: CRString) -> Self {
+        s.as_str().to_owned()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Clone for CRString {
+    fn clone(&self) -> Self {
+        self.as_str().into()
+    }
+}
+
+impl Deref for CRString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for CRString {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl CSlice<u8> for CRString {
+    fn as_ptr(&self) -> *const u8 {
+        self.ptr
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl fmt::Debug for CRString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl fmt::Display for CRString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl PartialEq for CRString {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for CRString {}
+
+impl PartialEq<str> for CRString {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for CRString {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl Hash for CRString {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    #![allow(unsafe_code)]
+    use core::{
+        ptr,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_cstr_ref() {
+        let s = CStrRef::from("crusty");
+        assert_eq!(s, "crusty");
+        assert_eq!(s.len(), 6);
+        assert_eq!(s.to_uppercase(), "CRUSTY");
+        assert_eq!(s.as_bytes().as_slice(), b"crusty");
+        // SAFETY: An empty string may be null.
+        let empty = unsafe { CStrRef::from_raw_parts(ptr::null(), 0) };
+        assert_eq!(empty, CStrRef::default());
+        assert_eq!(empty.as_str(), "");
+    }
+
+    #[test]
+    fn test_cstr_ref_from_utf8() {
+        let bytes = [0xe2, 0x9c, 0x93];
+        let s = CStrRef::from_utf8(CSliceRef::from(&bytes[..])).unwrap();
+        assert_eq!(s, "\u{2713}");
+        assert!(CStrRef::from_utf8(CSliceRef::from(&bytes[..2])).is_err());
+    }
+
+    #[test]
+    fn test_crstring() {
+        let s = CRString::from("crusty");
+        assert_eq!(s, "crusty");
+        assert_eq!(s.as_c_str(), Some(c"crusty"));
+        // SAFETY: The string is NUL-terminated.
+        assert_eq!(unsafe { CStr::from_ptr(s.as_c_ptr()) }, c"crusty");
+        assert_eq!(s.clone(), s);
+        assert_eq!(String::from(s), "crusty");
+        let empty = CRString::new();
+        assert_eq!(empty.as_c_str(), Some(c""));
+        assert_eq!(empty, CRString::from(String::new()));
+        assert_eq!(CRString::from("a\0b").as_c_str(), None);
+    }
+
+    #[test]
+    fn test_crstring_from_c_str() {
+        let s = CRString::try_from(c"\xe2\x9c\x93").unwrap();
+        assert_eq!(s.to_string(), "\u{2713}");
+        assert!(CRString::try_from(c"\xe2\x9c").is_err());
+    }
+
+    static FREED: AtomicUsize = AtomicUsize::new(0);
+
+    unsafe extern "C" fn counted_drop(_ptr: *mut u8, len: usize, _capacity: usize) {
+        FREED.fetch_add(len, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn test_crstring_from_raw_parts() {
+        let mut valid = *b"ok\0";
+        // SAFETY: The buffer is NUL-terminated and outlives the string.
+        let s = unsafe { CRString::from_raw_parts(valid.as_mut_ptr(), 2, 3, counted_drop) };
+        assert_eq!(s.unwrap(), "ok");
+        assert_eq!(FREED.load(Ordering::SeqCst), 2);
+        let mut invalid = *b"\xff\xfe\xfd\0";
+        // SAFETY: As above.
+        let s = unsafe { CRString::from_raw_parts(invalid.as_mut_ptr(), 3, 4, counted_drop) };
+        assert!(s.is_err());
+        assert_eq!(FREED.load(Ordering::SeqCst), 5);
+    }
+}
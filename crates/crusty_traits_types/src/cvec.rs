@@ -1,36 +1,188 @@
 //! C-compatible vector types and traits. that converts to the rust Vec type.
 //!
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "alloc")]
+use core::ptr;
 use crusty_traits_core::*;
 use crusty_traits_macros::crusty_trait;
 
-use crate::cslice::{CSlice, CSliceVTable};
+use crate::{
+    coption::COption,
+    cresult::CResult,
+    cslice::{CSlice, CSliceVTable, impl_slice_vtable},
+};
 
 #[crusty_trait]
 /// A trait that represents a C-compatible vector.
+///
+/// Methods taking an index hand back what they could not insert or remove instead of panicking,
+/// as a panic can't unwind out of the vtable.
 pub trait CVec<V>: CSlice<V> {
     /// Adds an element to the end of the vector.
     fn push(&mut self, value: V);
-    /// Extends the vector's capacity by the given amount.
-    fn extend(&mut self, amount: usize);
+    /// Reserves capacity for at least `additional` more elements.
+    fn reserve(&mut self, additional: usize);
     /// Returns the capacity of the vector.
     fn capacity(&self) -> usize;
+    /// Removes the last element of the vector and returns it, or `None` if it is empty.
+    fn pop(&mut self) -> COption<V>;
+    /// Inserts an element at `index`, shifting the elements after it to the right.
+    ///
+    /// Returns the element back as an error if `index` is greater than the length.
+    fn insert(&mut self, index: usize, value: V) -> CResult<(), V>;
+    /// Removes the element at `index` and returns it, shifting the elements after it to the left.
+    ///
+    /// Returns `None` if `index` is out of bounds.
+    fn remove(&mut self, index: usize) -> COption<V>;
+    /// Shortens the vector to `len` elements, dropping the rest.
+    fn truncate(&mut self, len: usize);
+    /// Removes all elements of the vector.
+    fn clear(&mut self);
+    /// Sets the length of the vector without dropping or initialising elements.
+    /// # Safety
+    /// `len` must not be greater than the capacity and the elements up to `len` must be
+    /// initialised.
+    #[allow(unsafe_code)]
+    unsafe fn set_len(&mut self, len: usize);
+    /// Reserves capacity for exactly `additional` more elements.
+    fn reserve_exact(&mut self, additional: usize);
+    /// Shrinks the capacity of the vector as much as possible.
+    fn shrink_to_fit(&mut self);
+    /// Moves `len` elements from `ptr` to the end of the vector.
+    /// # Safety
+    /// `ptr` must be valid for reading `len` elements, which are moved out and must not be used
+    /// or dropped by the caller afterwards.
+    #[allow(unsafe_code)]
+    unsafe fn extend_from_raw(&mut self, ptr: *const V, len: usize);
+    /// Moves the elements in `start..end` to the end of `other`.
+    ///
+    /// Returns `false` and moves nothing if the range is out of bounds.
+    fn drain_into(&mut self, start: usize, end: usize, other: CRefMut<'_, CVecVTable<V>>) -> bool;
+    /// Moves all elements of `other` to the end of the vector, leaving `other` empty.
+    fn append(&mut self, other: CRefMut<'_, CVecVTable<V>>);
 }
 
+#[cfg(feature = "alloc")]
 impl<T> CVec<T> for Vec<T> {
     fn push(&mut self, value: T) {
         self.push(value);
     }
 
-    fn extend(&mut self, amount: usize) {
-        self.reserve(amount);
+    fn reserve(&mut self, additional: usize) {
+        self.reserve(additional);
     }
 
     fn capacity(&self) -> usize {
         self.capacity()
     }
+
+    fn pop(&mut self) -> COption<T> {
+        self.pop().into()
+    }
+
+    fn insert(&mut self, index: usize, value: T) -> CResult<(), T> {
+        if index > self.len() {
+            return CResult::Err(value);
+        }
+        self.insert(index, value);
+        CResult::Ok(())
+    }
+
+    fn remove(&mut self, index: usize) -> COption<T> {
+        (index < self.len()).then(|| self.remove(index)).into()
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.truncate(len);
+    }
+
+    fn clear(&mut self) {
+        self.clear();
+    }
+
+    #[allow(unsafe_code)]
+    unsafe fn set_len(&mut self, len: usize) {
+        // SAFETY: The caller upholds the contract of `Vec::set_len`.
+        unsafe { self.set_len(len) }
+    }
+
+    fn reserve_exact(&mut self, additional: usize) {
+        self.reserve_exact(additional);
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.shrink_to_fit();
+    }
+
+    #[allow(unsafe_code)]
+    unsafe fn extend_from_raw(&mut self, ptr: *const T, len: usize) {
+        self.reserve(len);
+        // SAFETY: `ptr` is valid for `len` reads per the caller, the capacity was reserved above
+        // and the moved elements are initialised.
+        unsafe {
+            ptr::copy_nonoverlapping(ptr, self.as_mut_ptr().add(self.len()), len);
+            self.set_len(self.len() + len);
+        }
+    }
+
+    fn drain_into(
+        &mut self,
+        start: usize,
+        end: usize,
+        mut other: CRefMut<'_, CVecVTable<T>>,
+    ) -> bool {
+        if start > end || end > self.len() {
+            return false;
+        }
+        let len = self.len();
+        #[allow(unsafe_code)]
+        // SAFETY: The range is in bounds, its elements are moved into `other` before the tail is
+        // shifted over them and the length is shortened.
+        unsafe {
+            let base = self.as_mut_ptr();
+            other.extend_from_raw(base.add(start), end - start);
+            ptr::copy(base.add(end), base.add(start), len - end);
+            self.set_len(len - (end - start));
+        }
+        true
+    }
+
+    fn append(&mut self, mut other: CRefMut<'_, CVecVTable<T>>) {
+        let len = other.len();
+        #[allow(unsafe_code)]
+        // SAFETY: The elements of `other` are moved out before its length is set to zero.
+        unsafe {
+            CVec::extend_from_raw(self, CSlice::as_ptr(&other), len);
+            other.set_len(0);
+        }
+    }
 }
 
-#[cfg(test)]
+impl_slice_vtable!(CVecVTable);
+
+impl<T> CAsMutSlice for CVecVTable<T> {
+    fn as_mut_slice(repr: CRefMut<'_, Self>) -> &mut [T] {
+        #[allow(unsafe_code)]
+        // SAFETY: A vector owns its elements, see `as_slice`, and the `CRefMut` gives exclusive
+        // access to them.
+        unsafe {
+            crate::cslice::elements_mut(CSlice::as_ptr(&repr), CSlice::len(&repr))
+        }
+    }
+}
+
+impl<T> CExtend for CVecVTable<T> {
+    fn extend<I: IntoIterator<Item = T>>(mut repr: CRefMut<'_, Self>, iter: I) {
+        let iter = iter.into_iter();
+        CVec::reserve(&mut repr, iter.size_hint().0);
+        for value in iter {
+            CVec::push(&mut repr, value);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
 mod tests {
     #![allow(unsafe_code)]
     use super::*;
@@ -51,7 +203,7 @@ mod tests {
     }
 
     fn test_cvec_inner(mut cvec: impl CVec<TestData>) {
-        cvec.extend(10);
+        cvec.reserve(10);
         assert_eq!(cvec.len(), 0);
         assert!(cvec.capacity() >= 10);
 
@@ -63,9 +215,90 @@ mod tests {
         {
             let slice = cvec.as_slice();
 
-            assert!(slice.len() > 0);
+            assert!(!slice.is_empty());
             assert_eq!(slice[0].string, "Hello");
             assert_eq!(slice[0].number, 42);
         }
     }
+
+    fn strings(cvec: &CRepr<CVecVTable<String>>) -> &[String] {
+        cvec.as_slice()
+    }
+
+    #[test]
+    fn test_cvec_vec_api() {
+        let words = ["a", "b", "c", "d"].map(String::from);
+        let mut cvec = CVecVTable::new_boxed(Vec::from(words));
+        assert_eq!(cvec.pop(), COption::Some("d".to_string()));
+        assert_eq!(cvec.insert(1, "x".to_string()), CResult::Ok(()));
+        assert_eq!(
+            cvec.insert(9, "y".to_string()),
+            CResult::Err("y".to_string())
+        );
+        assert_eq!(strings(&cvec), ["a", "x", "b", "c"]);
+        assert_eq!(cvec.remove(0), COption::Some("a".to_string()));
+        assert_eq!(cvec.remove(3), COption::None);
+
+        let mut other = CVecVTable::new_boxed(vec!["z".to_string()]);
+        assert!(cvec.drain_into(0, 2, other.as_cref_mut()));
+        assert!(!cvec.drain_into(1, 5, other.as_cref_mut()));
+        assert_eq!(strings(&cvec), ["c"]);
+        assert_eq!(strings(&other), ["z", "x", "b"]);
+        cvec.append(other.as_cref_mut());
+        assert_eq!(strings(&cvec), ["c", "z", "x", "b"]);
+        assert_eq!(other.len(), 0);
+
+        let mut moved = core::mem::ManuallyDrop::new(["e".to_string(), "f".to_string()]);
+        // SAFETY: The strings are moved into the vector and not dropped here.
+        unsafe { cvec.extend_from_raw(moved.as_mut_ptr(), moved.len()) };
+        cvec.truncate(5);
+        assert_eq!(strings(&cvec), ["c", "z", "x", "b", "e"]);
+
+        cvec.reserve_exact(100);
+        assert!(cvec.capacity() >= 105);
+        cvec.shrink_to_fit();
+        assert!(cvec.capacity() < 105);
+        // SAFETY: The last element is moved out before the length excludes it.
+        let last = unsafe {
+            let last = ptr::read(CSlice::as_ptr(&cvec).add(4));
+            cvec.set_len(4);
+            last
+        };
+        assert_eq!(last, "e");
+        cvec.clear();
+        assert_eq!(cvec.len(), 0);
+    }
+
+    #[test]
+    fn test_cvec_reserve_slot() {
+        // `reserve` keeps the slot it had as `extend`, right after `push`
+        assert_eq!(
+            core::mem::offset_of!(CVecVTable<u8>, reserve),
+            core::mem::offset_of!(CVecVTable<u8>, push) + size_of::<usize>()
+        );
+    }
+
+    #[test]
+    fn test_cvec_std_traits() {
+        let mut cvec: CRepr<CVecVTable<u32>> = (1..4).collect();
+        cvec.extend([4, 5]);
+        cvec[0] = 10;
+        cvec.as_mut()[1..].iter_mut().for_each(|value| *value *= 2);
+        assert_eq!(cvec[..2], [10, 4]);
+        assert_eq!((&cvec).into_iter().sum::<u32>(), 38);
+        assert_eq!(format!("{cvec:?}"), "[10, 4, 6, 8, 10]");
+
+        let clone = cvec.clone();
+        assert_eq!(clone, cvec);
+        assert_eq!(clone.as_cref(), cvec.as_cref());
+        // vectors of different implementations are equal by their elements
+        let copy: Vec<u32> = cvec.as_ref().to_vec();
+        let borrowed = CVecVTable::new_scoped(copy);
+        assert_eq!(borrowed.as_cref(), cvec.as_cref());
+        assert_ne!(CRepr::<CVecVTable<u32>>::from(vec![1]), cvec);
+
+        let slice = CSliceVTable::from_vec(vec!["a", "b"]);
+        assert_eq!(slice.as_ref(), ["a", "b"]);
+        assert_eq!(slice.clone(), slice);
+    }
 }
@@ -5,11 +5,44 @@
 //!
 //! ## Available Types
 //!
+//! - [`cbox`] - A C-compatible owned pointer to a single heap value
+//! - [`cfuture`] - C-compatible futures and wakers for async trait methods, with the `alloc`
+//!   feature
+//! - [`citerator`] - C-compatible iterators for methods returning iterators
+//! - [`cmap`] - C-compatible maps for key-value data, with the `alloc` feature
+//! - [`coption`] - A C-compatible option type
+//! - [`cresult`] - A C-compatible result type
 //! - [`cslice`] - C-compatible slice types for passing array data across FFI
+//! - [`cstring`] - Borrowed and owned C-compatible strings for passing UTF-8 text across FFI
 //! - [`cvec`] - C-compatible vector types for dynamic arrays
+//! - [`raw`] - Vectors over memory owned by C
 //!
 //! These types are designed to work seamlessly with the `crusty_trait` macro system
 //! and provide safe, efficient data exchange between Rust and C code.
+//!
+//! ## Features
+//!
+//! - `std` (default) - Enables `alloc`.
+//! - `alloc` - Enables the implementations backed by `Vec` and the `CRepr` based APIs.
+//! - `serde` - Implements `Serialize` and `Deserialize` for the types, and `CSerialize` and
+//!   `CDeserialize` for the vtables so their wrappers can be serialized.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
 
+pub mod cbox;
+#[cfg(feature = "alloc")]
+pub mod cfuture;
+pub mod citerator;
+#[cfg(feature = "alloc")]
+pub mod cmap;
+pub mod coption;
+pub mod cresult;
+#[cfg(feature = "serde")]
+mod cserde;
 pub mod cslice;
+pub mod cstring;
 pub mod cvec;
+pub mod raw;
@@ -0,0 +1,415 @@
+//! Vectors over memory owned by C, such as a `malloc`ed array or a fixed buffer.
+//!
+//! A [`RawVec`] grows and frees its buffer with the callbacks of a [`RawAllocator`], so it can be
+//! boxed into a `CRepr<CVecVTable<T>>` and grown through the vtable by Rust code. Memory borrowed
+//! from the caller is viewed with a [`CSliceRef`](crate::cslice::CSliceRef) instead.
+use core::{alloc::Layout, ffi::c_void, mem::size_of, ptr};
+
+use crusty_traits_core::*;
+
+use crate::{
+    coption::COption,
+    cresult::CResult,
+    cslice::CSlice,
+    cvec::{CVec, CVecVTable},
+};
+
+/// The callbacks a [`RawVec`] manages its buffer with, matching the `realloc` and `free` of C.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct RawAllocator {
+    /// Resizes the buffer at `ptr` to `size` bytes, allocating a new one if `ptr` is null.
+    ///
+    /// Returns the resized buffer aligned for the elements, or null if it could not be allocated.
+    pub realloc: unsafe extern "C" fn(ptr: *mut c_void, size: usize) -> *mut c_void,
+    /// Frees the buffer at `ptr`.
+    pub free: unsafe extern "C" fn(ptr: *mut c_void),
+}
+
+/// A vector over a buffer allocated by C, implementing [`CVec`].
+///
+/// The buffer is grown, shrunk and freed with its [`RawAllocator`].
+#[repr(C)]
+#[derive(Debug)]
+pub struct RawVec<T> {
+    ptr: *mut T,
+    len: usize,
+    cap: usize,
+    allocator: RawAllocator,
+}
+
+impl<T> RawVec<T> {
+    /// Creates an empty vector, which allocates with `allocator` once elements are added.
+    pub const fn new(allocator: RawAllocator) -> Self {
+        Self {
+            ptr: Self::empty(),
+            len: 0,
+            cap: 0,
+            allocator,
+        }
+    }
+
+    /// Returns the buffer of an empty vector, which is dangling for zero sized elements.
+    const fn empty() -> *mut T {
+        if size_of::<T>() == 0 {
+            ptr::dangling_mut()
+        } else {
+            ptr::null_mut()
+        }
+    }
+
+    /// Creates a vector from the buffer at `ptr` holding `len` elements with room for `cap`.
+    /// # Safety
+    /// If `cap` is not zero and `T` is not zero sized, `ptr` must be a buffer of `cap` elements
+    /// aligned for `T` that may be resized and freed with `allocator`. The first `len` elements must be initialised, and are
+    /// owned by the vector.
+    #[allow(unsafe_code)]
+    pub const unsafe fn from_raw_parts(
+        ptr: *mut T,
+        len: usize,
+        cap: usize,
+        allocator: RawAllocator,
+    ) -> Self {
+        Self {
+            ptr: if size_of::<T>() == 0 {
+                Self::empty()
+            } else {
+                ptr
+            },
+            len,
+            cap,
+            allocator,
+        }
+    }
+
+    /// Returns the buffer, length and capacity of the vector without freeing it.
+    pub fn into_raw_parts(self) -> (*mut T, usize, usize) {
+        let this = core::mem::ManuallyDrop::new(self);
+        (this.ptr, this.len, this.cap)
+    }
+
+    /// Returns the allocator of the buffer.
+    pub fn allocator(&self) -> RawAllocator {
+        self.allocator
+    }
+
+    /// Returns the elements of the vector.
+    pub fn as_slice(&self) -> &[T] {
+        #[allow(unsafe_code)]
+        // SAFETY: The first `len` elements of the buffer are initialised.
+        unsafe {
+            crate::cslice::elements(self.ptr, self.len)
+        }
+    }
+
+    /// Returns the elements of the vector mutably.
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        #[allow(unsafe_code)]
+        // SAFETY: The first `len` elements of the buffer are initialised and owned by the vector.
+        unsafe {
+            crate::cslice::elements_mut(self.ptr, self.len)
+        }
+    }
+
+    fn spare_capacity(&self) -> usize {
+        CVec::capacity(self) - self.len
+    }
+
+    /// Resizes the buffer to `cap` elements, which must hold the current length.
+    fn set_capacity(&mut self, cap: usize) {
+        if size_of::<T>() == 0 || cap == self.cap {
+            return;
+        }
+        if cap == 0 {
+            #[allow(unsafe_code)]
+            // SAFETY: The buffer was allocated with the allocator and holds no elements.
+            unsafe {
+                (self.allocator.free)(self.ptr.cast());
+            }
+            self.ptr = Self::empty();
+            self.cap = 0;
+            return;
+        }
+        let size = Layout::array::<T>(cap).expect("capacity overflow").size();
+        let old = if self.cap == 0 {
+            ptr::null_mut()
+        } else {
+            self.ptr.cast()
+        };
+        #[allow(unsafe_code)]
+        // SAFETY: The buffer is null or was allocated with the allocator.
+        let ptr = unsafe { (self.allocator.realloc)(old, size) }.cast::<T>();
+        assert!(
+            !ptr.is_null() && ptr.is_aligned(),
+            "the allocator of a `RawVec` failed to allocate {size} bytes"
+        );
+        self.ptr = ptr;
+        self.cap = cap;
+    }
+
+    /// Grows the buffer to hold `additional` more elements, at least doubling its capacity.
+    fn grow_amortized(&mut self, additional: usize) {
+        if self.spare_capacity() >= additional {
+            return;
+        }
+        let required = self.len.checked_add(additional).expect("capacity overflow");
+        self.set_capacity(required.max(self.cap * 2).max(4));
+    }
+}
+
+impl<T> Drop for RawVec<T> {
+    fn drop(&mut self) {
+        #[allow(unsafe_code)]
+        // SAFETY: The elements are dropped once, before their buffer is freed.
+        unsafe {
+            ptr::drop_in_place(self.as_mut_slice());
+        }
+        self.len = 0;
+        self.set_capacity(0);
+    }
+}
+
+impl<T> CSlice<T> for RawVec<T> {
+    fn as_ptr(&self) -> *const T {
+        self.ptr
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl<T> CVec<T> for RawVec<T> {
+    fn push(&mut self, value: T) {
+        self.grow_amortized(1);
+        #[allow(unsafe_code)]
+        // SAFETY: There is room for one more element after the initialised ones.
+        unsafe {
+            self.ptr.add(self.len).write(value);
+        }
+        self.len += 1;
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.grow_amortized(additional);
+    }
+
+    fn capacity(&self) -> usize {
+        if size_of::<T>() == 0 {
+            usize::MAX
+        } else {
+            self.cap
+        }
+    }
+
+    fn pop(&mut self) -> COption<T> {
+        if self.len == 0 {
+            return COption::None;
+        }
+        self.len -= 1;
+        #[allow(unsafe_code)]
+        // SAFETY: The element was initialised and is no longer part of the vector.
+        COption::Some(unsafe { self.ptr.add(self.len).read() })
+    }
+
+    fn insert(&mut self, index: usize, value: T) -> CResult<(), T> {
+        if index > self.len {
+            return CResult::Err(value);
+        }
+        self.grow_amortized(1);
+        #[allow(unsafe_code)]
+        // SAFETY: There is room for one more element, the tail is shifted before it is written.
+        unsafe {
+            let at = self.ptr.add(index);
+            ptr::copy(at, at.add(1), self.len - index);
+            at.write(value);
+        }
+        self.len += 1;
+        CResult::Ok(())
+    }
+
+    fn remove(&mut self, index: usize) -> COption<T> {
+        if index >= self.len {
+            return COption::None;
+        }
+        self.len -= 1;
+        #[allow(unsafe_code)]
+        // SAFETY: The element is in bounds and read once before the tail is shifted over it.
+        unsafe {
+            let at = self.ptr.add(index);
+            let value = at.read();
+            ptr::copy(at.add(1), at, self.len - index);
+            COption::Some(value)
+        }
+    }
+
+    fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        let tail = ptr::slice_from_raw_parts_mut(self.ptr.wrapping_add(len), self.len - len);
+        self.len = len;
+        #[allow(unsafe_code)]
+        // SAFETY: The tail was initialised and is no longer part of the vector.
+        unsafe {
+            ptr::drop_in_place(tail);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    #[allow(unsafe_code)]
+    unsafe fn set_len(&mut self, len: usize) {
+        self.len = len;
+    }
+
+    fn reserve_exact(&mut self, additional: usize) {
+        if self.spare_capacity() < additional {
+            let required = self.len.checked_add(additional).expect("capacity overflow");
+            self.set_capacity(required);
+        }
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.set_capacity(self.len);
+    }
+
+    #[allow(unsafe_code)]
+    unsafe fn extend_from_raw(&mut self, ptr: *const T, len: usize) {
+        self.grow_amortized(len);
+        // SAFETY: `ptr` is valid for `len` reads per the caller and there is room for them.
+        unsafe {
+            ptr::copy_nonoverlapping(ptr, self.ptr.add(self.len), len);
+        }
+        self.len += len;
+    }
+
+    fn drain_into(
+        &mut self,
+        start: usize,
+        end: usize,
+        mut other: CRefMut<'_, CVecVTable<T>>,
+    ) -> bool {
+        if start > end || end > self.len {
+            return false;
+        }
+        #[allow(unsafe_code)]
+        // SAFETY: The range is in bounds, its elements are moved into `other` before the tail is
+        // shifted over them and the length is shortened.
+        unsafe {
+            other.extend_from_raw(self.ptr.add(start), end - start);
+            ptr::copy(self.ptr.add(end), self.ptr.add(start), self.len - end);
+        }
+        self.len -= end - start;
+        true
+    }
+
+    fn append(&mut self, mut other: CRefMut<'_, CVecVTable<T>>) {
+        let len = other.len();
+        #[allow(unsafe_code)]
+        // SAFETY: The elements of `other` are moved out before its length is set to zero.
+        unsafe {
+            CVec::extend_from_raw(self, CSlice::as_ptr(&other), len);
+            other.set_len(0);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    #![allow(unsafe_code)]
+    use core::sync::atomic::{AtomicIsize, Ordering};
+
+    use super::*;
+    use crate::cslice::{CSliceRef, CSliceVTable};
+
+    unsafe extern "C" {
+        fn realloc(ptr: *mut c_void, size: usize) -> *mut c_void;
+        fn free(ptr: *mut c_void);
+    }
+
+    static BUFFERS: AtomicIsize = AtomicIsize::new(0);
+
+    unsafe extern "C" fn counted_realloc(ptr: *mut c_void, size: usize) -> *mut c_void {
+        if ptr.is_null() {
+            BUFFERS.fetch_add(1, Ordering::SeqCst);
+        }
+        // SAFETY: The buffer was allocated by `realloc`.
+        unsafe { realloc(ptr, size) }
+    }
+
+    unsafe extern "C" fn counted_free(ptr: *mut c_void) {
+        BUFFERS.fetch_sub(1, Ordering::SeqCst);
+        // SAFETY: The buffer was allocated by `realloc`.
+        unsafe { free(ptr) }
+    }
+
+    const COUNTED: RawAllocator = RawAllocator {
+        realloc: counted_realloc,
+        free: counted_free,
+    };
+
+    #[test]
+    fn test_raw_vec() {
+        let mut vec = CVecVTable::new_boxed(RawVec::new(COUNTED));
+        vec.extend((0..10).map(|i| i.to_string()));
+        assert!(vec.capacity() >= 10);
+        assert_eq!(vec.remove(0), COption::Some("0".to_string()));
+        assert_eq!(vec.insert(9, "x".to_string()), CResult::Ok(()));
+        assert_eq!(vec.pop(), COption::Some("x".to_string()));
+        vec.truncate(3);
+        vec.shrink_to_fit();
+        assert_eq!(vec.capacity(), 3);
+        assert_eq!(vec.as_ref(), ["1", "2", "3"]);
+
+        let mut other = CVecVTable::new_boxed(vec!["4".to_string()]);
+        vec.append(other.as_cref_mut());
+        assert!(vec.drain_into(0, 1, other.as_cref_mut()));
+        assert_eq!(vec.as_ref(), ["2", "3", "4"]);
+        assert_eq!(other.as_ref(), ["1"]);
+        drop(vec);
+        assert_eq!(BUFFERS.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_raw_vec_from_c_buffer() {
+        // SAFETY: The buffer is allocated with `realloc` and the vector takes ownership of it.
+        let mut vec = unsafe {
+            let ptr = realloc(ptr::null_mut(), 2 * size_of::<u32>()).cast::<u32>();
+            ptr.write(1);
+            CVecVTable::new_boxed(RawVec::from_raw_parts(
+                ptr,
+                1,
+                2,
+                RawAllocator { realloc, free },
+            ))
+        };
+        vec.extend([2, 3, 4]);
+        assert_eq!(vec.as_ref(), [1, 2, 3, 4]);
+        vec.clear();
+        assert_eq!(vec.len(), 0);
+    }
+
+    #[test]
+    fn test_raw_vec_of_zero_sized_elements() {
+        let mut vec = CVecVTable::new_boxed(RawVec::new(COUNTED));
+        vec.extend([(), ()]);
+        assert_eq!(vec.capacity(), usize::MAX);
+        assert_eq!(vec.pop(), COption::Some(()));
+        assert_eq!(vec.len(), 1);
+    }
+
+    #[test]
+    fn test_slice_borrowed_from_c() {
+        let values = [1u8, 2, 3];
+        // SAFETY: The view borrows `values`.
+        let view = unsafe { CSliceRef::from_raw_parts(values.as_ptr(), values.len()) };
+        let slice = CSliceVTable::new_scoped(view);
+        assert_eq!(slice.as_ref(), [1, 2, 3]);
+        // SAFETY: An empty view may be null.
+        let empty = unsafe { CSliceRef::<u8>::from_raw_parts(ptr::null(), 0) };
+        assert_eq!(CSliceVTable::borrow(&empty).as_ref(), [0u8; 0]);
+    }
+}
@@ -32,8 +32,27 @@ use syn::ItemTrait;
 ///
 /// This macro generates:
 /// - A repr(C) vtable struct containing function pointers for each trait method
-/// - Implementation of the original trait for `CRepr<TraitVTable>`  
+/// - Implementation of the original trait for `CRepr<TraitVTable>`, `CReprInline` and
+///   `CRefMut<TraitVTable>`
+/// - Implementation for `CRef<TraitVTable>`, of the original trait if every method takes `&self`,
+///   otherwise of a generated `TraitRef` trait holding just the `&self` methods
 /// - Memory management through the `CDrop` trait
+/// - `CSend` and `CSync` markers on the vtable for `Send` and `Sync` super traits, which the
+///   wrappers of the vtable need to be `Send` and `Sync`
+/// - A `clone` slot and an implementation of `CClone` when the trait has a `Clone` super trait,
+///   which makes `CRepr<TraitVTable>` `Clone`
+/// - `debug` and `display` slots writing into a `CFormatter` for `Debug` and `Display` super
+///   traits, which make the wrappers of the vtable implement the same traits
+/// - `eq`, `partial_cmp`, `cmp` and `hash` slots for `PartialEq`, `Eq`, `PartialOrd`, `Ord` and
+///   `Hash` super traits, and an `implementation` slot identifying the implementer for the
+///   comparison traits, so only objects of the same implementation are compared by the slots.
+///   Their vtables are only built for `'static` implementers
+/// - `async fn` methods, and methods returning `impl Future<Output = T>`, returning a boxed
+///   `CFuture` object in the vtable, with the `alloc` feature. The future only borrows `self`, so
+///   the other arguments can't be borrowed
+/// - Methods returning `impl Iterator<Item = T>` or `Box<dyn Iterator<Item = T>>`, returning a
+///   boxed `CIterator` object in the vtable, with the `alloc` feature. Unless it is a `'static`
+///   `Box<dyn Iterator>`, the iterator only borrows `self`, so the other arguments can't be borrowed
 /// - Helper functions for creating and managing trait objects across FFI boundaries
 ///
 /// # Example
@@ -51,6 +70,21 @@ use syn::ItemTrait;
 /// This will generate a `BufferVTable` struct and all necessary implementations
 /// to use this trait safely across FFI boundaries.
 ///
+/// # Trait arguments
+///
+/// - `#[crusty_trait(stable_layout)]` lays the vtable out as `vtable_size` and `drop`, followed by
+///   the methods in the order of their `slot` index. Every method must have a slot, and the slots
+///   must be numbered from 0 without gaps or duplicates.
+/// - `#[crusty_trait(stable_layout(Named = 2, Clone = 3))]` also gives the super traits their
+///   slot, which they share with the methods. Super traits must come before any `since` method.
+///
+/// # Method attributes
+///
+/// - `#[crusty(since = N)]` appends the method's slot after `drop` instead of in declaration
+///   order, so vtables built against an older version of the trait remain valid. Calling the
+///   method on such a vtable runs the method's default body, which the method must have.
+/// - `#[crusty(slot = N)]` sets the position of the method in a `stable_layout` vtable.
+///
 /// # Safety
 ///
 /// The generated code uses unsafe operations internally but provides a safe API.
@@ -58,10 +92,12 @@ use syn::ItemTrait;
 /// to ensure proper lifetime management and memory safety.
 #[proc_macro_attribute]
 pub fn crusty_trait(
-    _attrs: proc_macro::TokenStream,
+    attrs: proc_macro::TokenStream,
     input: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
     let input = syn::parse_macro_input!(input as ItemTrait);
 
-    impl_crusty_trait(input).to_token_stream().into()
+    impl_crusty_trait(attrs.into(), input)
+        .to_token_stream()
+        .into()
 }
@@ -41,6 +41,16 @@
 //! The trait is also implemented for `CRepr<MyTraitVTable>` and any `CRepr<GEN>` where `GEN` implements
 //! `AsVTable<&'static MyTraitVTable>` (used for super/sub traits) and `CDrop`, allowing for seamless
 //! usage of the trait across FFI boundaries in Rust code.
+//!
+//! ## Features
+//!
+//! - `std` (default) - Enables `alloc`.
+//! - `alloc` - Enables `Global` and the generated `new_boxed` constructors.
+//!
+//! Without `alloc` the crate and the code generated by `crusty_trait` are `no_std`, objects are
+//! created with `new_in` and a custom `Allocator` or in place with `new_in_place`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
 
 pub use crusty_traits_core::*;
 pub use crusty_traits_macros::crusty_trait;
@@ -50,17 +60,44 @@ pub use crusty_traits_macros::crusty_trait;
 /// This module provides a convenient way to import all the core functionality needed to use
 /// the `crusty_trait` macro and work with C-compatible vtables.
 pub mod prelude {
+    pub use crate::Allocator;
     pub use crate::AsVTable;
+    #[cfg(feature = "alloc")]
+    pub use crate::CArc;
+    pub use crate::CClone;
+    pub use crate::CDebug;
+    pub use crate::CDisplay;
     pub use crate::CDrop;
+    pub use crate::CEq;
+    pub use crate::CFormatter;
+    pub use crate::CHash;
+    pub use crate::CHasher;
+    pub use crate::CImplementationId;
+    pub use crate::COrd;
+    pub use crate::CPartialEq;
+    pub use crate::CPartialOrd;
     pub use crate::CRef;
     pub use crate::CRefMut;
     pub use crate::CRepr;
+    pub use crate::CReprInline;
+    pub use crate::CReprScoped;
+    pub use crate::CSend;
+    pub use crate::CSync;
+    #[cfg(feature = "alloc")]
+    pub use crate::Global;
+    pub use crate::StaticVTable;
+    #[doc(hidden)]
+    pub use crate::crusty_alloc;
     pub use crate::crusty_trait;
+    pub use crate::implementation_id;
+    #[cfg(feature = "alloc")]
+    pub use crate::types::cfuture::{CFuture, CFutureVTable};
+    pub use crate::types::citerator::CIteratorVTable;
 }
 
 pub use crusty_traits_types as types;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
 
@@ -73,7 +110,7 @@ mod tests {
 
     impl Buffer for Vec<u8> {
         fn as_slice(&self) -> *mut u8 {
-            self.as_ptr() as *mut u8
+            self.as_ptr().cast_mut()
         }
 
         fn extend(&mut self, amount: usize) {
@@ -102,19 +139,21 @@ mod tests {
         assert!(!slice.is_null());
         #[allow(unsafe_code)]
         {
-            let slice = unsafe { std::slice::from_raw_parts_mut(slice as *mut _, vtable.len()) };
+            // SAFETY: The buffer owns `len` initialised bytes starting at `slice`.
+            let slice = unsafe { std::slice::from_raw_parts_mut(slice, vtable.len()) };
             assert_eq!(slice.len(), 10);
-            for i in 0..10 {
-                slice[i] = i as u8;
+            for (i, byte) in slice.iter_mut().enumerate() {
+                *byte = i as u8;
             }
         }
         let slice = vtable.as_slice();
         #[allow(unsafe_code)]
         {
-            let slice = unsafe { std::slice::from_raw_parts(slice as *const _, vtable.len()) };
+            // SAFETY: The buffer owns `len` initialised bytes starting at `slice`.
+            let slice = unsafe { std::slice::from_raw_parts(slice.cast_const(), vtable.len()) };
             assert_eq!(slice.len(), 10);
-            for i in 0..10 {
-                assert_eq!(slice[i], i as u8);
+            for (i, byte) in slice.iter().enumerate() {
+                assert_eq!(*byte, i as u8);
             }
         }
     }
@@ -0,0 +1,79 @@
+//! A `no_std` library without an allocator, checking the generated code only needs `core`.
+#![no_std]
+#![allow(unsafe_code)]
+
+use core::{alloc::Layout, mem::MaybeUninit, ptr::NonNull};
+
+use crusty_traits::prelude::*;
+
+/// A sensor that can be read.
+#[crusty_trait]
+pub trait Sensor {
+    /// Reads the current value.
+    fn read(&self) -> u32;
+}
+
+/// A sensor with a configurable offset.
+#[crusty_trait]
+pub trait Calibrated: Sensor {
+    /// Sets the offset added to each reading.
+    fn set_offset(&mut self, offset: u32);
+}
+
+/// A thermometer reading a fixed temperature.
+pub struct Thermometer {
+    /// The measured temperature.
+    pub temperature: u32,
+    /// The offset added to each reading.
+    pub offset: u32,
+}
+
+impl Sensor for Thermometer {
+    fn read(&self) -> u32 {
+        self.temperature + self.offset
+    }
+}
+
+impl Calibrated for Thermometer {
+    fn set_offset(&mut self, offset: u32) {
+        self.offset = offset;
+    }
+}
+
+/// The vtable for [`Thermometer`], built at compile time.
+pub static THERMOMETER_VTABLE: &CalibratedVTable = CalibratedVTable::create_vtable::<Thermometer>();
+
+/// Reads the thermometer through the vtable of its `Sensor` super trait.
+pub fn read(thermometer: &Thermometer) -> u32 {
+    let sensor_vtable = THERMOMETER_VTABLE.field_sensor;
+    // SAFETY: The vtable was built for `Thermometer` and the context outlives the call.
+    unsafe {
+        let sensor = CRef::from_raw_parts(
+            NonNull::from(sensor_vtable),
+            NonNull::from(thermometer).cast(),
+        );
+        (sensor_vtable.read)(sensor)
+    }
+}
+
+/// Reads a thermometer placed in a stack buffer, calling through its `CRepr`.
+pub fn read_placed(temperature: u32, offset: u32) -> u32 {
+    let mut buffer = MaybeUninit::<Thermometer>::uninit();
+    let thermometer = Thermometer {
+        temperature,
+        offset: 0,
+    };
+    // SAFETY: The buffer outlives the object, which is dropped at the end of the function.
+    let placed = unsafe {
+        CalibratedVTable::new_in_place(
+            NonNull::from(&mut buffer).cast(),
+            Layout::new::<Thermometer>(),
+            thermometer,
+        )
+    };
+    let Ok(mut calibrated) = placed else {
+        unreachable!("the buffer is laid out for a `Thermometer`")
+    };
+    calibrated.set_offset(offset);
+    calibrated.read()
+}
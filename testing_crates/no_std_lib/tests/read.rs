@@ -0,0 +1,19 @@
+#![allow(missing_docs)]
+
+use no_std_lib::{Calibrated, Thermometer, read, read_placed};
+
+#[test]
+fn read_through_static_vtable() {
+    let mut thermometer = Thermometer {
+        temperature: 20,
+        offset: 0,
+    };
+    assert_eq!(read(&thermometer), 20);
+    thermometer.set_offset(3);
+    assert_eq!(read(&thermometer), 23);
+}
+
+#[test]
+fn read_placed_object() {
+    assert_eq!(read_placed(20, 5), 25);
+}
@@ -2,20 +2,27 @@
 #![allow(unsafe_code)]
 use crusty_traits::prelude::*;
 pub use crusty_traits::types::cvec::CVecVTable;
+use crusty_traits::types::raw::{RawAllocator, RawVec};
 
 /// print "Hello from the shared library!"
+/// # Safety
+/// Always safe to call.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn hello_world() {
     println!("Hello from the rust shared library!");
 }
 
 /// add two numbers and return the result
+/// # Safety
+/// Always safe to call.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn add(a: isize, b: isize) -> isize {
     a + b
 }
 
 /// multiply two numbers and return the result
+/// # Safety
+/// Always safe to call.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn multiply(a: isize, b: isize) -> isize {
     a * b
@@ -23,7 +30,24 @@ pub unsafe extern "C" fn multiply(a: isize, b: isize) -> isize {
 
 #[unsafe(no_mangle)]
 /// Create a new C-compatible vector of i32 and return it.
+/// # Safety
+/// The returned vector must be dropped through its vtable.
 pub unsafe extern "C" fn create_vector() -> CRepr<CVecVTable<i32>> {
     let vec: Vec<i32> = Vec::new();
     CVecVTable::new_boxed(vec)
 }
+
+#[unsafe(no_mangle)]
+/// Wrap a vector of i32 allocated by the caller, which is grown and freed with `allocator`.
+/// # Safety
+/// `ptr` must hold `len` elements with room for `cap`, allocated with `allocator`, see
+/// `RawVec::from_raw_parts`. The returned vector must be dropped through its vtable.
+pub unsafe extern "C" fn wrap_vector(
+    ptr: *mut i32,
+    len: usize,
+    cap: usize,
+    allocator: RawAllocator,
+) -> CRepr<CVecVTable<i32>> {
+    // SAFETY: The caller upholds the contract of `RawVec::from_raw_parts`.
+    CVecVTable::new_boxed(unsafe { RawVec::from_raw_parts(ptr, len, cap, allocator) })
+}
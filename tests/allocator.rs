@@ -0,0 +1,135 @@
+#![allow(missing_docs, unsafe_code)]
+
+use std::{
+    alloc::Layout,
+    cell::Cell,
+    mem::MaybeUninit,
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crusty_traits::prelude::*;
+
+#[crusty_trait]
+trait Counter {
+    fn get(&self) -> u32;
+    fn add(&mut self, value: u32);
+}
+
+static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+struct Tally(u32);
+
+impl Counter for Tally {
+    fn get(&self) -> u32 {
+        self.0
+    }
+
+    fn add(&mut self, value: u32) {
+        self.0 += value;
+    }
+}
+
+impl Drop for Tally {
+    fn drop(&mut self) {
+        DROPPED.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// A bump allocator over a fixed buffer that counts its allocations.
+struct Arena {
+    buffer: [Cell<MaybeUninit<u8>>; 256],
+    used: Cell<usize>,
+    live: Cell<usize>,
+}
+
+impl Arena {
+    fn new() -> Self {
+        Self {
+            buffer: [const { Cell::new(MaybeUninit::uninit()) }; 256],
+            used: Cell::new(0),
+            live: Cell::new(0),
+        }
+    }
+}
+
+// SAFETY: Blocks are carved out of the arena's buffer and never handed out twice.
+unsafe impl Allocator for Arena {
+    fn allocate(&self, layout: Layout) -> Option<NonNull<u8>> {
+        let base = self.buffer.as_ptr() as *mut u8;
+        let start = self.used.get()
+            + base
+                .wrapping_add(self.used.get())
+                .align_offset(layout.align());
+        if start + layout.size() > self.buffer.len() {
+            return None;
+        }
+        self.used.set(start + layout.size());
+        self.live.set(self.live.get() + 1);
+        NonNull::new(base.wrapping_add(start))
+    }
+
+    unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {
+        self.live.set(self.live.get() - 1);
+    }
+}
+
+#[test]
+fn new_in_frees_with_the_same_allocator() {
+    let arena: &'static Arena = Box::leak(Box::new(Arena::new()));
+    let dropped = DROPPED.load(Ordering::SeqCst);
+
+    let mut counter = CounterVTable::new_in(arena, Tally(1)).ok().unwrap();
+    counter.add(2);
+    assert_eq!(counter.get(), 3);
+    assert_eq!(arena.live.get(), 1);
+
+    drop(counter);
+    assert_eq!(arena.live.get(), 0);
+    assert!(DROPPED.load(Ordering::SeqCst) > dropped);
+}
+
+#[test]
+fn new_in_returns_the_value_when_exhausted() {
+    let arena: &'static Arena = Box::leak(Box::new(Arena::new()));
+    arena.used.set(arena.buffer.len());
+
+    let tally = CounterVTable::new_in(arena, Tally(7)).err().unwrap();
+    assert_eq!(tally.0, 7);
+}
+
+#[test]
+fn new_in_global() {
+    let counter = CounterVTable::new_in(Global, Tally(4)).ok().unwrap();
+    assert_eq!(counter.get(), 4);
+}
+
+#[test]
+fn new_in_place_uses_the_buffer() {
+    let mut buffer = MaybeUninit::<[u64; 2]>::uninit();
+    let ptr = NonNull::from(&mut buffer).cast::<u8>();
+
+    // SAFETY: The buffer outlives the object.
+    let counter = unsafe { CounterVTable::new_in_place(ptr, Layout::new::<[u64; 2]>(), Tally(5)) }
+        .ok()
+        .unwrap();
+    assert_eq!(counter.as_ptr(), ptr.as_ptr().cast_const().cast());
+    assert_eq!(counter.get(), 5);
+}
+
+#[test]
+fn new_in_place_rejects_small_buffers() {
+    let mut buffer = MaybeUninit::<u16>::uninit();
+
+    // SAFETY: The buffer is valid for its layout.
+    let tally = unsafe {
+        CounterVTable::new_in_place(
+            NonNull::from(&mut buffer).cast(),
+            Layout::new::<u16>(),
+            Tally(6),
+        )
+    }
+    .err()
+    .unwrap();
+    assert_eq!(tally.0, 6);
+}
@@ -31,11 +31,13 @@ struct BasicSharedLib {
 
 impl BasicSharedLib {
     fn new(path: impl AsRef<OsStr>) -> Self {
+        // SAFETY: The test libraries have no initialisation side effects.
         let lib = unsafe { libloading::Library::new(path) }.expect("Failed to load C library");
         Self { lib }
     }
 
     fn hello_world(&self) {
+        // SAFETY: `hello_world` is exported with this signature by both libraries.
         unsafe {
             let hello: libloading::Symbol<unsafe extern "C" fn()> = self
                 .lib
@@ -46,6 +48,7 @@ impl BasicSharedLib {
     }
 
     fn add(&self, a: isize, b: isize) -> isize {
+        // SAFETY: `add` is exported with this signature by both libraries.
         unsafe {
             let add: libloading::Symbol<unsafe extern "C" fn(isize, isize) -> isize> =
                 self.lib.get(b"add\0").expect("Failed to load add");
@@ -54,6 +57,7 @@ impl BasicSharedLib {
     }
 
     fn multiply(&self, a: isize, b: isize) -> isize {
+        // SAFETY: `multiply` is exported with this signature by both libraries.
         unsafe {
             let multiply: libloading::Symbol<unsafe extern "C" fn(isize, isize) -> isize> = self
                 .lib
@@ -0,0 +1,73 @@
+#![allow(missing_docs, unsafe_code)]
+
+use crusty_traits::prelude::*;
+
+#[crusty_trait]
+trait Visitor {
+    fn count(&self) -> u32;
+    fn visit(&mut self, value: u32);
+}
+
+struct Sum<'a> {
+    total: &'a mut u32,
+    visits: u32,
+}
+
+impl Visitor for Sum<'_> {
+    fn count(&self) -> u32 {
+        self.visits
+    }
+
+    fn visit(&mut self, value: u32) {
+        *self.total += value;
+        self.visits += 1;
+    }
+}
+
+/// Stands in for a C function that visits each value with a callback object.
+extern "C" fn visit_all(mut visitor: CRefMut<VisitorVTable>, values: *const u32, len: usize) {
+    // SAFETY: The caller passes a valid slice.
+    let values = unsafe { std::slice::from_raw_parts(values, len) };
+    for value in values {
+        // SAFETY: The vtable was built for the borrowed visitor.
+        unsafe { (visitor.get_vtable().visit)(visitor.reborrow(), *value) };
+    }
+}
+
+/// Stands in for a C function that reads from a callback object.
+extern "C" fn visit_count(visitor: CRef<VisitorVTable>) -> u32 {
+    // SAFETY: The vtable was built for the borrowed visitor.
+    unsafe { (visitor.get_vtable().count)(visitor) }
+}
+
+#[test]
+fn borrow_stack_values_for_a_call() {
+    let mut total = 0;
+    let mut sum = Sum {
+        total: &mut total,
+        visits: 0,
+    };
+    let values = [1, 2, 3];
+
+    visit_all(
+        VisitorVTable::borrow_mut(&mut sum),
+        values.as_ptr(),
+        values.len(),
+    );
+    assert_eq!(visit_count(VisitorVTable::borrow(&sum)), 3);
+    assert_eq!(sum.count(), 3);
+    assert_eq!(total, 6);
+}
+
+#[test]
+fn borrowed_context_is_the_value() {
+    let mut total = 0;
+    let sum = Sum {
+        total: &mut total,
+        visits: 4,
+    };
+
+    let borrowed = VisitorVTable::borrow(&sum);
+    assert_eq!(borrowed.as_ptr(), (&raw const sum).cast());
+    assert_eq!(visit_count(borrowed), 4);
+}
@@ -1,4 +1,4 @@
-/* Generated with cbindgen:0.29.0 */
+/* Generated with cbindgen:0.29.2 */
 
 /* Warning, this file is autogenerated by cbindgen. Don't modify this manually. */
 
@@ -9,6 +9,31 @@
 
 /**
  * A reference to a C-compatible object.
+ *
+ * It is a unique borrow of the object, so unlike [`CRef`] it is not `Copy`, and
+ * [`CRefMut::reborrow`] passes it on without giving it up:
+ *
+ * ```compile_fail
+ * use crusty_traits_core::*;
+ * use crusty_traits_macros::crusty_trait;
+ *
+ * #[crusty_trait]
+ * pub trait Counter {
+ *     fn add(&mut self, value: u32);
+ * }
+ *
+ * impl Counter for u32 {
+ *     fn add(&mut self, value: u32) {
+ *         *self += value;
+ *     }
+ * }
+ *
+ * let mut count = 0;
+ * let mut counter = CounterVTable::borrow_mut(&mut count);
+ * let mut copy = counter;
+ * copy.add(1);
+ * counter.add(2);
+ * ```
  */
 typedef struct Inner_CVecVTable_i32 CRefMut_CVecVTable_i32;
 
@@ -17,9 +42,38 @@ typedef struct Inner_CVecVTable_i32 CRefMut_CVecVTable_i32;
  */
 typedef struct Inner_CVecVTable_i32 CRef_CVecVTable_i32;
 
+/**
+ * A C-compatible [`Option`].
+ */
+typedef enum COption_i32_Tag {
+    /**
+     * No value.
+     */
+    None_i32,
+    /**
+     * Some value of type `T`.
+     */
+    Some_i32,
+} COption_i32_Tag;
+
+typedef struct COption_i32 {
+    COption_i32_Tag tag;
+    union {
+        struct {
+            int32_t some;
+        };
+    };
+} COption_i32;
+
+/**
+ * A vtable and the type erased context it is called with.
+ *
+ * The vtable is a shared `'static`, so it is only ever read. The context keeps the provenance
+ * of the allocation or borrow it was created from.
+ */
 typedef struct Inner_CSliceVTable_i32 {
     struct CSliceVTable_i32 *vtable;
-    uint8_t *ptr;
+    void *ptr;
 } Inner_CSliceVTable_i32;
 
 /**
@@ -29,6 +83,31 @@ typedef struct Inner_CSliceVTable_i32 CRef_CSliceVTable_i32;
 
 /**
  * A reference to a C-compatible object.
+ *
+ * It is a unique borrow of the object, so unlike [`CRef`] it is not `Copy`, and
+ * [`CRefMut::reborrow`] passes it on without giving it up:
+ *
+ * ```compile_fail
+ * use crusty_traits_core::*;
+ * use crusty_traits_macros::crusty_trait;
+ *
+ * #[crusty_trait]
+ * pub trait Counter {
+ *     fn add(&mut self, value: u32);
+ * }
+ *
+ * impl Counter for u32 {
+ *     fn add(&mut self, value: u32) {
+ *         *self += value;
+ *     }
+ * }
+ *
+ * let mut count = 0;
+ * let mut counter = CounterVTable::borrow_mut(&mut count);
+ * let mut copy = counter;
+ * copy.add(1);
+ * counter.add(2);
+ * ```
  */
 typedef struct Inner_CSliceVTable_i32 CRefMut_CSliceVTable_i32;
 
@@ -36,6 +115,10 @@ typedef struct Inner_CSliceVTable_i32 CRefMut_CSliceVTable_i32;
  *A repr C vtable for the trait CSlice
  */
 typedef struct CSliceVTable_i32 {
+    /**
+     *The size of the vtable in bytes, used to detect methods added in later versions
+     */
+    uintptr_t vtable_size;
     /**
      * Returns a pointer to the first element of the slice.
      */
@@ -45,7 +128,7 @@ typedef struct CSliceVTable_i32 {
      */
     uintptr_t (*len)(CRef_CSliceVTable_i32);
     /**
-     *A function pointer to the drop function for the trait
+     *Drops the object in place, its memory is freed afterwards by the `dealloc` of its owner
      */
     void (*drop)(CRefMut_CSliceVTable_i32);
 } CSliceVTable_i32;
@@ -54,51 +137,166 @@ typedef struct CSliceVTable_i32 {
  *A repr C vtable for the trait CVec
  */
 typedef struct CVecVTable_i32 {
+    /**
+     *The size of the vtable in bytes, used to detect methods added in later versions
+     */
+    uintptr_t vtable_size;
     /**
      * Adds an element to the end of the vector.
      */
     void (*push)(CRefMut_CVecVTable_i32, int32_t);
     /**
-     * Extends the vector's capacity by the given amount.
+     * Reserves capacity for at least `additional` more elements.
      */
-    void (*extend)(CRefMut_CVecVTable_i32, uintptr_t);
+    void (*reserve)(CRefMut_CVecVTable_i32, uintptr_t);
     /**
      * Returns the capacity of the vector.
      */
     uintptr_t (*capacity)(CRef_CVecVTable_i32);
+    /**
+     * Removes the last element of the vector and returns it, or `None` if it is empty.
+     */
+    struct COption_i32 (*pop)(CRefMut_CVecVTable_i32);
+    /**
+     * Inserts an element at `index`, shifting the elements after it to the right.
+     *
+     * Returns the element back if `index` is greater than the length.
+     */
+    struct COption_i32 (*insert)(CRefMut_CVecVTable_i32, uintptr_t, int32_t);
+    /**
+     * Removes the element at `index` and returns it, shifting the elements after it to the left.
+     *
+     * Returns `None` if `index` is out of bounds.
+     */
+    struct COption_i32 (*remove)(CRefMut_CVecVTable_i32, uintptr_t);
+    /**
+     * Shortens the vector to `len` elements, dropping the rest.
+     */
+    void (*truncate)(CRefMut_CVecVTable_i32, uintptr_t);
+    /**
+     * Removes all elements of the vector.
+     */
+    void (*clear)(CRefMut_CVecVTable_i32);
+    /**
+     * Sets the length of the vector without dropping or initialising elements.
+     * # Safety
+     * `len` must not be greater than the capacity and the elements up to `len` must be
+     * initialised.
+     */
+    void (*set_len)(CRefMut_CVecVTable_i32, uintptr_t);
+    /**
+     * Reserves capacity for exactly `additional` more elements.
+     */
+    void (*reserve_exact)(CRefMut_CVecVTable_i32, uintptr_t);
+    /**
+     * Shrinks the capacity of the vector as much as possible.
+     */
+    void (*shrink_to_fit)(CRefMut_CVecVTable_i32);
+    /**
+     * Moves `len` elements from `ptr` to the end of the vector.
+     * # Safety
+     * `ptr` must be valid for reading `len` elements, which are moved out and must not be used
+     * or dropped by the caller afterwards.
+     */
+    void (*extend_from_slice)(CRefMut_CVecVTable_i32, const int32_t*, uintptr_t);
+    /**
+     * Moves the elements in `start..end` to the end of `other`.
+     *
+     * Returns `false` and moves nothing if the range is out of bounds.
+     */
+    bool (*drain_into)(CRefMut_CVecVTable_i32,
+                       uintptr_t,
+                       uintptr_t,
+                       CRefMut_CVecVTable_i32);
+    /**
+     * Moves all elements of `other` to the end of the vector, leaving `other` empty.
+     */
+    void (*append)(CRefMut_CVecVTable_i32, CRefMut_CVecVTable_i32);
+    /**
+     *A vtable point for CSlice
+     */
     const struct CSliceVTable_i32 *field_c_slice;
     /**
-     *A function pointer to the drop function for the trait
+     *Drops the object in place, its memory is freed afterwards by the `dealloc` of its owner
      */
     void (*drop)(CRefMut_CVecVTable_i32);
 } CVecVTable_i32;
 
+/**
+ * A vtable and the type erased context it is called with.
+ *
+ * The vtable is a shared `'static`, so it is only ever read. The context keeps the provenance
+ * of the allocation or borrow it was created from.
+ */
 typedef struct Inner_CVecVTable_i32 {
     struct CVecVTable_i32 *vtable;
-    uint8_t *ptr;
+    void *ptr;
 } Inner_CVecVTable_i32;
 
 /**
  * A trait that represents a buffer that can be converted to a C-compatible slice.
+ *
+ * The vtable's `drop` only drops the context in place, `dealloc` then frees its memory with
+ * the strategy chosen when the object was created. A `drop` implemented in C must not also free
+ * the context when the object has a `dealloc`, or it is freed twice. Only objects created with
+ * [`CRepr::from_raw_parts`] have none, and their `drop` frees the context itself.
+ */
+typedef struct CRepr_CVecVTable_i32 {
+    struct Inner_CVecVTable_i32 inner;
+    void (*dealloc)(void *context);
+} CRepr_CVecVTable_i32;
+
+/**
+ * The callbacks a [`RawVec`] manages its buffer with, matching the `realloc` and `free` of C.
  */
-typedef struct Inner_CVecVTable_i32 CRepr_CVecVTable_i32;
+typedef struct RawAllocator {
+    /**
+     * Resizes the buffer at `ptr` to `size` bytes, allocating a new one if `ptr` is null.
+     *
+     * Returns the resized buffer aligned for the elements, or null if it could not be allocated.
+     */
+    void *(*realloc)(void *ptr, uintptr_t size);
+    /**
+     * Frees the buffer at `ptr`.
+     */
+    void (*free)(void *ptr);
+} RawAllocator;
 
 /**
  * print "Hello from the shared library!"
+ * # Safety
+ * Always safe to call.
  */
 void hello_world(void);
 
 /**
  * add two numbers and return the result
+ * # Safety
+ * Always safe to call.
  */
 intptr_t add(intptr_t a, intptr_t b);
 
 /**
  * multiply two numbers and return the result
+ * # Safety
+ * Always safe to call.
  */
 intptr_t multiply(intptr_t a, intptr_t b);
 
 /**
  * Create a new C-compatible vector of i32 and return it.
+ * # Safety
+ * The returned vector must be dropped through its vtable.
+ */
+struct CRepr_CVecVTable_i32 create_vector(void);
+
+/**
+ * Wrap a vector of i32 allocated by the caller, which is grown and freed with `allocator`.
+ * # Safety
+ * `ptr` must hold `len` elements with room for `cap`, allocated with `allocator`, see
+ * `RawVec::from_raw_parts`. The returned vector must be dropped through its vtable.
  */
-CRepr_CVecVTable_i32 create_vector(void);
+struct CRepr_CVecVTable_i32 wrap_vector(int32_t *ptr,
+                                        uintptr_t len,
+                                        uintptr_t cap,
+                                        struct RawAllocator allocator);
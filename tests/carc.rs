@@ -0,0 +1,122 @@
+#![allow(missing_docs, unsafe_code)]
+
+use std::{
+    ffi::c_void,
+    ptr::NonNull,
+    sync::atomic::{AtomicU32, AtomicUsize, Ordering},
+};
+
+use crusty_traits::prelude::*;
+use crusty_traits::{CArcHeader, CWeak};
+
+#[crusty_trait]
+trait Listener: Send + Sync {
+    fn notify(&self, event: u32);
+    fn received(&self) -> u32;
+}
+
+static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Default)]
+struct Recorder {
+    total: AtomicU32,
+}
+
+impl Listener for Recorder {
+    fn notify(&self, event: u32) {
+        self.total.fetch_add(event, Ordering::SeqCst);
+    }
+
+    fn received(&self) -> u32 {
+        self.total.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        DROPPED.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+fn new_listener() -> CArc<ListenerVTable> {
+    CArc::new(
+        ListenerVTable::create_vtable::<Recorder>(),
+        Recorder::default(),
+    )
+}
+
+fn publish(listeners: &[CArc<ListenerVTable>], event: u32) {
+    for listener in listeners {
+        listener.notify(event);
+    }
+}
+
+#[test]
+fn clones_share_the_object() {
+    let listener = new_listener();
+    let listeners = vec![listener.clone(), listener.clone()];
+    assert_eq!(listener.strong_count(), 3);
+
+    publish(&listeners, 2);
+    assert_eq!(listener.received(), 4);
+    assert_eq!(listener.as_ptr(), listeners[0].as_ptr());
+
+    drop(listeners);
+    assert_eq!(listener.strong_count(), 1);
+}
+
+#[test]
+fn shared_between_threads() {
+    let listener = new_listener();
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            let listener = listener.clone();
+            scope.spawn(move || listener.notify(1));
+        }
+    });
+    assert_eq!(listener.received(), 4);
+    assert_eq!(listener.strong_count(), 1);
+}
+
+#[test]
+fn weak_does_not_keep_the_object_alive() {
+    let listener = new_listener();
+    let weak: CWeak<ListenerVTable> = listener.downgrade();
+    assert_eq!(listener.weak_count(), 1);
+    weak.upgrade().unwrap().notify(3);
+    assert_eq!(listener.received(), 3);
+
+    let dropped = DROPPED.load(Ordering::SeqCst);
+    drop(listener);
+    assert!(DROPPED.load(Ordering::SeqCst) > dropped);
+    assert_eq!(weak.strong_count(), 0);
+    assert!(weak.upgrade().is_none());
+}
+
+/// The layout of a `CArc` as seen from C.
+#[repr(C)]
+struct RawArc {
+    vtable: NonNull<ListenerVTable>,
+    context: NonNull<c_void>,
+    header: NonNull<CArcHeader>,
+}
+
+#[test]
+fn retain_and_release_from_c() {
+    let listener = new_listener();
+    // SAFETY: `CArc` is `repr(C)` with this layout.
+    let raw: RawArc = unsafe { std::mem::transmute(listener.clone()) };
+    assert_eq!(listener.strong_count(), 2);
+
+    // SAFETY: The header belongs to a live `CArc` and each call is balanced.
+    unsafe {
+        let header = raw.header.as_ref();
+        (header.retain)(raw.header);
+        assert_eq!(listener.strong_count(), 3);
+        (raw.vtable.as_ref().notify)(CRef::from_raw_parts(raw.vtable, raw.context), 5);
+        (header.release)(raw.header);
+        (header.release)(raw.header);
+    }
+    assert_eq!(listener.strong_count(), 1);
+    assert_eq!(listener.received(), 5);
+}
@@ -0,0 +1,132 @@
+#![allow(missing_docs)]
+
+use crusty_traits::prelude::*;
+
+#[crusty_trait]
+trait Counter: Clone {
+    fn increment(&mut self);
+    fn count(&self) -> u32;
+}
+
+#[derive(Clone)]
+struct Simple(u32);
+
+impl Counter for Simple {
+    fn increment(&mut self) {
+        self.0 += 1;
+    }
+
+    fn count(&self) -> u32 {
+        self.0
+    }
+}
+
+#[test]
+fn cloned_objects_are_independent() {
+    let mut first = CounterVTable::new_boxed(Simple(1));
+    let mut second = first.clone();
+    first.increment();
+    second.increment();
+    second.increment();
+    assert_eq!(first.count(), 2);
+    assert_eq!(second.count(), 3);
+}
+
+#[test]
+fn clone_keeps_the_vtable() {
+    let first = CounterVTable::new_boxed(Simple(0));
+    let second = first.clone();
+    assert!(core::ptr::eq(first.get_vtable(), second.get_vtable()));
+    assert_ne!(first.as_ptr(), second.as_ptr());
+}
+
+#[test]
+fn clone_an_object_from_an_allocator() {
+    let first = CounterVTable::new_in(Global, Simple(4)).ok().unwrap();
+    let second = first.clone();
+    drop(first);
+    assert_eq!(second.count(), 4);
+}
+
+#[derive(Clone)]
+struct Borrowed<'a>(&'a u32);
+
+impl Counter for Borrowed<'_> {
+    fn increment(&mut self) {}
+
+    fn count(&self) -> u32 {
+        *self.0
+    }
+}
+
+#[test]
+fn clone_a_scoped_object() {
+    let value = 6;
+    let first = CounterVTable::new_scoped(Borrowed(&value));
+    let second = first.clone();
+    drop(first);
+    assert_eq!(second.count(), 6);
+}
+
+#[test]
+fn clone_a_borrowed_object() {
+    let value = 7;
+    let borrowed = Borrowed(&value);
+    // the clone is scoped to the borrow, see the `compile_fail` example of `CClone`
+    let cloned: CReprScoped<'_, CounterVTable> =
+        CounterVTable::clone(CounterVTable::borrow(&borrowed));
+    assert_ne!(cloned.as_ptr(), core::ptr::from_ref(&borrowed).cast());
+    assert_eq!(cloned.count(), 7);
+}
+
+#[test]
+fn clone_an_inline_object() {
+    let first: CReprInline<CounterVTable, 1> =
+        CReprInline::new(CounterVTable::create_vtable::<Simple>(), Simple(8));
+    let mut second = first.clone();
+    second.increment();
+    assert!(first.is_inline());
+    assert_eq!(first.count(), 8);
+    assert_eq!(second.count(), 9);
+}
+
+#[crusty_trait]
+trait Named: Counter + Clone {
+    fn name(&self) -> u8;
+}
+
+impl Named for Simple {
+    fn name(&self) -> u8 {
+        b's'
+    }
+}
+
+#[test]
+fn clone_a_sub_trait_object() {
+    let first = NamedVTable::new_boxed(Simple(2));
+    let second = first.clone();
+    assert_eq!(second.name(), b's');
+    assert_eq!(second.count(), 2);
+}
+
+#[crusty_trait]
+trait Holder<T>: Clone {
+    fn get(&self) -> T;
+}
+
+#[derive(Clone)]
+struct Holding<T>(T);
+
+impl<T: Clone> Holder<T> for Holding<T> {
+    fn get(&self) -> T {
+        self.0.clone()
+    }
+}
+
+#[test]
+fn clone_a_generic_object() {
+    let first = HolderVTable::new_boxed(Holding(String::from("held")));
+    let second = first.clone();
+    drop(first);
+    assert_eq!(second.get(), "held");
+}
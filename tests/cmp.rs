@@ -0,0 +1,159 @@
+#![allow(missing_docs)]
+
+use std::collections::{BTreeMap, HashMap};
+
+use crusty_traits::prelude::*;
+
+#[crusty_trait]
+trait Key: Ord + core::hash::Hash {
+    fn name(&self) -> u32;
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Id(u32);
+
+impl Key for Id {
+    fn name(&self) -> u32 {
+        self.0
+    }
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Other(u32);
+
+impl Key for Other {
+    fn name(&self) -> u32 {
+        self.0
+    }
+}
+
+#[test]
+fn objects_of_one_implementation_compare_by_value() {
+    let a = KeyVTable::new_boxed(Id(1));
+    let b = KeyVTable::new_boxed(Id(2));
+    assert!(a == KeyVTable::new_boxed(Id(1)));
+    assert!(a != b);
+    assert!(a < b);
+    assert_eq!(b.as_cref().cmp(&a.as_cref()), core::cmp::Ordering::Greater);
+}
+
+#[test]
+fn different_implementations_are_never_equal() {
+    let id = KeyVTable::new_boxed(Id(1));
+    let other = KeyVTable::new_boxed(Other(1));
+    assert!(id != other);
+    assert_eq!(id.partial_cmp(&other), Some(id.cmp(&other)));
+    assert_eq!(id.cmp(&other), other.cmp(&id).reverse());
+}
+
+#[test]
+fn objects_are_map_keys() {
+    let mut hashed = HashMap::new();
+    hashed.insert(KeyVTable::new_boxed(Id(1)), "one");
+    hashed.insert(KeyVTable::new_boxed(Other(1)), "other one");
+    hashed.insert(KeyVTable::new_boxed(Id(1)), "one again");
+    assert_eq!(hashed.len(), 2);
+    assert_eq!(hashed[&KeyVTable::new_boxed(Id(1))], "one again");
+
+    let mut ordered = BTreeMap::new();
+    for id in [3, 1, 2] {
+        ordered.insert(KeyVTable::new_boxed(Id(id)), id);
+    }
+    assert_eq!(ordered.values().copied().collect::<Vec<_>>(), [1, 2, 3]);
+    assert_eq!(ordered.keys().next().map(Key::name), Some(1));
+}
+
+#[test]
+fn equal_objects_hash_alike() {
+    use core::hash::BuildHasher;
+
+    let state = std::hash::RandomState::new();
+    let a = KeyVTable::new_boxed(Id(5));
+    let b = CArc::new(KeyVTable::create_vtable::<Id>(), Id(5));
+    assert_eq!(state.hash_one(&a), state.hash_one(Id(5)));
+    assert_eq!(state.hash_one(&a), state.hash_one(&b));
+}
+
+#[crusty_trait]
+trait Measure: PartialOrd {
+    fn value(&self) -> f64;
+}
+
+impl Measure for f64 {
+    fn value(&self) -> f64 {
+        *self
+    }
+}
+
+#[test]
+fn unordered_values_stay_unordered() {
+    let nan = MeasureVTable::new_boxed(f64::NAN);
+    let one = MeasureVTable::new_boxed(1.0);
+    assert_eq!(nan.partial_cmp(&one), None);
+    assert!(nan != MeasureVTable::new_boxed(f64::NAN));
+    assert!(one < MeasureVTable::new_boxed(2.0));
+}
+
+#[test]
+fn copies_of_a_vtable_share_the_implementation() {
+    let copy: &'static KeyVTable = Box::leak(Box::new(KeyVTable {
+        ..*KeyVTable::create_vtable::<Id>()
+    }));
+    assert!(!std::ptr::eq(copy, KeyVTable::create_vtable::<Id>()));
+    let context = std::ptr::NonNull::from(Box::leak(Box::new(Id(1)))).cast();
+    #[allow(unsafe_code)]
+    // SAFETY: The context was boxed as the `Id` the vtable was created for.
+    let copied = unsafe { CRepr::from_raw_parts(std::ptr::NonNull::from(copy), context) };
+    assert!(copied == KeyVTable::new_boxed(Id(1)));
+    assert!(copied < KeyVTable::new_boxed(Id(2)));
+}
+
+#[derive(PartialEq)]
+struct Unit;
+
+#[derive(PartialEq)]
+struct OtherUnit;
+
+#[crusty_trait]
+trait Marker: PartialEq {}
+
+impl Marker for Unit {}
+
+impl Marker for OtherUnit {}
+
+#[crusty_trait]
+trait Name: PartialEq {
+    fn size(&self) -> usize;
+}
+
+impl Name for &str {
+    fn size(&self) -> usize {
+        self.len()
+    }
+}
+
+#[test]
+fn implementations_with_identical_slots_differ() {
+    assert_ne!(
+        implementation_id::<Unit>(),
+        implementation_id::<OtherUnit>()
+    );
+    assert!(MarkerVTable::new_boxed(Unit) != MarkerVTable::new_boxed(OtherUnit));
+    assert!(MarkerVTable::new_boxed(Unit) == MarkerVTable::new_boxed(Unit));
+}
+
+#[test]
+fn implementation_ids_keep_the_type_id() {
+    let id = implementation_id::<Unit>();
+    assert_eq!(CImplementationId::from(u128::from(id)), id);
+    assert_ne!(id, implementation_id::<Option<Unit>>());
+    let c_id = CImplementationId { high: 1, low: 2 };
+    assert_eq!(u128::from(c_id), (1 << 64) | 2);
+}
+
+#[test]
+fn borrowed_implementations_need_static_lifetimes() {
+    let name: &'static str = "static";
+    assert!(NameVTable::borrow(&name) == NameVTable::borrow(&"static"));
+    assert!(NameVTable::borrow(&name) != NameVTable::borrow(&"other"));
+}
@@ -0,0 +1,78 @@
+#![allow(missing_docs)]
+
+use crusty_traits::prelude::*;
+
+#[crusty_trait]
+trait Named {
+    fn id(&self) -> u32;
+}
+
+#[crusty_trait]
+trait Counter: Named {
+    fn get(&self) -> u32;
+    fn add(&mut self, value: u32);
+}
+
+struct Tally {
+    id: u32,
+    count: u32,
+}
+
+impl Named for Tally {
+    fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+impl Counter for Tally {
+    fn get(&self) -> u32 {
+        self.count
+    }
+
+    fn add(&mut self, value: u32) {
+        self.count += value;
+    }
+}
+
+fn add_twice(counter: &mut impl Counter, value: u32) -> u32 {
+    counter.add(value);
+    counter.add(value);
+    counter.get()
+}
+
+fn describe(named: &dyn Named) -> u32 {
+    named.id()
+}
+
+/// Stands in for a C callback receiving a borrowed object.
+extern "C" fn on_tick(mut counter: CRefMut<CounterVTable>) -> u32 {
+    add_twice(&mut counter, 2);
+    counter.reborrow().add(1);
+    CounterRef::get(&counter.as_cref()) + describe(&counter)
+}
+
+#[test]
+fn crefmut_implements_the_trait() {
+    let mut tally = Tally { id: 100, count: 0 };
+    assert_eq!(on_tick(CounterVTable::borrow_mut(&mut tally)), 105);
+    assert_eq!(tally.count, 5);
+}
+
+#[test]
+fn cref_implements_the_read_only_methods() {
+    let tally = Tally { id: 7, count: 3 };
+
+    let counter = CounterVTable::borrow(&tally);
+    assert_eq!(counter.get(), 3);
+    assert_eq!(counter.id(), 7);
+
+    let named = NamedVTable::borrow(&tally);
+    assert_eq!(describe(&named), 7);
+}
+
+#[test]
+fn crefmut_from_a_crepr() {
+    let mut counter = CounterVTable::new_boxed(Tally { id: 1, count: 0 });
+    assert_eq!(add_twice(&mut counter.as_cref_mut(), 4), 8);
+    assert_eq!(counter.get(), 8);
+}
@@ -0,0 +1,96 @@
+#![allow(missing_docs, unsafe_code)]
+
+use core::{fmt, ptr::NonNull};
+use crusty_traits::prelude::*;
+
+#[crusty_trait]
+trait Plugin: fmt::Debug + fmt::Display {
+    fn id(&self) -> u32;
+}
+
+#[derive(Debug)]
+struct Logger {
+    level: u8,
+}
+
+impl fmt::Display for Logger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            write!(f, "logger at level {}", self.level)
+        } else {
+            write!(f, "logger")
+        }
+    }
+}
+
+impl Plugin for Logger {
+    fn id(&self) -> u32 {
+        7
+    }
+}
+
+#[test]
+fn wrappers_forward_formatting() {
+    let mut plugin = PluginVTable::new_boxed(Logger { level: 2 });
+    assert_eq!(format!("{plugin:?}"), "Logger { level: 2 }");
+    assert_eq!(format!("{plugin}"), "logger");
+    assert_eq!(format!("{plugin:#}"), "logger at level 2");
+    assert_eq!(format!("{:?}", plugin.as_cref()), "Logger { level: 2 }");
+    assert_eq!(format!("{}", plugin.as_cref_mut()), "logger");
+    assert_eq!(plugin.id(), 7);
+}
+
+#[test]
+fn alternate_debug_is_forwarded() {
+    let plugin = PluginVTable::new_boxed(Logger { level: 2 });
+    assert_eq!(
+        format!("{plugin:#?}"),
+        format!("{:#?}", Logger { level: 2 })
+    );
+}
+
+#[test]
+fn shared_and_inline_objects_format() {
+    let shared = CArc::new(PluginVTable::create_vtable::<Logger>(), Logger { level: 1 });
+    assert_eq!(format!("{shared:?}"), "Logger { level: 1 }");
+    let inline: CReprInline<PluginVTable, 1> =
+        CReprInline::new(PluginVTable::create_vtable::<Logger>(), Logger { level: 3 });
+    assert_eq!(format!("{inline}"), "logger");
+}
+
+// A plugin implemented on the C side, which writes its own text into the sink.
+unsafe extern "C" fn c_id(_: CRef<PluginVTable>) -> u32 {
+    1
+}
+
+unsafe extern "C" fn c_debug(_: CRef<PluginVTable>, f: CFormatter<'_>) -> bool {
+    let text = b"CPlugin\xff";
+    // SAFETY: The text is valid for its length.
+    unsafe { (f.write_str)(f.context, text.as_ptr(), text.len()) }
+}
+
+unsafe extern "C" fn c_display(_: CRef<PluginVTable>, _: CFormatter<'_>) -> bool {
+    false
+}
+
+unsafe extern "C" fn c_drop(_: CRefMut<PluginVTable>) {}
+
+static C_VTABLE: PluginVTable = PluginVTable {
+    vtable_size: size_of::<PluginVTable>(),
+    id: c_id,
+    debug: c_debug,
+    display: c_display,
+    drop: c_drop,
+};
+
+#[test]
+fn c_implementers_write_their_own_text() {
+    let mut context = 0u8;
+    // SAFETY: The vtable is static and the context outlives the object.
+    let plugin = unsafe {
+        CRepr::from_raw_parts(NonNull::from(&C_VTABLE), NonNull::from(&mut context).cast())
+    };
+    assert_eq!(format!("{plugin:?}"), "CPlugin\u{fffd}");
+    let mut text = String::new();
+    assert!(fmt::write(&mut text, format_args!("{plugin}")).is_err());
+}
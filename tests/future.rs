@@ -0,0 +1,202 @@
+#![allow(missing_docs, unsafe_code)]
+
+use std::{
+    future::Future,
+    pin::pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+};
+
+use crusty_traits::prelude::*;
+use crusty_traits::types::cfuture::{CContext, CPoll, CWaker};
+
+/// Wakes the thread blocked on a future.
+struct Unpark(Thread);
+
+impl Wake for Unpark {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(value) = future.as_mut().poll(&mut cx) {
+            return value;
+        }
+        thread::park();
+    }
+}
+
+/// Returns `Pending` once, waking the task from another thread.
+#[derive(Default)]
+struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            return Poll::Ready(());
+        }
+        self.yielded = true;
+        let waker = cx.waker().clone();
+        thread::spawn(move || waker.wake());
+        Poll::Pending
+    }
+}
+
+#[crusty_trait]
+trait Store {
+    async fn get(&self, key: u32) -> u32;
+    async fn put(&mut self, key: u32, value: u32);
+    fn len(&self) -> impl Future<Output = usize>;
+}
+
+#[derive(Default)]
+struct MemoryStore {
+    values: Vec<(u32, u32)>,
+}
+
+impl Store for MemoryStore {
+    async fn get(&self, key: u32) -> u32 {
+        YieldNow::default().await;
+        self.values
+            .iter()
+            .find_map(|(k, v)| (*k == key).then_some(*v))
+            .unwrap_or_default()
+    }
+
+    async fn put(&mut self, key: u32, value: u32) {
+        YieldNow::default().await;
+        self.values.push((key, value));
+    }
+
+    fn len(&self) -> impl Future<Output = usize> {
+        let len = self.values.len();
+        async move { len }
+    }
+}
+
+#[test]
+fn async_methods_are_awaited_through_the_vtable() {
+    let mut store = StoreVTable::new_boxed(MemoryStore::default());
+    block_on(async {
+        store.put(1, 10).await;
+        store.put(2, 20).await;
+        assert_eq!(store.get(2).await, 20);
+        assert_eq!(store.len().await, 2);
+    });
+}
+
+#[test]
+fn futures_are_objects() {
+    let store = MemoryStore {
+        values: vec![(3, 30)],
+    };
+    let borrowed = StoreVTable::borrow(&store);
+    let future: CReprScoped<CFutureVTable<u32>> =
+        // SAFETY: The vtable was built for `MemoryStore`, the future borrows `store`.
+        unsafe { (borrowed.get_vtable().get)(borrowed, 3) };
+    assert_eq!(block_on(future), 30);
+
+    let ready = CFutureVTable::new_future(async { 5 });
+    assert_eq!(block_on(ready), 5);
+}
+
+#[crusty_trait]
+// the future can only borrow the object, so the other arguments are owned
+trait Index {
+    async fn count(&self, initial: u8) -> usize;
+}
+
+struct Words(Vec<&'static str>);
+
+impl Index for Words {
+    async fn count(&self, initial: u8) -> usize {
+        YieldNow::default().await;
+        self.0
+            .iter()
+            .filter(|word| word.as_bytes().first() == Some(&initial))
+            .count()
+    }
+}
+
+#[test]
+fn async_methods_with_arguments() {
+    let index = IndexVTable::new_boxed(Words(vec!["apple", "apricot", "banana"]));
+    assert_eq!(block_on(index.count(b'a')), 2);
+    assert_eq!(block_on(index.count(b'c')), 0);
+}
+
+#[crusty_trait]
+trait Source<T> {
+    async fn next(&mut self) -> T;
+}
+
+struct Counter(u64);
+
+impl Source<u64> for Counter {
+    async fn next(&mut self) -> u64 {
+        self.0 += 1;
+        self.0
+    }
+}
+
+#[test]
+fn generic_async_methods() {
+    let mut source = SourceVTable::new_boxed(Counter(0));
+    assert_eq!(block_on(source.next()), 1);
+    assert_eq!(block_on(source.next()), 2);
+}
+
+// A future implemented on the C side, which wakes its task once before it is ready.
+unsafe extern "C" fn c_poll(this: CRefMut<CFutureVTable<u32>>, cx: CContext<'_>) -> CPoll<u32> {
+    // SAFETY: The context is the `Mutex<bool>` created by the test.
+    let polled = unsafe { &*this.as_ptr().cast::<Mutex<bool>>() };
+    let mut polled = polled.lock().unwrap();
+    if *polled {
+        return CPoll::Ready(42);
+    }
+    *polled = true;
+    let waker: CWaker = cx.waker().clone();
+    thread::spawn(move || waker.wake());
+    CPoll::Pending
+}
+
+unsafe extern "C" fn c_drop(_: CRefMut<CFutureVTable<u32>>) {}
+
+static C_VTABLE: CFutureVTable<u32> = CFutureVTable {
+    vtable_size: size_of::<CFutureVTable<u32>>(),
+    poll: c_poll,
+    drop: c_drop,
+};
+
+#[test]
+fn c_futures_wake_the_host() {
+    let polled = Mutex::new(false);
+    // SAFETY: The vtable is static and the context outlives the future.
+    let future = unsafe {
+        CRepr::from_raw_parts(
+            std::ptr::NonNull::from(&C_VTABLE),
+            std::ptr::NonNull::from(&polled).cast(),
+        )
+    };
+    assert_eq!(block_on(future), 42);
+}
+
+#[test]
+fn wakers_round_trip() {
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let c_waker = CWaker::from(waker.clone());
+    assert!(Waker::from(c_waker.clone()).will_wake(&waker));
+    let context = CContext::new(&c_waker);
+    context.with_context(|cx| assert!(cx.waker().will_wake(&waker)));
+    c_waker.wake();
+}
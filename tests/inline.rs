@@ -0,0 +1,91 @@
+#![allow(missing_docs)]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crusty_traits::prelude::*;
+
+#[crusty_trait]
+trait Shape {
+    fn area(&self) -> u64;
+}
+
+#[crusty_trait]
+trait Scale: Shape {
+    fn scale(&mut self, factor: u64);
+}
+
+static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+struct Unit;
+
+impl Shape for Unit {
+    fn area(&self) -> u64 {
+        1
+    }
+}
+
+struct Square(u64);
+
+impl Shape for Square {
+    fn area(&self) -> u64 {
+        self.0 * self.0
+    }
+}
+
+impl Scale for Square {
+    fn scale(&mut self, factor: u64) {
+        self.0 *= factor;
+    }
+}
+
+impl Drop for Square {
+    fn drop(&mut self) {
+        DROPPED.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+struct Polygon([u64; 8]);
+
+impl Shape for Polygon {
+    fn area(&self) -> u64 {
+        self.0.iter().sum()
+    }
+}
+
+#[test]
+fn zero_sized_implementer_is_inline() {
+    let unit = CReprInline::<ShapeVTable, 0>::new(ShapeVTable::create_vtable::<Unit>(), Unit);
+    assert!(unit.is_inline());
+    assert_eq!(unit.area(), 1);
+}
+
+#[test]
+fn inline_object_survives_moves() {
+    let dropped = DROPPED.load(Ordering::SeqCst);
+    let mut square = CReprInline::<ScaleVTable, 1>::new_inline(
+        ScaleVTable::create_vtable::<Square>(),
+        Square(3),
+    )
+    .ok()
+    .unwrap();
+    square.scale(2);
+
+    let mut squares = vec![square];
+    squares.reserve(16);
+    assert!(squares[0].is_inline());
+    assert_eq!(squares[0].area(), 36);
+
+    drop(squares);
+    assert!(DROPPED.load(Ordering::SeqCst) > dropped);
+}
+
+#[test]
+fn large_implementer_is_boxed() {
+    let vtable = ShapeVTable::create_vtable::<Polygon>();
+    assert!(!CReprInline::<ShapeVTable, 2>::fits_inline::<Polygon>());
+    assert!(CReprInline::<ShapeVTable, 2>::new_inline(vtable, Polygon([1; 8])).is_err());
+
+    let polygon = CReprInline::<ShapeVTable, 2>::new(vtable, Polygon([2; 8]));
+    assert!(!polygon.is_inline());
+    assert_eq!(polygon.area(), 16);
+}
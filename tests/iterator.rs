@@ -0,0 +1,189 @@
+#![allow(missing_docs, unsafe_code)]
+
+use crusty_traits::prelude::*;
+use crusty_traits::types::citerator::CSizeHint;
+use crusty_traits::types::coption::COption;
+
+#[crusty_trait]
+trait Catalog {
+    fn names(&self) -> impl Iterator<Item = String>;
+    fn ids(&self) -> Box<dyn Iterator<Item = u32>>;
+    fn drain(&mut self) -> impl Iterator<Item = u32> + '_;
+}
+
+struct Shelf {
+    names: Vec<&'static str>,
+    ids: Vec<u32>,
+}
+
+impl Catalog for Shelf {
+    fn names(&self) -> impl Iterator<Item = String> {
+        self.names.iter().map(|name| name.to_uppercase())
+    }
+
+    fn ids(&self) -> Box<dyn Iterator<Item = u32>> {
+        Box::new(self.ids.clone().into_iter())
+    }
+
+    fn drain(&mut self) -> impl Iterator<Item = u32> + '_ {
+        self.ids.drain(..)
+    }
+}
+
+fn shelf() -> Shelf {
+    Shelf {
+        names: vec!["a", "b"],
+        ids: vec![1, 2, 3],
+    }
+}
+
+#[test]
+fn iterators_are_returned_through_the_vtable() {
+    let mut catalog = CatalogVTable::new_boxed(shelf());
+    assert_eq!(catalog.names().collect::<Vec<_>>(), ["A", "B"]);
+    let ids = catalog.ids();
+    assert_eq!(ids.size_hint(), (3, Some(3)));
+    assert_eq!(catalog.drain().sum::<u32>(), 6);
+    assert_eq!(catalog.ids().count(), 0);
+    // the boxed iterator owns its items
+    assert_eq!(ids.collect::<Vec<_>>(), [1, 2, 3]);
+}
+
+#[test]
+fn iterators_are_objects() {
+    let shelf = shelf();
+    let borrowed = CatalogVTable::borrow(&shelf);
+    let mut names: CReprScoped<CIteratorVTable<String>> =
+        // SAFETY: The vtable was built for `Shelf`, the iterator borrows `shelf`.
+        unsafe { (borrowed.get_vtable().names)(borrowed) };
+    assert_eq!(names.next().as_deref(), Some("A"));
+    // SAFETY: The vtable belongs to the iterator.
+    let hint = unsafe { (names.get_vtable().size_hint)(names.as_cref()) };
+    assert_eq!(
+        hint,
+        CSizeHint {
+            lower: 1,
+            upper: COption::Some(1)
+        }
+    );
+
+    let evens = CIteratorVTable::new_iter((0..10).filter(|i| i % 2 == 0));
+    assert_eq!(evens.collect::<Vec<_>>(), [0, 2, 4, 6, 8]);
+}
+
+#[crusty_trait]
+// the items of a `'static` boxed iterator would need `T: 'static`
+trait Generator<T> {
+    fn take(&self, count: usize) -> Box<dyn Iterator<Item = T> + '_>;
+}
+
+struct Repeat<T>(T);
+
+impl<T: Clone + 'static> Generator<T> for Repeat<T> {
+    fn take(&self, count: usize) -> Box<dyn Iterator<Item = T> + '_> {
+        Box::new(std::iter::repeat_n(self.0.clone(), count))
+    }
+}
+
+#[test]
+fn generic_iterators() {
+    let generator = GeneratorVTable::new_boxed(Repeat('x'));
+    assert_eq!(generator.take(3).collect::<String>(), "xxx");
+}
+
+#[crusty_trait]
+trait Pages {
+    fn pages<'a>(&'a self) -> Box<dyn Iterator<Item = &'static str> + 'a>;
+}
+
+struct Book(Vec<&'static str>);
+
+impl Pages for Book {
+    fn pages<'a>(&'a self) -> Box<dyn Iterator<Item = &'static str> + 'a> {
+        Box::new(self.0.iter().copied())
+    }
+}
+
+#[test]
+fn iterators_borrowing_a_named_lifetime() {
+    let book = PagesVTable::new_boxed(Book(vec!["one", "two"]));
+    assert_eq!(book.pages().collect::<Vec<_>>(), ["one", "two"]);
+    let book = Book(vec!["three"]);
+    assert_eq!(PagesVTable::borrow(&book).pages().count(), 1);
+}
+
+#[crusty_trait]
+// a borrowing iterator can only borrow the object, but a `'static` one may take references
+trait Words {
+    fn starting_with(&self, initial: u8) -> impl Iterator<Item = String>;
+    fn pick(&self, indices: &[usize; 2]) -> Box<dyn Iterator<Item = String>>;
+}
+
+struct Dictionary(Vec<&'static str>);
+
+impl Words for Dictionary {
+    fn starting_with(&self, initial: u8) -> impl Iterator<Item = String> {
+        self.0
+            .iter()
+            .filter(move |word| word.as_bytes().first() == Some(&initial))
+            .map(ToString::to_string)
+    }
+
+    fn pick(&self, indices: &[usize; 2]) -> Box<dyn Iterator<Item = String>> {
+        let words = indices.map(|i| self.0[i].to_string());
+        Box::new(words.into_iter())
+    }
+}
+
+#[test]
+fn iterators_with_arguments() {
+    let words = WordsVTable::new_boxed(Dictionary(vec!["apple", "banana", "avocado"]));
+    assert_eq!(
+        words.starting_with(b'a').collect::<Vec<_>>(),
+        ["apple", "avocado"]
+    );
+    let indices = vec![[2, 0]];
+    let picked = words.pick(&indices[0]);
+    drop(indices);
+    assert_eq!(picked.collect::<Vec<_>>(), ["avocado", "apple"]);
+}
+
+// An iterator implemented on the C side, counting down from its context.
+unsafe extern "C" fn c_next(this: CRefMut<CIteratorVTable<u32>>) -> COption<u32> {
+    // SAFETY: The context is the `u32` created by the test.
+    let left = unsafe { &mut *this.as_mut_ptr().cast::<u32>() };
+    if *left == 0 {
+        return COption::None;
+    }
+    *left -= 1;
+    COption::Some(*left)
+}
+
+unsafe extern "C" fn c_size_hint(this: CRef<CIteratorVTable<u32>>) -> CSizeHint {
+    // SAFETY: The context is the `u32` created by the test.
+    let left = unsafe { *this.as_ptr().cast::<u32>() } as usize;
+    (left, Some(left)).into()
+}
+
+unsafe extern "C" fn c_drop(_: CRefMut<CIteratorVTable<u32>>) {}
+
+static C_VTABLE: CIteratorVTable<u32> = CIteratorVTable {
+    vtable_size: size_of::<CIteratorVTable<u32>>(),
+    next: c_next,
+    size_hint: c_size_hint,
+    drop: c_drop,
+};
+
+#[test]
+fn c_iterators() {
+    let mut left = 3u32;
+    // SAFETY: The vtable is static and the context outlives the iterator.
+    let iter = unsafe {
+        CRepr::from_raw_parts(
+            std::ptr::NonNull::from(&C_VTABLE),
+            std::ptr::NonNull::from(&mut left).cast(),
+        )
+    };
+    assert_eq!(iter.size_hint(), (3, Some(3)));
+    assert_eq!(iter.collect::<Vec<_>>(), [2, 1, 0]);
+}
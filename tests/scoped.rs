@@ -0,0 +1,69 @@
+#![allow(missing_docs)]
+
+use crusty_traits::prelude::*;
+
+#[crusty_trait]
+trait Parser {
+    fn next_byte(&mut self) -> u32;
+    fn remaining(&self) -> usize;
+}
+
+struct SliceParser<'a> {
+    input: &'a [u8],
+}
+
+impl Parser for SliceParser<'_> {
+    fn next_byte(&mut self) -> u32 {
+        match self.input.split_first() {
+            Some((first, rest)) => {
+                self.input = rest;
+                u32::from(*first)
+            }
+            None => u32::MAX,
+        }
+    }
+
+    fn remaining(&self) -> usize {
+        self.input.len()
+    }
+}
+
+fn sum(parser: &mut impl Parser) -> u32 {
+    let mut total = 0;
+    while parser.remaining() > 0 {
+        total += parser.next_byte();
+    }
+    total
+}
+
+#[test]
+fn parser_over_borrowed_input() {
+    let input = vec![1, 2, 3, 4];
+    let mut parser = ParserVTable::new_scoped(SliceParser { input: &input });
+    assert_eq!(parser.remaining(), 4);
+    assert_eq!(sum(&mut parser), 10);
+    drop(parser);
+    drop(input);
+}
+
+#[test]
+fn scoped_in_an_allocator() {
+    let input = [5, 6];
+    let mut parser = CReprScoped::new_in(
+        ParserVTable::create_vtable::<SliceParser<'_>>(),
+        Global,
+        SliceParser { input: &input },
+    )
+    .ok()
+    .unwrap();
+    assert_eq!(parser.next_byte(), 5);
+    assert_eq!(parser.remaining(), 1);
+}
+
+#[test]
+fn static_objects_convert_to_scoped() {
+    static INPUT: [u8; 2] = [7, 8];
+    let repr = ParserVTable::new_boxed(SliceParser { input: &INPUT });
+    let mut scoped: CReprScoped<'_, ParserVTable> = repr.into();
+    assert_eq!(sum(&mut scoped), 15);
+}
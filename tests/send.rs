@@ -0,0 +1,113 @@
+#![allow(missing_docs)]
+
+use core::marker::PhantomData;
+use std::rc::Rc;
+
+use crusty_traits::prelude::*;
+
+// `Check::<T>::SEND` picks the inherent constant when `T: Send` and falls back to the trait's
+// constant otherwise.
+struct Check<T: ?Sized>(PhantomData<T>);
+
+trait Fallback {
+    const SEND: bool = false;
+    const SYNC: bool = false;
+}
+
+impl<T: ?Sized> Fallback for Check<T> {}
+
+#[allow(dead_code)]
+impl<T: ?Sized + Send> Check<T> {
+    const SEND: bool = true;
+}
+
+struct SyncCheck<T: ?Sized>(PhantomData<T>);
+
+impl<T: ?Sized> Fallback for SyncCheck<T> {}
+
+#[allow(dead_code)]
+impl<T: ?Sized + Sync> SyncCheck<T> {
+    const SYNC: bool = true;
+}
+
+#[crusty_trait]
+trait Local {
+    fn get(&self) -> u32;
+}
+
+impl Local for Rc<u32> {
+    fn get(&self) -> u32 {
+        **self
+    }
+}
+
+#[crusty_trait]
+trait Sendable: Send {
+    fn get(&self) -> u32;
+}
+
+impl Sendable for u32 {
+    fn get(&self) -> u32 {
+        *self
+    }
+}
+
+#[crusty_trait]
+trait Shared: Send + Sync {
+    fn get(&self) -> u32;
+}
+
+impl Shared for u32 {
+    fn get(&self) -> u32 {
+        *self
+    }
+}
+
+// objects of a trait without `Send` are neither `Send` nor `Sync`
+const _: () = {
+    assert!(!Check::<CRepr<LocalVTable>>::SEND);
+    assert!(!SyncCheck::<CRepr<LocalVTable>>::SYNC);
+    assert!(!Check::<CRef<'static, LocalVTable>>::SEND);
+    assert!(!Check::<CRefMut<'static, LocalVTable>>::SEND);
+    assert!(!Check::<CReprInline<LocalVTable, 1>>::SEND);
+    assert!(!Check::<CReprScoped<'static, LocalVTable>>::SEND);
+    assert!(!Check::<CArc<LocalVTable>>::SEND);
+};
+
+// objects of a `Send` trait can be moved, but not shared
+const _: () = {
+    assert!(Check::<CRepr<SendableVTable>>::SEND);
+    assert!(Check::<CRefMut<'static, SendableVTable>>::SEND);
+    assert!(!SyncCheck::<CRepr<SendableVTable>>::SYNC);
+    assert!(!Check::<CRef<'static, SendableVTable>>::SEND);
+    assert!(!Check::<CArc<SendableVTable>>::SEND);
+};
+
+const _: () = {
+    assert!(Check::<CRepr<SharedVTable>>::SEND);
+    assert!(SyncCheck::<CRepr<SharedVTable>>::SYNC);
+    assert!(Check::<CRef<'static, SharedVTable>>::SEND);
+    assert!(Check::<CArc<SharedVTable>>::SEND);
+};
+
+#[test]
+fn local_objects_work_on_their_thread() {
+    let local = LocalVTable::new_boxed(Rc::new(1));
+    assert_eq!(local.get(), 1);
+}
+
+#[test]
+fn send_objects_move_between_threads() {
+    let sendable = SendableVTable::new_boxed(2);
+    let value = std::thread::spawn(move || sendable.get()).join().unwrap();
+    assert_eq!(value, 2);
+}
+
+#[test]
+fn sync_objects_are_shared_between_threads() {
+    let shared = SharedVTable::new_boxed(3);
+    std::thread::scope(|scope| {
+        let shared = shared.as_cref();
+        scope.spawn(move || assert_eq!(shared.get(), 3));
+    });
+}
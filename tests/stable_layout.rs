@@ -0,0 +1,145 @@
+#![allow(missing_docs, unsafe_code)]
+
+use std::{mem::offset_of, ptr::NonNull};
+
+use crusty_traits::prelude::*;
+
+#[crusty_trait]
+trait Named {
+    fn name(&self) -> u32;
+}
+
+#[crusty_trait(stable_layout(Named = 2))]
+trait Counter: Named {
+    #[crusty(slot = 1)]
+    fn increment(&mut self);
+    #[crusty(slot = 0)]
+    fn get(&self) -> u32;
+    #[crusty(slot = 3, since = 2)]
+    fn reset(&mut self) {}
+}
+
+#[derive(Clone)]
+struct Count(u32);
+
+impl Named for Count {
+    fn name(&self) -> u32 {
+        42
+    }
+}
+
+impl Counter for Count {
+    fn increment(&mut self) {
+        self.0 += 1;
+    }
+
+    fn get(&self) -> u32 {
+        self.0
+    }
+
+    fn reset(&mut self) {
+        self.0 = 0;
+    }
+}
+
+#[test]
+fn stable_layout_field_order() {
+    let pointer = size_of::<usize>();
+    assert_eq!(offset_of!(CounterVTable, vtable_size), 0);
+    assert_eq!(offset_of!(CounterVTable, drop), pointer);
+    assert_eq!(offset_of!(CounterVTable, get), 2 * pointer);
+    assert_eq!(offset_of!(CounterVTable, increment), 3 * pointer);
+    assert_eq!(offset_of!(CounterVTable, field_named), 4 * pointer);
+    assert_eq!(offset_of!(CounterVTable, reset), 5 * pointer);
+}
+
+// an earlier version of `CounterV2`, before `Named` and `Clone` were added as super traits
+#[crusty_trait(stable_layout)]
+trait CounterV1 {
+    #[crusty(slot = 1)]
+    fn increment(&mut self);
+    #[crusty(slot = 0)]
+    fn get(&self) -> u32;
+}
+
+#[crusty_trait(stable_layout(Named = 2, Clone = 3))]
+trait CounterV2: Named + Clone {
+    #[crusty(slot = 1)]
+    fn increment(&mut self);
+    #[crusty(slot = 0)]
+    fn get(&self) -> u32;
+    #[crusty(slot = 4, since = 2)]
+    fn reset(&mut self) {}
+}
+
+impl CounterV2 for Count {
+    fn increment(&mut self) {
+        self.0 += 1;
+    }
+
+    fn get(&self) -> u32 {
+        self.0
+    }
+
+    fn reset(&mut self) {
+        self.0 = 0;
+    }
+}
+
+#[test]
+fn adding_super_traits_keeps_the_slots() {
+    assert_eq!(
+        offset_of!(CounterV1VTable, drop),
+        offset_of!(CounterV2VTable, drop)
+    );
+    assert_eq!(
+        offset_of!(CounterV1VTable, get),
+        offset_of!(CounterV2VTable, get)
+    );
+    assert_eq!(
+        offset_of!(CounterV1VTable, increment),
+        offset_of!(CounterV2VTable, increment)
+    );
+}
+
+#[test]
+fn adding_methods_keeps_the_super_traits() {
+    // `reset` was added after the super traits, so it follows their slots
+    assert!(offset_of!(CounterV2VTable, field_named) < offset_of!(CounterV2VTable, reset));
+    assert!(offset_of!(CounterV2VTable, clone) < offset_of!(CounterV2VTable, reset));
+}
+
+/// Simulates a counter built against version 1 of `CounterV2`, whose vtable ends before `reset`.
+fn old_counter(count: u32) -> CRepr<CounterV2VTable> {
+    let vtable: &'static CounterV2VTable = Box::leak(Box::new(CounterV2VTable {
+        vtable_size: offset_of!(CounterV2VTable, reset),
+        ..*CounterV2VTable::create_vtable::<Count>()
+    }));
+    let context = NonNull::from(Box::leak(Box::new(Count(count)))).cast();
+    // SAFETY: The context was boxed as the `Count` the vtable was created for.
+    unsafe { CRepr::from_raw_parts(NonNull::from(vtable), context) }
+}
+
+#[test]
+fn old_vtables_upcast_and_clone() {
+    let mut counter = old_counter(1);
+    counter.increment();
+    assert_eq!(counter.name(), 42);
+    let mut clone = counter.clone();
+    assert_eq!(clone.get(), 2);
+    assert_eq!(clone.name(), 42);
+    // the old vtable has no `reset`, so its default body runs
+    clone.reset();
+    assert_eq!(clone.get(), 2);
+}
+
+#[test]
+fn stable_layout_calls() {
+    let mut counter = CounterVTable::new_boxed(Count(0));
+    counter.increment();
+    counter.increment();
+    assert_eq!(counter.get(), 2);
+    assert_eq!(counter.name(), 42);
+    counter.reset();
+    assert_eq!(counter.get(), 0);
+}
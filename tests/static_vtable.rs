@@ -0,0 +1,51 @@
+#![allow(missing_docs)]
+
+use crusty_traits::prelude::*;
+
+#[crusty_trait]
+trait Shape {
+    fn area(&self) -> u32;
+}
+
+#[crusty_trait]
+trait Square: Shape {
+    fn side(&self) -> u32;
+}
+
+struct Tile(u32);
+
+impl Shape for Tile {
+    fn area(&self) -> u32 {
+        self.0 * self.0
+    }
+}
+
+impl Square for Tile {
+    fn side(&self) -> u32 {
+        self.0
+    }
+}
+
+struct Borrowed<'a>(&'a u32);
+
+impl Shape for Borrowed<'_> {
+    fn area(&self) -> u32 {
+        *self.0
+    }
+}
+
+static TILE_VTABLE: &SquareVTable = SquareVTable::create_vtable::<Tile>();
+
+#[test]
+fn vtable_in_static_initializer() {
+    assert_eq!(TILE_VTABLE.vtable_size, size_of::<SquareVTable>());
+    let tile = CRepr::new_boxed(TILE_VTABLE, Tile(3));
+    assert_eq!(tile.side(), 3);
+    assert_eq!(tile.area(), 9);
+}
+
+#[test]
+fn vtable_for_non_static_implementer() {
+    let vtable = ShapeVTable::create_vtable::<Borrowed<'_>>();
+    assert_eq!(vtable.vtable_size, size_of::<ShapeVTable>());
+}
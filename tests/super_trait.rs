@@ -23,7 +23,7 @@ impl SuperBuffer for Vec<u8> {
 
 impl Buffer for Vec<u8> {
     fn as_slice(&self) -> *mut u8 {
-        self.as_ptr() as *mut u8
+        self.as_ptr().cast_mut()
     }
 
     fn extend(&mut self, amount: usize) {
@@ -0,0 +1,76 @@
+#![allow(missing_docs, unsafe_code)]
+
+use std::ptr::NonNull;
+
+use crusty_traits::prelude::*;
+
+#[crusty_trait]
+trait Plugin {
+    fn id(&self) -> u32;
+    #[crusty(since = 3)]
+    fn reset(&mut self) {}
+    #[crusty(since = 2)]
+    fn priority(&self) -> u32 {
+        1
+    }
+}
+
+struct NewPlugin(u32);
+
+impl Plugin for NewPlugin {
+    fn id(&self) -> u32 {
+        self.0
+    }
+
+    fn reset(&mut self) {
+        self.0 = 0;
+    }
+
+    fn priority(&self) -> u32 {
+        10
+    }
+}
+
+/// Simulates a plugin built against version 1 of the trait, whose vtable ends after `drop`.
+fn old_plugin(id: u32) -> CRepr<PluginVTable> {
+    let vtable: &'static PluginVTable = Box::leak(Box::new(PluginVTable {
+        vtable_size: std::mem::offset_of!(PluginVTable, priority),
+        ..*PluginVTable::create_vtable::<NewPlugin>()
+    }));
+    let context = NonNull::from(Box::leak(Box::new(NewPlugin(id)))).cast();
+    // SAFETY: The context was boxed as the `NewPlugin` the vtable was created for.
+    unsafe { CRepr::from_raw_parts(NonNull::from(vtable), context) }
+}
+
+#[test]
+fn since_slots_are_appended() {
+    assert!(
+        std::mem::offset_of!(PluginVTable, drop) < std::mem::offset_of!(PluginVTable, priority)
+    );
+    assert!(
+        std::mem::offset_of!(PluginVTable, priority) < std::mem::offset_of!(PluginVTable, reset)
+    );
+}
+
+#[test]
+fn new_vtable_calls_since_methods() {
+    let mut plugin = PluginVTable::new_boxed(NewPlugin(7));
+    assert_eq!(plugin.get_vtable().vtable_size, size_of::<PluginVTable>());
+    assert_eq!(plugin.priority(), 10);
+    plugin.reset();
+    assert_eq!(plugin.id(), 0);
+}
+
+#[test]
+fn old_vtable_falls_back_to_default() {
+    let plugin = old_plugin(7);
+    assert_eq!(plugin.id(), 7);
+    assert_eq!(plugin.priority(), 1);
+}
+
+#[test]
+fn old_vtable_skips_later_methods() {
+    let mut plugin = old_plugin(7);
+    plugin.reset();
+    assert_eq!(plugin.id(), 7);
+}